@@ -3,9 +3,20 @@ use crate::backend::{start_backend, ServerCommand, ServerResponse};
 use crate::user::User;
 use eframe::{egui, App, Frame};
 use egui::{Frame as UiFrame, RichText, Color32, Margin};
-use crate::group::Group;
-use crate::expenses::Expenses;
-use crate::notification::Notification;
+use crate::group::{Group, GroupActivity, GroupDetail, GroupRole};
+use crate::invitation::{DebtTransfer, Invitation, INVITE_CODE_VALID_DAYS};
+use qrcodegen::{QrCode, QrCodeEcc};
+use crate::expenses::{Expense, Expenses};
+use crate::delivery::Channel;
+use crate::notification::{format_reminder_days, parse_reminder_days, Notification, NotificationKind, NotificationPreferences};
+use crate::money::Money;
+use crate::split::{parse_percentage, Split, SplitMode};
+use crate::settlement::{Balance, Transfer};
+use crate::payment::{Payment, PaymentMethod};
+use crate::scheduler::Alert;
+use crate::recurring::{RecurrenceInterval, RecurringExpense, RecurringStatus};
+use crate::currency::{format_amount, ExchangeRate, DEFAULT_CURRENCY, RATE_SCALE};
+use std::collections::HashMap;
 use std::sync::mpsc::TryRecvError;
 
 #[derive(Clone)]
@@ -15,9 +26,44 @@ pub enum Screen {
     MainApp(User),
     CreateGroup(i32),
     MyGroups(i32),
+    GroupDetail(i32, i32),
+    GroupMembers(i32, i32),
+    Invitations(i32),
     AddExp(i32, i32),
+    GroupExpenses(i32, i32),
+    RecurringExpenses(i32, i32),
+    GroupSettlement(i32, i32),
     MyDebtsOrCredits(i32, bool),
+    Counterparties(i32),
+    PaymentHistory(i32, Option<i32>),
+    ExchangeRates(i32),
     MyNotifications(i32),
+    NotificationSettings(i32),
+}
+
+impl Screen {
+    /// The signed-in user the screen belongs to, if anyone is signed in.
+    fn user_id(&self) -> Option<i32> {
+        match self {
+            Screen::Login | Screen::Register => None,
+            Screen::MainApp(user) => Some(user.id()),
+            Screen::CreateGroup(user_id)
+            | Screen::MyGroups(user_id)
+            | Screen::GroupDetail(user_id, _)
+            | Screen::GroupMembers(user_id, _)
+            | Screen::Invitations(user_id)
+            | Screen::AddExp(user_id, _)
+            | Screen::GroupExpenses(user_id, _)
+            | Screen::RecurringExpenses(user_id, _)
+            | Screen::GroupSettlement(user_id, _)
+            | Screen::MyDebtsOrCredits(user_id, _)
+            | Screen::Counterparties(user_id)
+            | Screen::PaymentHistory(user_id, _)
+            | Screen::ExchangeRates(user_id)
+            | Screen::MyNotifications(user_id)
+            | Screen::NotificationSettings(user_id) => Some(*user_id),
+        }
+    }
 }
 
 pub enum Action {
@@ -26,99 +72,119 @@ pub enum Action {
     Register,
     CreateGroup(i32),
     MyGroups(i32),
+    GroupDetail(i32, i32),
+    GroupMembers(i32, i32),
+    Invitations(i32),
     AddExp(i32, i32),
+    GroupExpenses(i32, i32),
+    RecurringExpenses(i32, i32),
+    GroupSettlement(i32, i32),
     MyDebtsOrCredits(i32, bool),
+    Counterparties(i32),
+    PaymentHistory(i32, Option<i32>),
+    ExchangeRates(i32),
     MyNotifications(i32),
+    NotificationSettings(i32),
 }
 
+#[derive(Default)]
 pub struct LoginState {
     login_email: String,
     login_password: String,
 }
 
-impl Default for LoginState {
-    fn default() -> Self {
-        Self {
-            login_email: String::new(),
-            login_password: String::new(),
-        }
-    }
-}
-
+#[derive(Default)]
 pub struct RegistrationState {
     reg_username: String,
     reg_email: String,
     reg_password: String,
 }
-impl Default for RegistrationState {
-    fn default() -> Self {
-        Self {
-            reg_username: String::new(),
-            reg_email: String::new(),
-            reg_password: String::new(),
-        }
-
-    }
-}
 
+#[derive(Default)]
 pub struct GroupState {
     group_name: String,
+    group_currency: String,
     search_query: String,
     search_results: Vec<User>,
     selected_users: Vec<i32>,
     group_loading: bool,
     my_groups: Vec<Group>,
+    members_loading: bool,
+    member_roles: Vec<(i32, GroupRole)>,
+    detail: Option<GroupDetail>,
+    detail_loading: bool,
+    show_archived_groups: bool,
+    rename_to: String,
+    transfer_to: Option<i32>,
+    invitations: Vec<Invitation>,
+    debt_transfers: Vec<DebtTransfer>,
+    invitations_loading: bool,
+    invite_code: Option<String>,
+    join_code: String,
 }
 
-impl Default for GroupState {
-    fn default() -> Self {
-        Self {
-            group_name: String::new(),
-            search_query: String::new(),
-            search_results: Vec::new(),
-            selected_users: Vec::new(),
-            group_loading: false,
-            my_groups: Vec::new(),
-        }
-    }
-}
-
+#[derive(Default)]
 pub struct ExpensesState {
-    exp_amount: f32,
+    exp_amount: String,
     exp_description: String,
     exp_due_date: String,
+    exp_currency: String,
+    exp_split_mode: SplitMode,
+    exp_split_values: HashMap<i32, String>,
+    exp_participants: Vec<i32>,
+    exp_editing: Option<i32>,
+    exp_recurring: bool,
+    exp_interval: RecurrenceInterval,
+    exp_end_date: String,
+    exp_members: Vec<User>,
+    members_loading: bool,
+    group_expenses: Vec<Expense>,
+    group_expenses_loading: bool,
+    recurring: Vec<RecurringExpense>,
+    recurring_loading: bool,
     my_debts_or_credits: Vec<Expenses>,
     debts_or_credits_loading: bool,
+    payment_amounts: HashMap<i32, String>,
+    payment_dates: HashMap<i32, String>,
+    payment_methods: HashMap<i32, PaymentMethod>,
 }
 
-impl Default for ExpensesState {
-    fn default() -> Self {
-        Self {
-            exp_amount: 0.0,
-            exp_description: String::new(),
-            exp_due_date: String::new(),
-            my_debts_or_credits: Vec::new(),
-            debts_or_credits_loading: false,
-        }
-    }
+#[derive(Default)]
+pub struct SettlementState {
+    balances: Vec<Balance>,
+    transfers: Vec<Transfer>,
+    settlement_loading: bool,
+    counterparties: Vec<Balance>,
+    counterparties_loading: bool,
+    payment_history: Vec<Payment>,
+    payment_history_loading: bool,
+    group_currency: String,
+    rates: Vec<ExchangeRate>,
+    rates_loading: bool,
+    rates_path: String,
 }
 
+#[derive(Default)]
 pub struct NotificationState {
     notifications: Vec<Notification>,
     notification_loading: bool,
+    show_archived: bool,
+    unread_count: i64,
+    unread_loading: bool,
+    reminder_days: String,
+    reminder_loading: bool,
+    channels: Vec<(Channel, bool, String)>,
+    channels_loading: bool,
+    muted_kinds: Vec<NotificationKind>,
+    muted_groups: Vec<i32>,
+    quiet_hours: bool,
+    quiet_start: String,
+    quiet_end: String,
+    digest: bool,
+    digest_time: String,
+    preferences_loading: bool,
 }
 
-impl Default for NotificationState {
-    fn default() -> Self {
-        Self {
-            notifications: Vec::new(),
-            notification_loading: false,
-        }
-    }
-}
-
-
-
 pub struct MyApp {
     tx_cmd: Sender<ServerCommand>,
     rx_resp: Receiver<ServerResponse>,
@@ -128,6 +194,8 @@ pub struct MyApp {
     group_state: GroupState,
     expenses: ExpensesState,
     notifications_state: NotificationState,
+    settlement: SettlementState,
+    pending_alerts: Vec<Alert>,
     loading: bool,
     success_message: Option<String>,
     success_time: Option<std::time::Instant>,
@@ -148,6 +216,8 @@ impl Default for MyApp {
             group_state: GroupState::default(),
             expenses: ExpensesState::default(),
             notifications_state: NotificationState::default(),
+            settlement: SettlementState::default(),
+            pending_alerts: Vec::new(),
             loading: false,
             success_message: None,
             success_time: None,
@@ -160,6 +230,7 @@ impl Default for MyApp {
 impl App for MyApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
         self.process_backend_responses(ctx);
+        self.show_alerts();
 
         let action = {
             match &self.screen {
@@ -168,11 +239,21 @@ impl App for MyApp {
                 Screen::Register => Action::Register,
                 Screen::CreateGroup(user_id) => Action::CreateGroup(*user_id),
                 Screen::MyGroups(user_id) => Action::MyGroups(*user_id),
+                Screen::GroupDetail(user_id, group_id) => Action::GroupDetail(*user_id, *group_id),
+                Screen::GroupMembers(user_id, group_id) => Action::GroupMembers(*user_id, *group_id),
+                Screen::Invitations(user_id) => Action::Invitations(*user_id),
                 Screen::AddExp(user_id, group_id) => Action::AddExp(*user_id, *group_id),
+                Screen::GroupExpenses(user_id, group_id) => Action::GroupExpenses(*user_id, *group_id),
+                Screen::RecurringExpenses(user_id, group_id) => Action::RecurringExpenses(*user_id, *group_id),
+                Screen::GroupSettlement(user_id, group_id) => Action::GroupSettlement(*user_id, *group_id),
                 Screen::MyDebtsOrCredits(user_id, is_debt) => {
                     Action::MyDebtsOrCredits(*user_id, *is_debt)
                 }
+                Screen::Counterparties(user_id) => Action::Counterparties(*user_id),
+                Screen::PaymentHistory(user_id, group_id) => Action::PaymentHistory(*user_id, *group_id),
+                Screen::ExchangeRates(user_id) => Action::ExchangeRates(*user_id),
                 Screen::MyNotifications(user_id) => Action::MyNotifications(*user_id),
+                Screen::NotificationSettings(user_id) => Action::NotificationSettings(*user_id),
             }
         };
 
@@ -182,11 +263,21 @@ impl App for MyApp {
             Action::Register => self.show_register(ctx),
             Action::CreateGroup(user_id) => self.show_create_group(ctx, user_id),
             Action::MyGroups(user_id) => self.show_my_groups(ctx, user_id),
+            Action::GroupDetail(user_id, group_id) => self.show_group_detail(ctx, user_id, group_id),
+            Action::GroupMembers(user_id, group_id) => self.show_group_members(ctx, user_id, group_id),
+            Action::Invitations(user_id) => self.show_invitations(ctx, user_id),
             Action::AddExp(user_id, group_id) => self.show_add_expenses(ctx, user_id, group_id),
+            Action::GroupExpenses(user_id, group_id) => self.show_group_expenses(ctx, user_id, group_id),
+            Action::RecurringExpenses(user_id, group_id) => self.show_recurring_expenses(ctx, user_id, group_id),
+            Action::GroupSettlement(user_id, group_id) => self.show_group_settlement(ctx, user_id, group_id),
             Action::MyDebtsOrCredits(user_id, is_debt) => {
                 self.show_my_debts_or_credits(ctx, user_id, is_debt)
             }
+            Action::Counterparties(user_id) => self.show_counterparties(ctx, user_id),
+            Action::PaymentHistory(user_id, group_id) => self.show_payment_history(ctx, user_id, group_id),
+            Action::ExchangeRates(user_id) => self.show_exchange_rates(ctx, user_id),
             Action::MyNotifications(user_id) => self.show_my_notifications(ctx, user_id),
+            Action::NotificationSettings(user_id) => self.show_notification_settings(ctx, user_id),
         }
     }
 }
@@ -210,6 +301,7 @@ impl MyApp {
                         }
                         ServerResponse::User(user) => {
                             self.screen = Screen::MainApp(user);
+                            self.notifications_state.unread_loading = false;
                             self.loading = false;
                         }
                         ServerResponse::Users(users) => {
@@ -220,14 +312,99 @@ impl MyApp {
                             self.group_state.my_groups = groups;
                             self.loading = false;
                         }
+                        ServerResponse::GroupMembers(members) => {
+                            if self.expenses.exp_editing.is_none() {
+                                self.expenses.exp_participants = members.iter().map(|m| m.id()).collect();
+                            }
+                            self.expenses.exp_members = members;
+                            self.loading = false;
+                        }
+                        ServerResponse::GroupDetail(detail) => {
+                            self.group_state.detail = Some(detail);
+                            self.loading = false;
+                        }
+                        ServerResponse::GroupRoles(roles) => {
+                            self.group_state.member_roles = roles;
+                            self.loading = false;
+                        }
+                        ServerResponse::Invitations(invitations) => {
+                            self.group_state.invitations = invitations;
+                            self.loading = false;
+                        }
+                        ServerResponse::DebtTransfers(transfers) => {
+                            self.group_state.debt_transfers = transfers;
+                            self.loading = false;
+                        }
+                        ServerResponse::InviteCode(code) => {
+                            self.group_state.invite_code = Some(code);
+                            self.loading = false;
+                        }
                         ServerResponse::Expenses(expenses) => {
                             self.expenses.my_debts_or_credits = expenses;
                             self.loading = false;
                         }
+                        ServerResponse::GroupExpenses(expenses) => {
+                            self.expenses.group_expenses = expenses;
+                            self.loading = false;
+                        }
                         ServerResponse::Notifications(notifications) => {
                             self.notifications_state.notifications = notifications;
                             self.loading = false;
                         }
+                        ServerResponse::UnreadCount(count) => {
+                            self.notifications_state.unread_count = count;
+                        }
+                        ServerResponse::ReminderDays(days) => {
+                            self.notifications_state.reminder_days = format_reminder_days(&days);
+                            self.loading = false;
+                        }
+                        ServerResponse::NotificationChannels(preferences) => {
+                            self.notifications_state.channels = preferences
+                                .iter()
+                                .filter(|preference| preference.channel() != Channel::InApp)
+                                .map(|preference| (preference.channel(), preference.is_enabled(), preference.target().to_string()))
+                                .collect();
+                            self.loading = false;
+                        }
+                        ServerResponse::NotificationPreferences(preferences) => {
+                            let (quiet_start, quiet_end) = preferences.quiet_hours().unwrap_or(("22:00", "08:00"));
+                            self.notifications_state.quiet_start = quiet_start.to_string();
+                            self.notifications_state.quiet_end = quiet_end.to_string();
+                            self.notifications_state.quiet_hours = preferences.quiet_hours().is_some();
+                            self.notifications_state.muted_kinds = preferences.muted_kinds().to_vec();
+                            self.notifications_state.muted_groups = preferences.muted_groups().to_vec();
+                            self.notifications_state.digest = preferences.digest();
+                            self.notifications_state.digest_time = preferences.digest_time().to_string();
+                            self.loading = false;
+                        }
+                        ServerResponse::Settlement { balances, transfers } => {
+                            if let Some(balance) = balances.first() {
+                                self.settlement.group_currency = balance.currency().to_string();
+                            }
+                            self.settlement.balances = balances;
+                            self.settlement.transfers = transfers;
+                            self.loading = false;
+                        }
+                        ServerResponse::Counterparties(balances) => {
+                            self.settlement.counterparties = balances;
+                            self.loading = false;
+                        }
+                        ServerResponse::Payments(payments) => {
+                            self.settlement.payment_history = payments;
+                            self.loading = false;
+                        }
+                        ServerResponse::Rates(rates) => {
+                            self.settlement.rates = rates;
+                            self.loading = false;
+                        }
+                        ServerResponse::RecurringExpenses(recurring) => {
+                            self.expenses.recurring = recurring;
+                            self.loading = false;
+                        }
+                        ServerResponse::Alerts(alerts) => {
+                            self.pending_alerts.extend(alerts);
+                            self.notifications_state.unread_loading = false;
+                        }
                     }
                 }
                 Err(TryRecvError::Empty) => {
@@ -243,20 +420,39 @@ impl MyApp {
 
     }
 
+    /// Shows what the background jobs found for the signed-in user. Alerts for
+    /// other users are kept until they sign in.
+    fn show_alerts(&mut self) {
+        let Some(user_id) = self.screen.user_id() else {
+            return;
+        };
+        if !self.pending_alerts.iter().any(|alert| alert.user_id() == user_id) {
+            return;
+        }
+
+        let (mine, others): (Vec<Alert>, Vec<Alert>) = std::mem::take(&mut self.pending_alerts)
+            .into_iter()
+            .partition(|alert| alert.user_id() == user_id);
+        self.pending_alerts = others;
+
+        let messages: Vec<&str> = mine.iter().map(|alert| alert.message()).collect();
+        self.success_message = Some(messages.join("\n"));
+        self.success_time = Some(std::time::Instant::now());
+
+        self.expenses.group_expenses_loading = false;
+        self.expenses.debts_or_credits_loading = false;
+    }
+
     fn update_messages(&mut self, ctx: &egui::Context) {
 
-        if let Some(start) = self.success_time {
-            if start.elapsed().as_secs() > 3 {
-                self.success_message = None;
-                self.success_time = None;
-            }
+        if self.success_time.is_some_and(|start| start.elapsed().as_secs() > 3) {
+            self.success_message = None;
+            self.success_time = None;
         }
 
-        if let Some(start) = self.error_time {
-            if start.elapsed().as_secs() > 3 {
-                self.error_message = None;
-                self.error_time = None;
-            }
+        if self.error_time.is_some_and(|start| start.elapsed().as_secs() > 3) {
+            self.error_message = None;
+            self.error_time = None;
         }
 
         ctx.request_repaint();
@@ -386,6 +582,15 @@ impl MyApp {
     fn show_main_app(&mut self, ctx: &egui::Context, user: &User) {
         use egui::Color32;
 
+        if !self.notifications_state.unread_loading {
+            if let Err(e) = self.tx_cmd.send(ServerCommand::CountUnreadNotifications {
+                user_id: user.id(),
+            }) {
+                self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+            }
+            self.notifications_state.unread_loading = true;
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
             egui::ScrollArea::vertical()
                 .auto_shrink([false; 2])
@@ -408,6 +613,12 @@ impl MyApp {
                                 }
                                 ui.add_space(5.0);
 
+                                if ui.add_sized(button_size, egui::Button::new("Покани за групи").fill(Color32::from_rgb(0, 76, 153))).clicked() {
+                                    self.group_state.invitations_loading = false;
+                                    self.screen = Screen::Invitations(user.id());
+                                }
+                                ui.add_space(5.0);
+
                                 if ui.add_sized(button_size, egui::Button::new("Моите дългове").fill(Color32::from_rgb(0, 102, 0))).clicked() {
                                     self.screen = Screen::MyDebtsOrCredits(user.id(), true);
                                 }
@@ -418,7 +629,29 @@ impl MyApp {
                                 }
                                 ui.add_space(5.0);
 
-                                if ui.add_sized(button_size, egui::Button::new("Известия").fill(Color32::from_rgb(153, 76, 0))).clicked() {
+                                if ui.add_sized(button_size, egui::Button::new("Сметки с хора").fill(Color32::from_rgb(76, 0, 153))).clicked() {
+                                    self.settlement.counterparties_loading = false;
+                                    self.screen = Screen::Counterparties(user.id());
+                                }
+                                ui.add_space(5.0);
+
+                                if ui.add_sized(button_size, egui::Button::new("История на плащанията").fill(Color32::from_rgb(0, 102, 102))).clicked() {
+                                    self.settlement.payment_history_loading = false;
+                                    self.screen = Screen::PaymentHistory(user.id(), None);
+                                }
+                                ui.add_space(5.0);
+
+                                if ui.add_sized(button_size, egui::Button::new("Валутни курсове").fill(Color32::from_rgb(0, 76, 153))).clicked() {
+                                    self.settlement.rates_loading = false;
+                                    self.screen = Screen::ExchangeRates(user.id());
+                                }
+                                ui.add_space(5.0);
+
+                                let unread = self.notifications_state.unread_count;
+                                let label = if unread > 0 { format!("Известия ({})", unread) } else { "Известия".to_string() };
+                                if ui.add_sized(button_size, egui::Button::new(label).fill(Color32::from_rgb(153, 76, 0))).clicked() {
+                                    self.notifications_state.notification_loading = false;
+                                    self.notifications_state.show_archived = false;
                                     self.screen = Screen::MyNotifications(user.id());
                                 }
 
@@ -439,13 +672,18 @@ impl MyApp {
             egui::ScrollArea::vertical()
                 .auto_shrink([false; 2])
                 .show(ui, |ui| {
-                    ui.heading("Известия");
-                    ui.add_space(20.0);
+                    let archived = self.notifications_state.show_archived;
+                    ui.heading(if archived { "Архивирани известия" } else { "Известия" });
+                    ui.add_space(10.0);
                     if !self.notifications_state.notification_loading {
                         self.notifications_state.notifications = Vec::new();
-                        if let Err(e) = self.tx_cmd.send(ServerCommand::ShowNotification {
-                            user_id,
-                        }) {
+                        let command = if archived {
+                            ServerCommand::ShowArchivedNotifications { user_id }
+                        }
+                        else {
+                            ServerCommand::ShowNotification { user_id }
+                        };
+                        if let Err(e) = self.tx_cmd.send(command) {
                             self.error_message = Some(format!("Неуспешно изпращане: {}", e));
                         }
                         self.notifications_state.notification_loading = true;
@@ -453,18 +691,104 @@ impl MyApp {
                         self.process_backend_responses(ctx);
                     }
 
+                    let mut command = None;
+                    let mut open = None;
+
+                    ui.horizontal(|ui| {
+                        if !archived && ui.add(
+                            egui::Button::new(
+                                RichText::new("Маркирай всички като прочетени").color(Color32::WHITE)
+                            ).fill(Color32::from_rgb(0, 102, 102))
+                        ).clicked() {
+                            command = Some(ServerCommand::MarkAllNotificationsRead { user_id });
+                        }
+                        if ui.add(
+                            egui::Button::new(
+                                RichText::new(if archived { "Входящи" } else { "Архив" }).color(Color32::WHITE)
+                            ).fill(Color32::from_rgb(102, 102, 0))
+                        ).clicked() {
+                            self.notifications_state.show_archived = !archived;
+                            self.notifications_state.notification_loading = false;
+                        }
+                        if ui.add(
+                            egui::Button::new(
+                                RichText::new("Настройки").color(Color32::WHITE)
+                            ).fill(Color32::from_rgb(76, 0, 153))
+                        ).clicked() {
+                            self.notifications_state.reminder_loading = false;
+                            self.notifications_state.channels_loading = false;
+                            self.notifications_state.preferences_loading = false;
+                            self.group_state.group_loading = false;
+                            self.screen = Screen::NotificationSettings(user_id);
+                        }
+                    });
+                    ui.add_space(10.0);
+
                     for notification in &self.notifications_state.notifications {
+                        let fill = if notification.is_read() { Color32::from_rgb(90, 90, 90) } else { Color32::from_rgb(0, 102, 204) };
                         UiFrame::group(ui.style())
-                            .fill(Color32::from_rgb(0, 102, 204))
+                            .fill(fill)
                             .rounding(6.0)
                             .inner_margin(Margin::same(6.0))
                             .show(ui, |ui| {
-                                ui.label(RichText::new(notification.message())
+                                ui.label(RichText::new(format!("{} · {}", notification.kind().label(), notification.created_at()))
+                                    .small()
                                     .color(Color32::WHITE));
+                                let message = RichText::new(notification.message()).color(Color32::WHITE);
+                                ui.label(if notification.is_read() { message } else { message.strong() });
+
+                                ui.horizontal(|ui| {
+                                    if self.notification_target(user_id, notification).is_some() && ui.button("Отвори").clicked() {
+                                        open = Some(notification.clone());
+                                    }
+                                    if ui.button(if notification.is_read() { "Непрочетено" } else { "Прочетено" }).clicked() {
+                                        command = Some(ServerCommand::SetNotificationRead {
+                                            user_id,
+                                            notification_id: notification.id(),
+                                            read: !notification.is_read(),
+                                        });
+                                    }
+                                    if ui.button(if archived { "Върни" } else { "Архивирай" }).clicked() {
+                                        command = Some(ServerCommand::SetNotificationArchived {
+                                            user_id,
+                                            notification_id: notification.id(),
+                                            archived: !archived,
+                                        });
+                                    }
+                                });
                             });
                         ui.add_space(10.0);
                     }
 
+                    if let Some(command) = command {
+                        if let Err(e) = self.tx_cmd.send(command) {
+                            self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                        }
+                        self.notifications_state.notification_loading = false;
+                        self.notifications_state.unread_loading = false;
+                        self.loading = true;
+                    }
+
+                    if let Some(notification) = open {
+                        if !notification.is_read() && let Err(e) = self.tx_cmd.send(ServerCommand::SetNotificationRead {
+                            user_id,
+                            notification_id: notification.id(),
+                            read: true,
+                        }) {
+                            self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                        }
+                        if let Some(screen) = self.notification_target(user_id, &notification) {
+                            self.notifications_state.notification_loading = false;
+                            self.notifications_state.unread_loading = false;
+                            self.expenses.debts_or_credits_loading = false;
+                            self.expenses.group_expenses_loading = false;
+                            self.settlement.payment_history_loading = false;
+                            self.group_state.group_loading = false;
+                            self.group_state.invitations_loading = false;
+                            self.screen = screen;
+                        }
+                    }
+
                     ui.add_enabled_ui(!self.loading, |ui| {
                         if ui.add(
                             egui::Button::new(
@@ -491,6 +815,195 @@ impl MyApp {
         });
     }
 
+    /// The screen that shows what a notification is about.
+    fn notification_target(&self, user_id: i32, notification: &Notification) -> Option<Screen> {
+        match notification.kind() {
+            NotificationKind::DebtOverdue | NotificationKind::DueReminder | NotificationKind::Nudge => Some(Screen::MyDebtsOrCredits(user_id, true)),
+            NotificationKind::PaymentConfirmed => Some(Screen::PaymentHistory(user_id, None)),
+            NotificationKind::ExpenseAdded => notification.group_id().map(|group_id| Screen::GroupExpenses(user_id, group_id)),
+            NotificationKind::AddedToGroup => Some(Screen::MyGroups(user_id)),
+            NotificationKind::GroupInvitation | NotificationKind::DebtTransfer => Some(Screen::Invitations(user_id)),
+            NotificationKind::Digest => None,
+        }
+    }
+
+    fn show_notification_settings(&mut self, ctx: &egui::Context, user_id: i32) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::vertical()
+                .auto_shrink([false; 2])
+                .show(ui, |ui| {
+                    ui.heading("Настройки на известията");
+                    ui.add_space(10.0);
+
+                    if !self.notifications_state.preferences_loading {
+                        if let Err(e) = self.tx_cmd.send(ServerCommand::GetNotificationPreferences { user_id }) {
+                            self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                        }
+                        self.notifications_state.preferences_loading = true;
+                        self.loading = true;
+                    }
+                    if !self.group_state.group_loading {
+                        self.group_state.my_groups = Vec::new();
+                        if let Err(e) = self.tx_cmd.send(ServerCommand::ShowGroups { user_id }) {
+                            self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                        }
+                        self.group_state.group_loading = true;
+                    }
+                    if !self.notifications_state.reminder_loading {
+                        if let Err(e) = self.tx_cmd.send(ServerCommand::GetReminderDays { user_id }) {
+                            self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                        }
+                        self.notifications_state.reminder_loading = true;
+                    }
+                    if !self.notifications_state.channels_loading {
+                        if let Err(e) = self.tx_cmd.send(ServerCommand::GetNotificationChannels { user_id }) {
+                            self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                        }
+                        self.notifications_state.channels_loading = true;
+                    }
+
+                    let mut command = None;
+                    let state = &mut self.notifications_state;
+
+                    ui.label("Известия за:");
+                    for kind in NotificationKind::ALL.into_iter().filter(|kind| *kind != NotificationKind::Digest) {
+                        let mut enabled = !state.muted_kinds.contains(&kind);
+                        if ui.checkbox(&mut enabled, kind.label()).changed() {
+                            if enabled {
+                                state.muted_kinds.retain(|muted| *muted != kind);
+                            } else {
+                                state.muted_kinds.push(kind);
+                            }
+                        }
+                    }
+                    ui.add_space(5.0);
+
+                    if !self.group_state.my_groups.is_empty() {
+                        ui.label("Заглушени групи:");
+                        for group in &self.group_state.my_groups {
+                            let mut muted = state.muted_groups.contains(&group.id());
+                            if ui.checkbox(&mut muted, group.groupname()).changed() {
+                                if muted {
+                                    state.muted_groups.push(group.id());
+                                } else {
+                                    state.muted_groups.retain(|group_id| *group_id != group.id());
+                                }
+                            }
+                        }
+                        ui.add_space(5.0);
+                    }
+
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut state.quiet_hours, "Тихи часове от");
+                        ui.add_enabled(state.quiet_hours, egui::TextEdit::singleline(&mut state.quiet_start)
+                            .hint_text("ЧЧ:ММ")
+                            .desired_width(50.0));
+                        ui.label("до");
+                        ui.add_enabled(state.quiet_hours, egui::TextEdit::singleline(&mut state.quiet_end)
+                            .hint_text("ЧЧ:ММ")
+                            .desired_width(50.0));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut state.digest, "Едно обобщение на ден вместо отделни известия, след");
+                        ui.add_enabled(state.digest, egui::TextEdit::singleline(&mut state.digest_time)
+                            .hint_text("ЧЧ:ММ")
+                            .desired_width(50.0));
+                    });
+                    ui.add_space(5.0);
+
+                    if ui.add(
+                        egui::Button::new(
+                            RichText::new("Запази настройките").color(Color32::WHITE)
+                        ).fill(Color32::from_rgb(30, 60, 150))
+                    ).clicked() {
+                        let quiet_hours = state.quiet_hours.then(|| (state.quiet_start.clone(), state.quiet_end.clone()));
+                        command = Some(ServerCommand::SetNotificationPreferences {
+                            user_id,
+                            preferences: NotificationPreferences::new()
+                                .with_muted_kinds(state.muted_kinds.clone())
+                                .with_muted_groups(state.muted_groups.clone())
+                                .with_quiet_hours(quiet_hours)
+                                .with_digest(state.digest)
+                                .with_digest_time(state.digest_time.clone()),
+                        });
+                    }
+                    ui.separator();
+
+                    ui.horizontal(|ui| {
+                        ui.label("Напомняне преди срока (дни):");
+                        ui.add(egui::TextEdit::singleline(&mut state.reminder_days)
+                            .hint_text("3, 1")
+                            .desired_width(80.0));
+                        if ui.add(
+                            egui::Button::new(
+                                RichText::new("Запази").color(Color32::WHITE)
+                            ).fill(Color32::from_rgb(30, 60, 150))
+                        ).clicked() {
+                            match parse_reminder_days(&state.reminder_days) {
+                                Ok(days) => {
+                                    state.reminder_days = format_reminder_days(&days);
+                                    command = Some(ServerCommand::SetReminderDays { user_id, days });
+                                }
+                                Err(e) => {
+                                    self.error_message = Some(e);
+                                    self.error_time = Some(std::time::Instant::now());
+                                }
+                            }
+                        }
+                    });
+                    ui.separator();
+
+                    for (channel, enabled, target) in &mut state.channels {
+                        ui.horizontal(|ui| {
+                            ui.checkbox(enabled, channel.label());
+                            let hint = if *channel == Channel::Webhook { "http://..." } else { "Имейл" };
+                            ui.add_enabled(*enabled, egui::TextEdit::singleline(target)
+                                .hint_text(hint)
+                                .desired_width(220.0));
+                            if ui.add(
+                                egui::Button::new(
+                                    RichText::new("Запази").color(Color32::WHITE)
+                                ).fill(Color32::from_rgb(30, 60, 150))
+                            ).clicked() {
+                                command = Some(ServerCommand::SetNotificationChannel {
+                                    user_id,
+                                    channel: *channel,
+                                    enabled: *enabled,
+                                    target: target.clone(),
+                                });
+                            }
+                        });
+                    }
+
+                    if let Some(command) = command {
+                        if let Err(e) = self.tx_cmd.send(command) {
+                            self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                        }
+                        self.loading = true;
+                    }
+
+                    ui.add_space(10.0);
+                    ui.add_enabled_ui(!self.loading, |ui| {
+                        if ui.add(
+                            egui::Button::new(
+                                RichText::new("Назад").color(Color32::WHITE)
+                            ).fill(Color32::from_rgb(0, 102, 0))
+                        ).clicked() {
+                            self.notifications_state.notification_loading = false;
+                            self.screen = Screen::MyNotifications(user_id);
+                        }
+                    });
+                    if self.loading {
+                        ui.separator();
+                        ui.label("Моля изчакайте...");
+                    }
+
+                    self.update_messages(ctx);
+                    self.show_messages(ui);
+            });
+        });
+    }
+
     fn show_create_group(&mut self, ctx: &egui::Context, owner_id: i32) {
         egui::CentralPanel::default().show(ctx, |ui| {
             egui::ScrollArea::vertical()
@@ -504,6 +1017,14 @@ impl MyApp {
                             ui.text_edit_singleline(&mut self.group_state.group_name);
                         });
 
+                        if self.group_state.group_currency.is_empty() {
+                            self.group_state.group_currency = DEFAULT_CURRENCY.to_string();
+                        }
+                        ui.horizontal(|ui| {
+                            ui.label("Валута на групата:");
+                            ui.add(egui::TextEdit::singleline(&mut self.group_state.group_currency).desired_width(50.0));
+                        });
+
                         ui.separator();
 
                         ui.horizontal(|ui| {
@@ -524,6 +1045,7 @@ impl MyApp {
                         });
 
                         ui.separator();
+                        ui.label("Избраните потребители ще получат покана за групата.");
 
                         for user in &self.group_state.search_results {
                             let mut checked = self.group_state.selected_users.contains(&user.id());
@@ -565,6 +1087,7 @@ impl MyApp {
                                     name: std::mem::take(&mut self.group_state.group_name),
                                     owner_id,
                                     members: std::mem::take(&mut self.group_state.selected_users),
+                                    currency: std::mem::take(&mut self.group_state.group_currency),
                                 }){
                                     self.error_message = Some(format!("Неуспешно изпращане: {}", e));
                                 }
@@ -610,14 +1133,19 @@ impl MyApp {
             egui::ScrollArea::vertical()
                 .auto_shrink([false; 2])
                 .show(ui, |ui| {
-                    ui.heading("Моите групи");
+                    let archived = self.group_state.show_archived_groups;
+                    ui.heading(if archived { "Архивирани групи" } else { "Моите групи" });
                     ui.add_space(10.0);
 
                     if !self.group_state.group_loading {
                         self.group_state.my_groups = Vec::new();
-                        if let Err(e) = self.tx_cmd.send(ServerCommand::ShowGroups {
-                            user_id,
-                        }) {
+                        let command = if archived {
+                            ServerCommand::ShowArchivedGroups { user_id }
+                        }
+                        else {
+                            ServerCommand::ShowGroups { user_id }
+                        };
+                        if let Err(e) = self.tx_cmd.send(command) {
                             self.error_message = Some(format!("Неуспешно изпращане: {}", e));
                         }
                         self.group_state.group_loading = true;
@@ -625,33 +1153,1215 @@ impl MyApp {
                         self.process_backend_responses(ctx);
                     }
 
+                    if archived && self.group_state.my_groups.is_empty() && !self.loading {
+                        ui.label("Нямате архивирани групи.");
+                    }
+
+                    let mut restore = None;
+
                     for group in &self.group_state.my_groups {
                         ui.horizontal(|ui| {
-                            ui.label(format!("{}", group.groupname()));
+                            if ui.link(group.groupname()).clicked() {
+                                self.group_state.detail_loading = false;
+                                self.screen = Screen::GroupDetail(user_id, group.id());
+                            }
+                            ui.weak(group.role().label());
+                            if group.is_archived() {
+                                if group.role().can_manage_group() && ui.add(
+                                    egui::Button::new(
+                                        RichText::new("Възстанови").color(Color32::WHITE)
+                                    ).fill(Color32::from_rgb(0, 102, 102))
+                                ).clicked() {
+                                    restore = Some(group.id());
+                                }
+                                return;
+                            }
                             if ui.add(
                                 egui::Button::new(
                                     RichText::new("Добави разход").color(Color32::WHITE)
                                 ).fill(Color32::from_rgb(30, 60, 150))
                             ).clicked() {
+                                self.expenses.members_loading = false;
+                                self.expenses.exp_currency = group.currency().to_string();
                                 self.screen = Screen::AddExp(user_id, group.id());
                             }
+                            if ui.add(
+                                egui::Button::new(
+                                    RichText::new("История").color(Color32::WHITE)
+                                ).fill(Color32::from_rgb(0, 102, 102))
+                            ).clicked() {
+                                self.expenses.group_expenses_loading = false;
+                                self.screen = Screen::GroupExpenses(user_id, group.id());
+                            }
+                            if ui.add(
+                                egui::Button::new(
+                                    RichText::new("Разплащане").color(Color32::WHITE)
+                                ).fill(Color32::from_rgb(102, 102, 0))
+                            ).clicked() {
+                                self.settlement.settlement_loading = false;
+                                self.screen = Screen::GroupSettlement(user_id, group.id());
+                            }
+                            if ui.add(
+                                egui::Button::new(
+                                    RichText::new("Плащания").color(Color32::WHITE)
+                                ).fill(Color32::from_rgb(0, 102, 102))
+                            ).clicked() {
+                                self.settlement.payment_history_loading = false;
+                                self.screen = Screen::PaymentHistory(user_id, Some(group.id()));
+                            }
+                            if ui.add(
+                                egui::Button::new(
+                                    RichText::new("Повтарящи се").color(Color32::WHITE)
+                                ).fill(Color32::from_rgb(76, 0, 153))
+                            ).clicked() {
+                                self.expenses.recurring_loading = false;
+                                self.screen = Screen::RecurringExpenses(user_id, group.id());
+                            }
+                            if ui.add(
+                                egui::Button::new(
+                                    RichText::new("Членове").color(Color32::WHITE)
+                                ).fill(Color32::from_rgb(0, 76, 153))
+                            ).clicked() {
+                                self.group_state.members_loading = false;
+                                self.group_state.search_results = Vec::new();
+                                self.group_state.transfer_to = None;
+                                self.group_state.invite_code = None;
+                                self.screen = Screen::GroupMembers(user_id, group.id());
+                            }
                         });
 
                         ui.separator();
                     }
 
-                    ui.add_enabled_ui(!self.loading, |ui| {
-                        ui.add_space(10.0);
-                        if ui.add(
-                            egui::Button::new(
+                    if let Some(group_id) = restore {
+                        if let Err(e) = self.tx_cmd.send(ServerCommand::SetGroupArchived { user_id, group_id, archived: false }) {
+                            self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                        }
+                        self.group_state.group_loading = false;
+                        self.loading = true;
+                    }
+
+                    ui.add_enabled_ui(!self.loading, |ui| {
+                        ui.add_space(10.0);
+                        ui.horizontal(|ui| {
+                            if ui.add(
+                                egui::Button::new(
+                                    RichText::new(if archived { "Активни групи" } else { "Архивирани групи" }).color(Color32::WHITE)
+                                ).fill(Color32::from_rgb(76, 0, 153))
+                            ).clicked() {
+                                self.group_state.show_archived_groups = !archived;
+                                self.group_state.group_loading = false;
+                            }
+                            if ui.add(
+                                egui::Button::new(
+                                    RichText::new("Назад").color(Color32::WHITE)
+                                ).fill(Color32::from_rgb(0, 102, 0))
+                            ).clicked() {
+                                let owner_id = user_id;
+                                if let Err(e) = self.tx_cmd.send(ServerCommand::GetUser { owner_id }){
+                                    self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                                }
+                                self.group_state.group_loading = false;
+                                self.group_state.show_archived_groups = false;
+                                self.loading = true;
+                                self.process_backend_responses(ctx);
+                            }
+                        });
+                    });
+
+                    if self.loading {
+                        ui.separator();
+                        ui.label("Моля изчакайте...");
+                    }
+
+                    self.update_messages(ctx);
+                    self.show_messages(ui);
+            });
+        });
+    }
+
+    fn show_group_detail(&mut self, ctx: &egui::Context, user_id: i32, group_id: i32) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::vertical()
+                .auto_shrink([false; 2])
+                .show(ui, |ui| {
+                    if !self.group_state.detail_loading {
+                        self.group_state.detail = None;
+                        if let Err(e) = self.tx_cmd.send(ServerCommand::ShowGroupDetail { user_id, group_id }) {
+                            self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                        }
+                        self.group_state.detail_loading = true;
+                        self.loading = true;
+                        self.process_backend_responses(ctx);
+                    }
+
+                    if let Some(detail) = &self.group_state.detail {
+                        let group = detail.group();
+                        ui.heading(group.groupname());
+                        ui.label(format!("Валута: {} · Вашата роля: {}", group.currency(), group.role().label()));
+                        ui.add_space(10.0);
+
+                        ui.label(RichText::new("Членове").strong());
+                        for member in detail.members() {
+                            ui.horizontal(|ui| {
+                                ui.label(member.username());
+                                if member.is_loyal_payer() {
+                                    ui.colored_label(Color32::GOLD, "⭐");
+                                }
+                                let balance = detail.balance_of(member.id());
+                                let color = if balance.is_negative() { Color32::RED } else { Color32::GREEN };
+                                ui.colored_label(color, format_amount(balance, group.currency()));
+                            });
+                        }
+                        ui.separator();
+
+                        ui.label(RichText::new("История").strong());
+                        if detail.history().is_empty() {
+                            ui.label("Все още няма разходи и плащания.");
+                        }
+                        for activity in detail.history() {
+                            let text = match activity {
+                                GroupActivity::Expense(expense) => format!(
+                                    "{} плати {} за „{}“",
+                                    expense.payer_name(),
+                                    format_amount(expense.amount(), expense.currency()),
+                                    expense.description()
+                                ),
+                                GroupActivity::Payment(payment) => format!(
+                                    "{} плати {} на {} ({}){}",
+                                    payment.from_name(),
+                                    format_amount(payment.amount(), payment.currency()),
+                                    payment.to_name(),
+                                    payment.method().label(),
+                                    if payment.is_confirmed() { "" } else { " – непотвърдено" }
+                                ),
+                            };
+                            ui.horizontal(|ui| {
+                                ui.weak(activity.created_at());
+                                ui.label(text);
+                            });
+                        }
+                        ui.separator();
+
+                        ui.label(RichText::new("Предложени плащания").strong());
+                        if detail.settlement().is_empty() {
+                            ui.label("Няма нужда от плащания.");
+                        }
+                        for transfer in detail.settlement() {
+                            ui.label(format!("{} плаща на {} {}", transfer.from_name(), transfer.to_name(), format_amount(transfer.amount(), transfer.currency())));
+                        }
+                        ui.separator();
+
+                        let mut command = None;
+                        if group.role().can_manage_group() {
+                            ui.horizontal(|ui| {
+                                ui.label("Ново име:");
+                                ui.text_edit_singleline(&mut self.group_state.rename_to);
+                                if ui.add(
+                                    egui::Button::new(
+                                        RichText::new("Преименувай").color(Color32::WHITE)
+                                    ).fill(Color32::from_rgb(0, 76, 153))
+                                ).clicked() {
+                                    command = Some(ServerCommand::RenameGroup {
+                                        user_id,
+                                        group_id,
+                                        name: std::mem::take(&mut self.group_state.rename_to),
+                                    });
+                                }
+                            });
+                            ui.horizontal(|ui| {
+                                let (text, color) = if group.is_archived() {
+                                    ("Възстанови", Color32::from_rgb(0, 102, 102))
+                                } else {
+                                    ("Архивирай", Color32::from_rgb(153, 76, 0))
+                                };
+                                if ui.add(
+                                    egui::Button::new(
+                                        RichText::new(text).color(Color32::WHITE)
+                                    ).fill(color)
+                                ).clicked() {
+                                    command = Some(ServerCommand::SetGroupArchived { user_id, group_id, archived: !group.is_archived() });
+                                }
+                                if group.role().is_owner() && ui.add(
+                                    egui::Button::new(
+                                        RichText::new("Изтрий групата").color(Color32::WHITE)
+                                    ).fill(Color32::from_rgb(153, 0, 0))
+                                ).clicked() {
+                                    command = Some(ServerCommand::DeleteGroup { user_id, group_id });
+                                }
+                            });
+                            ui.separator();
+                        }
+
+                        if let Some(command) = command {
+                            let deleting = matches!(command, ServerCommand::DeleteGroup { .. });
+                            if let Err(e) = self.tx_cmd.send(command) {
+                                self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                            }
+                            self.group_state.detail_loading = false;
+                            self.loading = true;
+                            if deleting {
+                                self.group_state.group_loading = false;
+                                self.screen = Screen::MyGroups(user_id);
+                            }
+                        }
+                    }
+
+                    ui.add_enabled_ui(!self.loading, |ui| {
+                        ui.add_space(10.0);
+                        ui.horizontal(|ui| {
+                            let archived = self.group_state.detail.as_ref().is_some_and(|detail| detail.group().is_archived());
+                            if !archived && ui.add(
+                                egui::Button::new(
+                                    RichText::new("Добави разход").color(Color32::WHITE)
+                                ).fill(Color32::from_rgb(30, 60, 150))
+                            ).clicked() {
+                                self.expenses.members_loading = false;
+                                self.expenses.exp_currency = self.group_state.detail
+                                    .as_ref()
+                                    .map(|detail| detail.group().currency().to_string())
+                                    .unwrap_or_default();
+                                self.screen = Screen::AddExp(user_id, group_id);
+                            }
+                            if ui.add(
+                                egui::Button::new(
+                                    RichText::new("Разплащане").color(Color32::WHITE)
+                                ).fill(Color32::from_rgb(102, 102, 0))
+                            ).clicked() {
+                                self.settlement.settlement_loading = false;
+                                self.screen = Screen::GroupSettlement(user_id, group_id);
+                            }
+                            if ui.add(
+                                egui::Button::new(
+                                    RichText::new("Членове").color(Color32::WHITE)
+                                ).fill(Color32::from_rgb(0, 76, 153))
+                            ).clicked() {
+                                self.group_state.members_loading = false;
+                                self.group_state.search_results = Vec::new();
+                                self.group_state.transfer_to = None;
+                                self.group_state.invite_code = None;
+                                self.screen = Screen::GroupMembers(user_id, group_id);
+                            }
+                            if ui.add(
+                                egui::Button::new(
+                                    RichText::new("Назад").color(Color32::WHITE)
+                                ).fill(Color32::from_rgb(0, 102, 0))
+                            ).clicked() {
+                                self.group_state.group_loading = false;
+                                self.screen = Screen::MyGroups(user_id);
+                            }
+                        });
+                    });
+
+                    if self.loading {
+                        ui.separator();
+                        ui.label("Моля изчакайте...");
+                    }
+
+                    self.update_messages(ctx);
+                    self.show_messages(ui);
+            });
+        });
+    }
+
+    fn show_group_members(&mut self, ctx: &egui::Context, user_id: i32, group_id: i32) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::vertical()
+                .auto_shrink([false; 2])
+                .show(ui, |ui| {
+                    ui.heading("Членове на групата");
+                    ui.add_space(10.0);
+
+                    if !self.group_state.members_loading {
+                        self.expenses.exp_members = Vec::new();
+                        self.group_state.member_roles = Vec::new();
+                        for command in [ServerCommand::ShowGroupMembers { user_id, group_id }, ServerCommand::ShowGroupRoles { user_id, group_id }] {
+                            if let Err(e) = self.tx_cmd.send(command) {
+                                self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                            }
+                        }
+                        self.group_state.members_loading = true;
+                        self.loading = true;
+                        self.process_backend_responses(ctx);
+                    }
+
+                    let role_of = |member_id: i32| self.group_state.member_roles
+                        .iter()
+                        .find(|(id, _)| *id == member_id)
+                        .map(|(_, role)| *role)
+                        .unwrap_or_default();
+                    let my_role = role_of(user_id);
+                    let mut command = None;
+
+                    for member in &self.expenses.exp_members {
+                        let role = role_of(member.id());
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{} ({})", member.username(), member.email()));
+                            if member.is_loyal_payer() {
+                                ui.colored_label(Color32::GOLD, "⭐");
+                            }
+                            ui.weak(role.label());
+                            if member.id() == user_id || role.is_owner() {
+                                return;
+                            }
+                            if my_role.is_owner() {
+                                let (text, new_role) = if role == GroupRole::Admin {
+                                    ("Направи член", GroupRole::Member)
+                                } else {
+                                    ("Направи администратор", GroupRole::Admin)
+                                };
+                                if ui.add(
+                                    egui::Button::new(
+                                        RichText::new(text).color(Color32::WHITE)
+                                    ).fill(Color32::from_rgb(76, 0, 153))
+                                ).clicked() {
+                                    command = Some(ServerCommand::SetMemberRole { user_id, group_id, member_id: member.id(), role: new_role });
+                                }
+                                if ui.add(
+                                    egui::Button::new(
+                                        RichText::new("Прехвърли собствеността").color(Color32::WHITE)
+                                    ).fill(Color32::from_rgb(153, 76, 0))
+                                ).clicked() {
+                                    command = Some(ServerCommand::TransferOwnership { user_id, group_id, new_owner_id: member.id() });
+                                }
+                            }
+                            if my_role.can_manage_members() && (role != GroupRole::Admin || my_role.is_owner()) && ui.add(
+                                egui::Button::new(
+                                    RichText::new("Премахни").color(Color32::WHITE)
+                                ).fill(Color32::from_rgb(153, 0, 0))
+                            ).clicked() {
+                                command = Some(ServerCommand::RemoveGroupMember { user_id, group_id, member_id: member.id() });
+                            }
+                        });
+                    }
+                    ui.separator();
+
+                    if my_role.can_manage_members() {
+                        ui.label("Покана на член:");
+                        ui.horizontal(|ui| {
+                            ui.text_edit_singleline(&mut self.group_state.search_query);
+                            if ui.add(
+                                egui::Button::new(
+                                    RichText::new("Търси").color(Color32::WHITE)
+                                ).fill(Color32::from_rgb(0, 102, 0))
+                            ).clicked() {
+                                command = Some(ServerCommand::SearchUsers {
+                                    query: std::mem::take(&mut self.group_state.search_query),
+                                });
+                            }
+                        });
+                        for user in &self.group_state.search_results {
+                            if self.expenses.exp_members.iter().any(|member| member.id() == user.id()) {
+                                continue;
+                            }
+                            ui.horizontal(|ui| {
+                                ui.label(format!("{} ({})", user.username(), user.email()));
+                                if ui.add(
+                                    egui::Button::new(
+                                        RichText::new("Покани").color(Color32::WHITE)
+                                    ).fill(Color32::from_rgb(30, 60, 150))
+                                ).clicked() {
+                                    command = Some(ServerCommand::InviteToGroup { user_id, group_id, invitee_id: user.id() });
+                                }
+                            });
+                        }
+                        ui.add_space(5.0);
+                        if ui.add(
+                            egui::Button::new(
+                                RichText::new("Нов код за покана").color(Color32::WHITE)
+                            ).fill(Color32::from_rgb(0, 102, 102))
+                        ).clicked() {
+                            command = Some(ServerCommand::CreateInviteCode { user_id, group_id });
+                        }
+                        if let Some(code) = &self.group_state.invite_code {
+                            ui.label(format!("Код за покана: {}", code));
+                            ui.label(format!("Може да се използва веднъж в следващите {} дни.", INVITE_CODE_VALID_DAYS));
+                            show_qr_code(ui, code, 4.0);
+                        }
+                        ui.separator();
+                    }
+
+                    ui.horizontal(|ui| {
+                        ui.label("Прехвърли дълговете ми на:");
+                        let selected = self.expenses.exp_members
+                            .iter()
+                            .find(|member| Some(member.id()) == self.group_state.transfer_to)
+                            .map(|member| member.username().to_string())
+                            .unwrap_or_default();
+                        egui::ComboBox::from_id_salt("transfer_debts_to")
+                            .selected_text(selected)
+                            .show_ui(ui, |ui| {
+                                for member in self.expenses.exp_members.iter().filter(|member| member.id() != user_id) {
+                                    ui.selectable_value(&mut self.group_state.transfer_to, Some(member.id()), member.username());
+                                }
+                            });
+                        if let Some(to_id) = self.group_state.transfer_to && ui.add(
+                            egui::Button::new(
+                                RichText::new("Изпрати молба").color(Color32::WHITE)
+                            ).fill(Color32::from_rgb(102, 102, 0))
+                        ).clicked() {
+                            command = Some(ServerCommand::RequestDebtTransfer { user_id, group_id, to_id });
+                        }
+                    });
+
+                    if let Some(command) = command {
+                        let refresh = !matches!(command, ServerCommand::SearchUsers { .. } | ServerCommand::CreateInviteCode { .. });
+                        if let Err(e) = self.tx_cmd.send(command) {
+                            self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                        }
+                        if refresh {
+                            self.group_state.members_loading = false;
+                        }
+                        self.loading = true;
+                    }
+
+                    ui.add_enabled_ui(!self.loading, |ui| {
+                        ui.add_space(10.0);
+                        ui.horizontal(|ui| {
+                            if ui.add(
+                                egui::Button::new(
+                                    RichText::new("Напусни групата").color(Color32::WHITE)
+                                ).fill(Color32::from_rgb(153, 0, 0))
+                            ).clicked() {
+                                if let Err(e) = self.tx_cmd.send(ServerCommand::LeaveGroup { user_id, group_id }) {
+                                    self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                                }
+                                self.group_state.group_loading = false;
+                                self.screen = Screen::MyGroups(user_id);
+                            }
+                            if ui.add(
+                                egui::Button::new(
+                                    RichText::new("Назад").color(Color32::WHITE)
+                                ).fill(Color32::from_rgb(0, 102, 0))
+                            ).clicked() {
+                                self.group_state.group_loading = false;
+                                self.screen = Screen::MyGroups(user_id);
+                            }
+                        });
+                    });
+
+                    if self.loading {
+                        ui.separator();
+                        ui.label("Моля изчакайте...");
+                    }
+
+                    self.update_messages(ctx);
+                    self.show_messages(ui);
+            });
+        });
+    }
+
+    fn show_invitations(&mut self, ctx: &egui::Context, user_id: i32) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::vertical()
+                .auto_shrink([false; 2])
+                .show(ui, |ui| {
+                    ui.heading("Покани и прехвърляния на дългове");
+                    ui.add_space(10.0);
+
+                    if !self.group_state.invitations_loading {
+                        self.group_state.invitations = Vec::new();
+                        self.group_state.debt_transfers = Vec::new();
+                        for command in [ServerCommand::ShowInvitations { user_id }, ServerCommand::ShowDebtTransfers { user_id }] {
+                            if let Err(e) = self.tx_cmd.send(command) {
+                                self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                            }
+                        }
+                        self.group_state.invitations_loading = true;
+                        self.loading = true;
+                        self.process_backend_responses(ctx);
+                    }
+
+                    let mut command = None;
+
+                    if self.group_state.invitations.is_empty() && self.group_state.debt_transfers.is_empty() && !self.loading {
+                        ui.label("Нямате чакащи покани.");
+                    }
+                    for invitation in &self.group_state.invitations {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{} ви кани в група {}", invitation.inviter(), invitation.group_name()));
+                            ui.weak(invitation.created_at());
+                            if ui.add(
+                                egui::Button::new(
+                                    RichText::new("Приеми").color(Color32::WHITE)
+                                ).fill(Color32::from_rgb(0, 102, 0))
+                            ).clicked() {
+                                command = Some(ServerCommand::RespondToInvitation { user_id, invitation_id: invitation.id(), accept: true });
+                            }
+                            if ui.add(
+                                egui::Button::new(
+                                    RichText::new("Откажи").color(Color32::WHITE)
+                                ).fill(Color32::from_rgb(153, 0, 0))
+                            ).clicked() {
+                                command = Some(ServerCommand::RespondToInvitation { user_id, invitation_id: invitation.id(), accept: false });
+                            }
+                        });
+                    }
+                    for transfer in &self.group_state.debt_transfers {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{} иска да ви прехвърли дълговете си в група {}", transfer.requester(), transfer.group_name()));
+                            ui.weak(transfer.created_at());
+                            if ui.add(
+                                egui::Button::new(
+                                    RichText::new("Поеми").color(Color32::WHITE)
+                                ).fill(Color32::from_rgb(0, 102, 0))
+                            ).clicked() {
+                                command = Some(ServerCommand::RespondToDebtTransfer { user_id, transfer_id: transfer.id(), accept: true });
+                            }
+                            if ui.add(
+                                egui::Button::new(
+                                    RichText::new("Откажи").color(Color32::WHITE)
+                                ).fill(Color32::from_rgb(153, 0, 0))
+                            ).clicked() {
+                                command = Some(ServerCommand::RespondToDebtTransfer { user_id, transfer_id: transfer.id(), accept: false });
+                            }
+                        });
+                    }
+                    ui.separator();
+
+                    ui.label("Присъединяване с код за покана:");
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(&mut self.group_state.join_code).desired_width(120.0));
+                        if ui.add(
+                            egui::Button::new(
+                                RichText::new("Присъедини се").color(Color32::WHITE)
+                            ).fill(Color32::from_rgb(30, 60, 150))
+                        ).clicked() {
+                            command = Some(ServerCommand::JoinGroupWithCode {
+                                user_id,
+                                code: std::mem::take(&mut self.group_state.join_code),
+                            });
+                        }
+                    });
+
+                    if let Some(command) = command {
+                        if let Err(e) = self.tx_cmd.send(command) {
+                            self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                        }
+                        self.group_state.invitations_loading = false;
+                        self.group_state.group_loading = false;
+                        self.loading = true;
+                    }
+
+                    ui.add_enabled_ui(!self.loading, |ui| {
+                        ui.add_space(10.0);
+                        if ui.add(
+                            egui::Button::new(
+                                RichText::new("Назад").color(Color32::WHITE)
+                            ).fill(Color32::from_rgb(0, 102, 0))
+                        ).clicked() {
+                            if let Err(e) = self.tx_cmd.send(ServerCommand::GetUser { owner_id: user_id }) {
+                                self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                            }
+                            self.loading = true;
+                            self.process_backend_responses(ctx);
+                        }
+                    });
+
+                    if self.loading {
+                        ui.separator();
+                        ui.label("Моля изчакайте...");
+                    }
+
+                    self.update_messages(ctx);
+                    self.show_messages(ui);
+            });
+        });
+    }
+
+    fn show_add_expenses(&mut self, ctx: &egui::Context, user_id: i32, group_id: i32) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::vertical()
+                .auto_shrink([false; 2])
+                .show(ui, |ui| {
+                    let editing = self.expenses.exp_editing;
+                    ui.heading(if editing.is_some() { "Редактиране на разход" } else { "Добавяне на разход" });
+
+                    if !self.expenses.members_loading {
+                        self.expenses.exp_members = Vec::new();
+                        if let Err(e) = self.tx_cmd.send(ServerCommand::ShowGroupMembers {
+                            user_id,
+                            group_id,
+                        }) {
+                            self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                        }
+                        self.expenses.members_loading = true;
+                        self.loading = true;
+                        self.process_backend_responses(ctx);
+                    }
+
+                    ui.add_enabled_ui(!self.loading, |ui| {
+                        ui.label("Сума:");
+                        ui.horizontal(|ui| {
+                            ui.text_edit_singleline(&mut self.expenses.exp_amount);
+                            ui.add(egui::TextEdit::singleline(&mut self.expenses.exp_currency)
+                                .hint_text("Валута")
+                                .desired_width(50.0));
+                        });
+
+                        ui.label("Описание:");
+                        ui.text_edit_singleline(&mut self.expenses.exp_description);
+
+                        if editing.is_none() {
+                            ui.checkbox(&mut self.expenses.exp_recurring, "Повтарящ се разход");
+                        }
+                        let recurring = editing.is_none() && self.expenses.exp_recurring;
+
+                        if recurring {
+                            ui.horizontal(|ui| {
+                                ui.label("Повтаряне:");
+                                egui::ComboBox::from_id_salt("recurrence_interval")
+                                    .selected_text(self.expenses.exp_interval.label())
+                                    .show_ui(ui, |ui| {
+                                        for option in RecurrenceInterval::ALL {
+                                            ui.selectable_value(&mut self.expenses.exp_interval, option, option.label());
+                                        }
+                                    });
+                            });
+                            ui.label("Първо плащане:");
+                            ui.text_edit_singleline(&mut self.expenses.exp_due_date);
+                            ui.label("Последно плащане (по желание):");
+                            ui.text_edit_singleline(&mut self.expenses.exp_end_date);
+                        }
+                        else {
+                            ui.label("Крайна дата за изплащане:");
+                            ui.text_edit_singleline(&mut self.expenses.exp_due_date);
+                        }
+
+                        ui.add_space(10.0);
+                        ui.label("Разпределение:");
+                        ui.horizontal(|ui| {
+                            for mode in SplitMode::ALL {
+                                ui.radio_value(&mut self.expenses.exp_split_mode, mode, mode.label());
+                            }
+                        });
+
+                        if self.expenses.exp_split_mode == SplitMode::Equal {
+                            ui.label("Участници:");
+                            for member in &self.expenses.exp_members {
+                                let mut checked = self.expenses.exp_participants.contains(&member.id());
+                                if ui.checkbox(&mut checked, member.username()).changed() {
+                                    if checked {
+                                        self.expenses.exp_participants.push(member.id());
+                                    }
+                                    else {
+                                        self.expenses.exp_participants.retain(|&id| id != member.id());
+                                    }
+                                }
+                            }
+                        }
+                        else {
+                            let hint = match self.expenses.exp_split_mode {
+                                SplitMode::Exact => "сума",
+                                SplitMode::Percentage => "%",
+                                _ => "дялове",
+                            };
+                            ui.label("Участниците без стойност не се включват в разхода.");
+                            for member in &self.expenses.exp_members {
+                                ui.horizontal(|ui| {
+                                    ui.label(member.username());
+                                    let value = self.expenses.exp_split_values.entry(member.id()).or_default();
+                                    ui.add(egui::TextEdit::singleline(value).desired_width(80.0).hint_text(hint));
+                                });
+                            }
+                        }
+
+                        ui.add_space(10.0);
+
+                        if ui.add(
+                            egui::Button::new(
+                                RichText::new(if editing.is_some() { "Запази промените" } else { "Добави разход" }).color(Color32::WHITE)
+                            ).fill(Color32::from_rgb(30, 60, 150))
+                        ).clicked() {
+                            match Money::parse(&self.expenses.exp_amount).and_then(|amount| Ok((amount, self.build_split()?))) {
+                                Ok((amount, split)) => {
+                                    let description = std::mem::take(&mut self.expenses.exp_description);
+                                    let due_date = std::mem::take(&mut self.expenses.exp_due_date);
+                                    let command = match editing {
+                                        None if recurring => ServerCommand::AddRecurringExpense {
+                                            user_id,
+                                            group_id,
+                                            amount,
+                                            currency: self.expenses.exp_currency.clone(),
+                                            description,
+                                            interval: self.expenses.exp_interval,
+                                            start_date: due_date,
+                                            end_date: std::mem::take(&mut self.expenses.exp_end_date),
+                                            split,
+                                        },
+                                        Some(expense_id) => ServerCommand::EditExpense {
+                                            user_id,
+                                            expense_id,
+                                            amount,
+                                            currency: self.expenses.exp_currency.clone(),
+                                            description,
+                                            due_date,
+                                            split,
+                                        },
+                                        None => ServerCommand::AddExpenses {
+                                            user_id,
+                                            group_id,
+                                            amount,
+                                            currency: self.expenses.exp_currency.clone(),
+                                            description,
+                                            due_date,
+                                            split,
+                                        },
+                                    };
+                                    if let Err(e) = self.tx_cmd.send(command) {
+                                        self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                                    }
+                                    self.expenses.exp_amount.clear();
+                                    self.expenses.exp_split_values.clear();
+                                    self.loading = true;
+
+                                    if editing.is_some() {
+                                        self.expenses.exp_editing = None;
+                                        self.expenses.members_loading = false;
+                                        self.expenses.group_expenses_loading = false;
+                                        self.screen = Screen::GroupExpenses(user_id, group_id);
+                                    }
+                                }
+                                Err(e) => {
+                                    self.error_message = Some(e);
+                                    self.error_time = Some(std::time::Instant::now());
+                                }
+                            }
+                        }
+
+                        ui.add_space(5.0);
+                        if ui.add(
+                            egui::Button::new(
+                                RichText::new("Назад").color(Color32::WHITE)
+                            ).fill(Color32::from_rgb(0, 102, 0))
+                        ).clicked() {
+                            self.expenses.members_loading = false;
+                            if editing.is_some() {
+                                self.expenses.exp_editing = None;
+                                self.expenses.exp_amount.clear();
+                                self.expenses.exp_description.clear();
+                                self.expenses.exp_due_date.clear();
+                                self.expenses.exp_split_values.clear();
+                                self.screen = Screen::GroupExpenses(user_id, group_id);
+                            }
+                            else {
+                                self.screen = Screen::MyGroups(user_id);
+                            }
+                        }
+
+                        self.process_backend_responses(ctx);
+                    });
+
+                    if self.loading {
+                        ui.separator();
+                        ui.label("Моля изчакайте...");
+                    }
+
+                    self.update_messages(ctx);
+                    self.show_messages(ui);
+            });
+        });
+    }
+
+    fn start_editing(&mut self, user_id: i32, expense: &Expense) {
+        let split = expense.split();
+
+        self.expenses.exp_editing = Some(expense.id());
+        self.expenses.exp_amount = expense.amount().to_string();
+        self.expenses.exp_currency = expense.currency().to_string();
+        self.expenses.exp_description = expense.description().to_string();
+        self.expenses.exp_due_date = expense.due_date().to_string();
+        self.expenses.exp_split_mode = split.mode();
+        self.expenses.exp_participants = split.participants();
+        self.expenses.exp_split_values = split.entries()
+            .iter()
+            .map(|&(id, value)| {
+                let text = match split.mode() {
+                    SplitMode::Exact | SplitMode::Percentage => Money::from_minor(value).to_string(),
+                    SplitMode::Equal | SplitMode::Shares => value.to_string(),
+                };
+                (id, text)
+            })
+            .collect();
+        self.expenses.members_loading = false;
+        self.screen = Screen::AddExp(user_id, expense.group_id());
+    }
+
+    fn build_split(&self) -> Result<Split, String> {
+        if self.expenses.exp_split_mode == SplitMode::Equal {
+            if self.expenses.exp_participants.is_empty() {
+                return Err("Изберете поне един участник.".to_string());
+            }
+            return Ok(Split::equal(self.expenses.exp_participants.clone()));
+        }
+
+        let mut entries = Vec::new();
+
+        for member in &self.expenses.exp_members {
+            let input = self.expenses.exp_split_values.get(&member.id()).map(|v| v.trim()).unwrap_or("");
+            if input.is_empty() {
+                continue;
+            }
+
+            let value = match self.expenses.exp_split_mode {
+                SplitMode::Equal => 1,
+                SplitMode::Exact => Money::parse(input)?.minor(),
+                SplitMode::Percentage => parse_percentage(input)?,
+                SplitMode::Shares => input.parse::<i64>().map_err(|_| "Невалиден брой дялове.".to_string())?,
+            };
+            entries.push((member.id(), value));
+        }
+
+        Ok(Split::new(self.expenses.exp_split_mode, entries))
+    }
+
+    fn show_group_expenses(&mut self, ctx: &egui::Context, user_id: i32, group_id: i32) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::vertical()
+                .auto_shrink([false; 2])
+                .show(ui, |ui| {
+                    ui.heading("История на разходите");
+                    ui.add_space(10.0);
+
+                    if !self.expenses.group_expenses_loading {
+                        self.expenses.group_expenses = Vec::new();
+                        if let Err(e) = self.tx_cmd.send(ServerCommand::ShowGroupExpenses {
+                            user_id,
+                            group_id,
+                        }) {
+                            self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                        }
+                        self.expenses.group_expenses_loading = true;
+                        self.loading = true;
+                        self.process_backend_responses(ctx);
+                    }
+
+                    let mut to_edit = None;
+                    for expense in &self.expenses.group_expenses {
+                        let participants = expense.shares()
+                            .iter()
+                            .map(|share| format!("{} ({})", share.username(), format_amount(share.amount(), expense.currency())))
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        let mut amount = format_amount(expense.amount(), expense.currency());
+                        if expense.currency() != expense.group_currency() {
+                            amount = format!("{} ({})", amount, format_amount(expense.group_amount(), expense.group_currency()));
+                        }
+                        ui.label(format!(
+                            "{}\nПлатил: {}\nСума: {}\nРазпределение: {}\nУчастници: {}\nДата: {}",
+                            expense.description(),
+                            expense.payer_name(),
+                            amount,
+                            expense.split_mode().label(),
+                            participants,
+                            expense.created_at()
+                        ));
+                        if expense.payer_id() == user_id {
+                            ui.horizontal(|ui| {
+                                if ui.add(
+                                    egui::Button::new(
+                                        RichText::new("Редактирай").color(Color32::WHITE)
+                                    ).fill(Color32::from_rgb(30, 60, 150))
+                                ).clicked() {
+                                    to_edit = Some(expense.clone());
+                                }
+                                if ui.add(
+                                    egui::Button::new(
+                                        RichText::new("Изтрий").color(Color32::WHITE)
+                                    ).fill(Color32::from_rgb(153, 0, 0))
+                                ).clicked() {
+                                    if let Err(e) = self.tx_cmd.send(ServerCommand::DeleteExpense {
+                                        user_id,
+                                        expense_id: expense.id(),
+                                    }) {
+                                        self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                                    }
+                                    self.expenses.group_expenses_loading = false;
+                                }
+                            });
+                        }
+                        ui.separator();
+                    }
+
+                    if let Some(expense) = to_edit {
+                        self.start_editing(user_id, &expense);
+                    }
+
+                    ui.add_enabled_ui(!self.loading, |ui| {
+                        ui.add_space(10.0);
+                        if ui.add(
+                            egui::Button::new(
+                                RichText::new("Назад").color(Color32::WHITE)
+                            ).fill(Color32::from_rgb(0, 102, 0))
+                        ).clicked() {
+                            self.expenses.group_expenses_loading = false;
+                            self.screen = Screen::MyGroups(user_id);
+                        }
+                    });
+
+                    if self.loading {
+                        ui.separator();
+                        ui.label("Моля изчакайте...");
+                    }
+
+                    self.update_messages(ctx);
+                    self.show_messages(ui);
+            });
+        });
+    }
+
+    fn show_recurring_expenses(&mut self, ctx: &egui::Context, user_id: i32, group_id: i32) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::vertical()
+                .auto_shrink([false; 2])
+                .show(ui, |ui| {
+                    ui.heading("Повтарящи се разходи");
+                    ui.add_space(10.0);
+
+                    if !self.expenses.recurring_loading {
+                        self.expenses.recurring = Vec::new();
+                        if let Err(e) = self.tx_cmd.send(ServerCommand::ShowRecurringExpenses {
+                            user_id,
+                            group_id,
+                        }) {
+                            self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                        }
+                        self.expenses.recurring_loading = true;
+                        self.loading = true;
+                        self.process_backend_responses(ctx);
+                    }
+
+                    for recurring in &self.expenses.recurring {
+                        let period = match recurring.end_date() {
+                            Some(end_date) => format!("{} - {}", recurring.start_date(), end_date),
+                            None => format!("от {}", recurring.start_date()),
+                        };
+                        ui.label(format!(
+                            "{}\nПлаща: {}\nСума: {}\n{}, {}\nСледващо плащане: {}\nСтатус: {}",
+                            recurring.description(),
+                            recurring.payer_name(),
+                            format_amount(recurring.amount(), recurring.currency()),
+                            recurring.interval().label(),
+                            period,
+                            recurring.next_date(),
+                            recurring.status().label()
+                        ));
+
+                        ui.horizontal(|ui| {
+                            let (label, status) = match recurring.status() {
+                                RecurringStatus::Paused => ("Поднови", RecurringStatus::Active),
+                                _ => ("Пауза", RecurringStatus::Paused),
+                            };
+                            let mut change = None;
+                            if ui.add(
+                                egui::Button::new(
+                                    RichText::new(label).color(Color32::WHITE)
+                                ).fill(Color32::from_rgb(102, 102, 0))
+                            ).clicked() {
+                                change = Some(status);
+                            }
+                            if ui.add(
+                                egui::Button::new(
+                                    RichText::new("Откажи").color(Color32::WHITE)
+                                ).fill(Color32::from_rgb(153, 0, 0))
+                            ).clicked() {
+                                change = Some(RecurringStatus::Cancelled);
+                            }
+
+                            if let Some(status) = change {
+                                if let Err(e) = self.tx_cmd.send(ServerCommand::SetRecurringStatus {
+                                    user_id,
+                                    recurring_id: recurring.id(),
+                                    status,
+                                }) {
+                                    self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                                }
+                                self.expenses.recurring_loading = false;
+                                self.loading = true;
+                            }
+                        });
+                        ui.separator();
+                    }
+
+                    ui.add_enabled_ui(!self.loading, |ui| {
+                        ui.add_space(10.0);
+                        if ui.add(
+                            egui::Button::new(
+                                RichText::new("Назад").color(Color32::WHITE)
+                            ).fill(Color32::from_rgb(0, 102, 0))
+                        ).clicked() {
+                            self.expenses.recurring_loading = false;
+                            self.screen = Screen::MyGroups(user_id);
+                        }
+                    });
+
+                    if self.loading {
+                        ui.separator();
+                        ui.label("Моля изчакайте...");
+                    }
+
+                    self.update_messages(ctx);
+                    self.show_messages(ui);
+            });
+        });
+    }
+
+    fn show_group_settlement(&mut self, ctx: &egui::Context, user_id: i32, group_id: i32) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::vertical()
+                .auto_shrink([false; 2])
+                .show(ui, |ui| {
+                    ui.heading("Разплащане в групата");
+                    ui.add_space(10.0);
+
+                    if !self.settlement.settlement_loading {
+                        self.settlement.balances = Vec::new();
+                        self.settlement.transfers = Vec::new();
+                        if let Err(e) = self.tx_cmd.send(ServerCommand::ShowSettlement {
+                            user_id,
+                            group_id,
+                        }) {
+                            self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                        }
+                        self.settlement.settlement_loading = true;
+                        self.loading = true;
+                        self.process_backend_responses(ctx);
+                    }
+
+                    ui.label("Баланси:");
+                    for balance in &self.settlement.balances {
+                        let color = if balance.amount().is_negative() { Color32::RED } else { Color32::GREEN };
+                        ui.colored_label(color, format!("{}: {}", balance.username(), format_amount(balance.amount(), balance.currency())));
+                    }
+
+                    ui.separator();
+                    ui.label("Предложени плащания:");
+                    if self.settlement.transfers.is_empty() {
+                        ui.label("Няма нужда от плащания.");
+                    }
+                    for transfer in &self.settlement.transfers {
+                        ui.label(format!("{} плаща на {} {}", transfer.from_name(), transfer.to_name(), format_amount(transfer.amount(), transfer.currency())));
+                    }
+
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.label("Валута на групата:");
+                        ui.add(egui::TextEdit::singleline(&mut self.settlement.group_currency).desired_width(50.0));
+                        if ui.add(
+                            egui::Button::new(
+                                RichText::new("Смени валутата").color(Color32::WHITE)
+                            ).fill(Color32::from_rgb(0, 76, 153))
+                        ).clicked() {
+                            if let Err(e) = self.tx_cmd.send(ServerCommand::SetGroupCurrency {
+                                user_id,
+                                group_id,
+                                currency: self.settlement.group_currency.clone(),
+                            }) {
+                                self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                            }
+                            self.settlement.settlement_loading = false;
+                            self.loading = true;
+                        }
+                    });
+
+                    ui.add_enabled_ui(!self.loading, |ui| {
+                        ui.add_space(10.0);
+                        if !self.settlement.transfers.is_empty() && ui.add(
+                            egui::Button::new(
+                                RichText::new("Приложи").color(Color32::WHITE)
+                            ).fill(Color32::from_rgb(30, 60, 150))
+                        ).clicked() {
+                            if let Err(e) = self.tx_cmd.send(ServerCommand::ApplySettlement {
+                                user_id,
+                                group_id,
+                            }) {
+                                self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                            }
+                            self.settlement.settlement_loading = false;
+                            self.loading = true;
+                        }
+
+                        ui.add_space(5.0);
+                        if ui.add(
+                            egui::Button::new(
+                                RichText::new("Назад").color(Color32::WHITE)
+                            ).fill(Color32::from_rgb(0, 102, 0))
+                        ).clicked() {
+                            self.settlement.settlement_loading = false;
+                            self.screen = Screen::MyGroups(user_id);
+                        }
+                    });
+
+                    if self.loading {
+                        ui.separator();
+                        ui.label("Моля изчакайте...");
+                    }
+
+                    self.update_messages(ctx);
+                    self.show_messages(ui);
+            });
+        });
+    }
+
+    fn show_counterparties(&mut self, ctx: &egui::Context, user_id: i32) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::vertical()
+                .auto_shrink([false; 2])
+                .show(ui, |ui| {
+                    ui.heading("Сметки с хора");
+                    ui.add_space(10.0);
+
+                    if !self.settlement.counterparties_loading {
+                        self.settlement.counterparties = Vec::new();
+                        if let Err(e) = self.tx_cmd.send(ServerCommand::ShowCounterparties {
+                            user_id,
+                        }) {
+                            self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                        }
+                        self.settlement.counterparties_loading = true;
+                        self.loading = true;
+                        self.process_backend_responses(ctx);
+                    }
+
+                    for balance in &self.settlement.counterparties {
+                        ui.horizontal(|ui| {
+                            let text = if balance.amount().is_negative() {
+                                format!("Дължите на {} {} общо от всички групи", balance.username(), format_amount(-balance.amount(), balance.currency()))
+                            } else {
+                                format!("{} ви дължи {} общо от всички групи", balance.username(), format_amount(balance.amount(), balance.currency()))
+                            };
+                            ui.label(text);
+                            ui.separator();
+                            if ui.add(
+                                egui::Button::new(
+                                    RichText::new("Уреди сметките").color(Color32::WHITE)
+                                ).fill(Color32::from_rgb(30, 60, 150))
+                            ).clicked() {
+                                if let Err(e) = self.tx_cmd.send(ServerCommand::SettleUp {
+                                    user_id,
+                                    counterparty_id: balance.user_id(),
+                                }) {
+                                    self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                                }
+                                self.settlement.counterparties_loading = false;
+                            }
+                        });
+
+                        ui.separator();
+                    }
+
+                    ui.add_enabled_ui(!self.loading, |ui| {
+                        ui.add_space(10.0);
+                        if ui.add(
+                            egui::Button::new(
                                 RichText::new("Назад").color(Color32::WHITE)
                             ).fill(Color32::from_rgb(0, 102, 0))
                         ).clicked() {
                             let owner_id = user_id;
-                            if let Err(e) = self.tx_cmd.send(ServerCommand::GetUser { owner_id }){
+                            if let Err(e) = self.tx_cmd.send(ServerCommand::GetUser { owner_id }) {
                                 self.error_message = Some(format!("Неуспешно изпращане: {}", e));
                             }
-                            self.group_state.group_loading = false;
+                            self.settlement.counterparties_loading = false;
                             self.loading = true;
                             self.process_backend_responses(ctx);
                         }
@@ -668,69 +2378,149 @@ impl MyApp {
         });
     }
 
-    fn show_add_expenses(&mut self, ctx: &egui::Context, user_id: i32, group_id: i32) {
+    fn show_payment_history(&mut self, ctx: &egui::Context, user_id: i32, group_id: Option<i32>) {
         egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("Добавяне на разход");
+            egui::ScrollArea::vertical()
+                .auto_shrink([false; 2])
+                .show(ui, |ui| {
+                    ui.heading("История на плащанията");
+                    ui.add_space(10.0);
 
-            ui.add_enabled_ui(!self.loading, |ui| {
-                let mut amount_str = self.expenses.exp_amount.to_string();
-                ui.label("Сума:");
-                ui.text_edit_singleline(&mut amount_str);
-                if amount_str.is_empty() {
-                    self.error_message = Some(
-                        "Моля въведете сума.".to_string(),
-                    );
-                    self.error_time = Some(std::time::Instant::now());
-                }
-                if let Ok(parsed) = amount_str.parse::<f32>() {
-                    self.expenses.exp_amount = parsed;
-                }
+                    if !self.settlement.payment_history_loading {
+                        self.settlement.payment_history = Vec::new();
+                        let command = match group_id {
+                            Some(group_id) => ServerCommand::ShowGroupPayments { user_id, group_id },
+                            None => ServerCommand::ShowUserPayments { user_id },
+                        };
+                        if let Err(e) = self.tx_cmd.send(command) {
+                            self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                        }
+                        self.settlement.payment_history_loading = true;
+                        self.loading = true;
+                        self.process_backend_responses(ctx);
+                    }
 
-                ui.label("Описание:");
-                ui.text_edit_singleline(&mut self.expenses.exp_description);
+                    for payment in &self.settlement.payment_history {
+                        let status = if payment.is_confirmed() { "потвърдено" } else { "очаква потвърждение" };
+                        ui.label(format!(
+                            "{} → {}: {}\nНачин: {}\nДата на плащане: {}\nЗаписано: {}\nГрупа: {}\nСтатус: {}",
+                            payment.from_name(),
+                            payment.to_name(),
+                            format_amount(payment.amount(), payment.currency()),
+                            payment.method().label(),
+                            payment.paid_on(),
+                            payment.created_at(),
+                            payment.group_name(),
+                            status
+                        ));
+                        ui.separator();
+                    }
+
+                    ui.add_enabled_ui(!self.loading, |ui| {
+                        ui.add_space(10.0);
+                        if ui.add(
+                            egui::Button::new(
+                                RichText::new("Назад").color(Color32::WHITE)
+                            ).fill(Color32::from_rgb(0, 102, 0))
+                        ).clicked() {
+                            self.settlement.payment_history_loading = false;
+                            if group_id.is_some() {
+                                self.screen = Screen::MyGroups(user_id);
+                            } else {
+                                let owner_id = user_id;
+                                if let Err(e) = self.tx_cmd.send(ServerCommand::GetUser { owner_id }) {
+                                    self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                                }
+                                self.loading = true;
+                                self.process_backend_responses(ctx);
+                            }
+                        }
+                    });
 
-                ui.label("Крайна дата за изплащане:");
-                ui.text_edit_singleline(&mut self.expenses.exp_due_date);
+                    if self.loading {
+                        ui.separator();
+                        ui.label("Моля изчакайте...");
+                    }
 
-                ui.add_space(10.0);
+                    self.update_messages(ctx);
+                    self.show_messages(ui);
+            });
+        });
+    }
 
+    fn show_exchange_rates(&mut self, ctx: &egui::Context, user_id: i32) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::vertical()
+                .auto_shrink([false; 2])
+                .show(ui, |ui| {
+                    ui.heading("Валутни курсове");
+                    ui.add_space(10.0);
 
-                if ui.add(
-                    egui::Button::new(
-                        RichText::new("Добави разход").color(Color32::WHITE)
-                    ).fill(Color32::from_rgb(30, 60, 150))
-                ).clicked() {
-                    if let Err(e) = self.tx_cmd.send(ServerCommand::AddExpenses {
-                        user_id,
-                        group_id,
-                        amount: std::mem::take(&mut self.expenses.exp_amount),
-                        description: std::mem::take(&mut self.expenses.exp_description),
-                        due_date: std::mem::take(&mut self.expenses.exp_due_date),
-                    }) {
-                        self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                    if !self.settlement.rates_loading {
+                        self.settlement.rates = Vec::new();
+                        if let Err(e) = self.tx_cmd.send(ServerCommand::ShowRates) {
+                            self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                        }
+                        self.settlement.rates_loading = true;
+                        self.loading = true;
+                        self.process_backend_responses(ctx);
                     }
-                    self.loading = true;
-                }
 
-                ui.add_space(5.0);
-                if ui.add(
-                    egui::Button::new(
-                        RichText::new("Назад").color(Color32::WHITE)
-                    ).fill(Color32::from_rgb(0, 102, 0))
-                ).clicked() {
-                    self.screen = Screen::MyGroups(user_id);
-                }
+                    ui.label("Единици валута за 1 EUR:");
+                    for rate in &self.settlement.rates {
+                        ui.label(format!(
+                            "{}: {}.{:06} (към {})",
+                            rate.currency(),
+                            rate.rate() / RATE_SCALE,
+                            rate.rate() % RATE_SCALE,
+                            rate.rate_date()
+                        ));
+                    }
 
-                self.process_backend_responses(ctx);
-            });
+                    ui.separator();
+                    ui.add_enabled_ui(!self.loading, |ui| {
+                        ui.label("Зареждане от CSV файл (формат на ЕЦБ):");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(&mut self.settlement.rates_path).hint_text("eurofxref.csv"));
+                            if ui.add(
+                                egui::Button::new(
+                                    RichText::new("Зареди").color(Color32::WHITE)
+                                ).fill(Color32::from_rgb(30, 60, 150))
+                            ).clicked() {
+                                if let Err(e) = self.tx_cmd.send(ServerCommand::ImportRates {
+                                    path: self.settlement.rates_path.trim().to_string(),
+                                }) {
+                                    self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                                }
+                                self.settlement.rates_loading = false;
+                                self.loading = true;
+                            }
+                        });
 
-            if self.loading {
-                ui.separator();
-                ui.label("Моля изчакайте...");
-            }
+                        ui.add_space(10.0);
+                        if ui.add(
+                            egui::Button::new(
+                                RichText::new("Назад").color(Color32::WHITE)
+                            ).fill(Color32::from_rgb(0, 102, 0))
+                        ).clicked() {
+                            let owner_id = user_id;
+                            if let Err(e) = self.tx_cmd.send(ServerCommand::GetUser { owner_id }) {
+                                self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                            }
+                            self.settlement.rates_loading = false;
+                            self.loading = true;
+                            self.process_backend_responses(ctx);
+                        }
+                    });
 
-            self.update_messages(ctx);
-            self.show_messages(ui);
+                    if self.loading {
+                        ui.separator();
+                        ui.label("Моля изчакайте...");
+                    }
+
+                    self.update_messages(ctx);
+                    self.show_messages(ui);
+            });
         });
     }
 
@@ -764,12 +2554,22 @@ impl MyApp {
                     }
 
                     for debt_or_credit in &self.expenses.my_debts_or_credits {
+                        let debt_id = debt_or_credit.id();
                         ui.horizontal(|ui| {
+                            let amount = if debt_or_credit.paid().is_zero() {
+                                format_amount(debt_or_credit.amount(), debt_or_credit.currency())
+                            } else {
+                                format!(
+                                    "{} (остават {})",
+                                    format_amount(debt_or_credit.amount(), debt_or_credit.currency()),
+                                    format_amount(debt_or_credit.remaining(), debt_or_credit.currency())
+                                )
+                            };
                             ui.label(format!(
-                                "{}: {}\nСума: {:.2} лв.\nОписание: {}\nКрайна дата: {}\nГрупа: {}",
+                                "{}: {}\nСума: {}\nОписание: {}\nКрайна дата: {}\nГрупа: {}",
                                 user,
                                 debt_or_credit.username(),
-                                debt_or_credit.amount(),
+                                amount,
                                 debt_or_credit.description(),
                                 debt_or_credit.due_date(),
                                 debt_or_credit.group_name()
@@ -780,7 +2580,6 @@ impl MyApp {
                                     RichText::new("Потвърждаване на плащане").color(Color32::WHITE)
                                 ).fill(Color32::from_rgb(30, 60, 150))
                             ).clicked() {
-                                let debt_id = debt_or_credit.id();
                                 if let Err(e) = self.tx_cmd.send(ServerCommand::PaymentConfirmation {
                                     user_id,
                                     debt_id,
@@ -789,6 +2588,82 @@ impl MyApp {
                                 }
                                 self.expenses.debts_or_credits_loading = false;
                             }
+                            if !is_debt && ui.add(
+                                egui::Button::new(
+                                    RichText::new("Напомни").color(Color32::WHITE)
+                                ).fill(Color32::from_rgb(153, 76, 0))
+                            ).clicked() && let Err(e) = self.tx_cmd.send(ServerCommand::NudgeDebtor {
+                                user_id,
+                                debt_id,
+                            }) {
+                                self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                            }
+                        });
+
+                        for payment in debt_or_credit.payments() {
+                            ui.horizontal(|ui| {
+                                let status = if payment.is_confirmed() { "потвърдено" } else { "очаква потвърждение" };
+                                ui.label(format!("Плащане от {}: {}, {} ({})", payment.paid_on(), format_amount(payment.amount(), payment.currency()), payment.method().label(), status));
+
+                                let confirmed_by_me = if is_debt { payment.confirmed_by_debtor() } else { payment.confirmed_by_creditor() };
+                                if !confirmed_by_me && ui.add(
+                                    egui::Button::new(
+                                        RichText::new("Потвърди").color(Color32::WHITE)
+                                    ).fill(Color32::from_rgb(30, 60, 150))
+                                ).clicked() {
+                                    if let Err(e) = self.tx_cmd.send(ServerCommand::ConfirmPayment {
+                                        user_id,
+                                        payment_id: payment.id(),
+                                    }) {
+                                        self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                                    }
+                                    self.expenses.debts_or_credits_loading = false;
+                                }
+                            });
+                        }
+
+                        ui.horizontal(|ui| {
+                            ui.label("Частично плащане:");
+                            ui.add(egui::TextEdit::singleline(self.expenses.payment_amounts.entry(debt_id).or_default())
+                                .hint_text("Сума")
+                                .desired_width(80.0));
+                            ui.add(egui::TextEdit::singleline(self.expenses.payment_dates.entry(debt_id).or_default())
+                                .hint_text("ГГГГ-ММ-ДД")
+                                .desired_width(100.0));
+                            let method = self.expenses.payment_methods.entry(debt_id).or_default();
+                            egui::ComboBox::from_id_salt(("payment_method", debt_id))
+                                .selected_text(method.label())
+                                .show_ui(ui, |ui| {
+                                    for option in PaymentMethod::ALL {
+                                        ui.selectable_value(method, option, option.label());
+                                    }
+                                });
+                            if ui.add(
+                                egui::Button::new(
+                                    RichText::new("Запиши плащане").color(Color32::WHITE)
+                                ).fill(Color32::from_rgb(30, 60, 150))
+                            ).clicked() {
+                                match Money::parse(&self.expenses.payment_amounts[&debt_id]) {
+                                    Ok(amount) => {
+                                        let paid_on = self.expenses.payment_dates[&debt_id].trim().to_string();
+                                        let method = self.expenses.payment_methods[&debt_id];
+                                        if let Err(e) = self.tx_cmd.send(ServerCommand::RecordPayment {
+                                            user_id,
+                                            debt_id,
+                                            amount,
+                                            method,
+                                            paid_on,
+                                        }) {
+                                            self.error_message = Some(format!("Неуспешно изпращане: {}", e));
+                                        }
+                                        self.expenses.payment_amounts.remove(&debt_id);
+                                        self.expenses.payment_dates.remove(&debt_id);
+                                        self.expenses.payment_methods.remove(&debt_id);
+                                        self.expenses.debts_or_credits_loading = false;
+                                    }
+                                    Err(e) => self.error_message = Some(e),
+                                }
+                            }
                         });
 
                         ui.separator();
@@ -823,6 +2698,23 @@ impl MyApp {
     }
 }
 
-
-
-
+/// Draws `text` as a QR code on a white background, `module` points per square.
+fn show_qr_code(ui: &mut egui::Ui, text: &str, module: f32) {
+    let Ok(qr) = QrCode::encode_text(text, QrCodeEcc::Medium) else {
+        return;
+    };
+    let border = 4;
+    let side = (qr.size() + 2 * border) as f32 * module;
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(side, side), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+
+    painter.rect_filled(rect, 0.0, Color32::WHITE);
+    for y in 0..qr.size() {
+        for x in 0..qr.size() {
+            if qr.get_module(x, y) {
+                let min = rect.min + egui::vec2((x + border) as f32 * module, (y + border) as f32 * module);
+                painter.rect_filled(egui::Rect::from_min_size(min, egui::vec2(module, module)), 0.0, Color32::BLACK);
+            }
+        }
+    }
+}
@@ -1,12 +1,22 @@
 use std::sync::mpsc::{Sender, Receiver};
 use std::sync::mpsc;
 use std::thread;
-use crate::db::{init_db, register_user, login_user, create_group, search_users, get_user_by_id, get_user_groups, add_expenses, get_user_debts_or_credits, payment_confirmation, get_user_notifications};
-use crate::group::Group;
+use crate::db::{init_db, register_user, login_user, create_group_with_invites, search_users, get_user_by_id, get_user_groups, get_archived_groups, rename_group, set_group_archived, delete_group, add_expenses, edit_expense, delete_expense, get_group_members, get_group_expenses, get_user_debts_or_credits, get_group_balances, get_group_detail, suggest_settlement, apply_settlement, get_counterparty_balances, settle_up_with, payment_confirmation, record_partial_payment, confirm_partial_payment, get_user_payments, get_group_payments, set_group_currency, get_exchange_rates, import_exchange_rates, add_recurring_expense, get_group_recurring_expenses, set_recurring_status, materialize_recurring_expenses, get_user_notifications, get_archived_notifications, count_unread_notifications, set_notification_read, set_notification_archived, mark_all_notifications_read, get_reminder_days, set_reminder_days, nudge_debtor, get_notification_channels, set_notification_channel, get_notification_preferences, set_notification_preferences, invite_to_group, get_pending_invitations, respond_to_invitation, create_invite_code, join_group_with_code, remove_group_member, leave_group, request_debt_transfer, get_pending_debt_transfers, respond_to_debt_transfer, get_group_roles, set_member_role, transfer_group_ownership};
+use crate::group::{Group, GroupDetail, GroupRole};
+use crate::invitation::{DebtTransfer, Invitation};
 use crate::user::User;
-use crate::expenses::Expenses;
-use crate::notification::Notification;
-use std::sync::mpsc::RecvError;
+use crate::expenses::{Expense, Expenses};
+use crate::notification::{Notification, NotificationPreferences};
+use crate::payment::{Payment, PaymentMethod};
+use crate::money::Money;
+use crate::split::Split;
+use crate::settlement::{Balance, Transfer};
+use crate::currency::ExchangeRate;
+use crate::recurring::{RecurrenceInterval, RecurringExpense, RecurringStatus};
+use crate::scheduler::{send_notifications, Alert, Scheduler};
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::{Duration, Instant};
+use crate::delivery::{Channel, ChannelPreference};
 
 #[derive(Debug)]
 pub enum ServerCommand {
@@ -20,13 +30,71 @@ pub enum ServerCommand {
         password: String,
     },
     SearchUsers { query: String },
-    CreateGroup { name: String, owner_id: i32, members: Vec<i32> },
+    CreateGroup { name: String, owner_id: i32, members: Vec<i32>, currency: String },
     GetUser {owner_id: i32},
     ShowGroups {user_id: i32},
-    AddExpenses { user_id: i32, group_id: i32, amount: f32, description: String, due_date: String },
+    AddExpenses { user_id: i32, group_id: i32, amount: Money, currency: String, description: String, due_date: String, split: Split },
+    EditExpense { user_id: i32, expense_id: i32, amount: Money, currency: String, description: String, due_date: String, split: Split },
+    SetGroupCurrency { user_id: i32, group_id: i32, currency: String },
+    ShowRates,
+    ImportRates { path: String },
+    DeleteExpense { user_id: i32, expense_id: i32 },
+    AddRecurringExpense {
+        user_id: i32,
+        group_id: i32,
+        amount: Money,
+        currency: String,
+        description: String,
+        interval: RecurrenceInterval,
+        start_date: String,
+        end_date: String,
+        split: Split,
+    },
+    ShowRecurringExpenses { user_id: i32, group_id: i32 },
+    SetRecurringStatus { user_id: i32, recurring_id: i32, status: RecurringStatus },
+    ShowGroupMembers { user_id: i32, group_id: i32 },
+    ShowGroupDetail { user_id: i32, group_id: i32 },
+    ShowArchivedGroups { user_id: i32 },
+    RenameGroup { user_id: i32, group_id: i32, name: String },
+    SetGroupArchived { user_id: i32, group_id: i32, archived: bool },
+    DeleteGroup { user_id: i32, group_id: i32 },
+    InviteToGroup { user_id: i32, group_id: i32, invitee_id: i32 },
+    ShowInvitations { user_id: i32 },
+    RespondToInvitation { user_id: i32, invitation_id: i32, accept: bool },
+    CreateInviteCode { user_id: i32, group_id: i32 },
+    JoinGroupWithCode { user_id: i32, code: String },
+    RemoveGroupMember { user_id: i32, group_id: i32, member_id: i32 },
+    LeaveGroup { user_id: i32, group_id: i32 },
+    RequestDebtTransfer { user_id: i32, group_id: i32, to_id: i32 },
+    ShowDebtTransfers { user_id: i32 },
+    RespondToDebtTransfer { user_id: i32, transfer_id: i32, accept: bool },
+    ShowGroupRoles { user_id: i32, group_id: i32 },
+    SetMemberRole { user_id: i32, group_id: i32, member_id: i32, role: GroupRole },
+    TransferOwnership { user_id: i32, group_id: i32, new_owner_id: i32 },
+    ShowGroupExpenses { user_id: i32, group_id: i32 },
     ShowDebtsOrCredits { user_id: i32 , is_debt: bool},
+    ShowSettlement { user_id: i32, group_id: i32 },
+    ApplySettlement { user_id: i32, group_id: i32 },
+    ShowCounterparties { user_id: i32 },
+    SettleUp { user_id: i32, counterparty_id: i32 },
     PaymentConfirmation { user_id: i32, debt_id: i32 },
+    NudgeDebtor { user_id: i32, debt_id: i32 },
+    RecordPayment { user_id: i32, debt_id: i32, amount: Money, method: PaymentMethod, paid_on: String },
+    ConfirmPayment { user_id: i32, payment_id: i32 },
+    ShowUserPayments { user_id: i32 },
+    ShowGroupPayments { user_id: i32, group_id: i32 },
     ShowNotification { user_id: i32 },
+    ShowArchivedNotifications { user_id: i32 },
+    CountUnreadNotifications { user_id: i32 },
+    SetNotificationRead { user_id: i32, notification_id: i32, read: bool },
+    SetNotificationArchived { user_id: i32, notification_id: i32, archived: bool },
+    MarkAllNotificationsRead { user_id: i32 },
+    GetReminderDays { user_id: i32 },
+    SetReminderDays { user_id: i32, days: Vec<i64> },
+    GetNotificationChannels { user_id: i32 },
+    SetNotificationChannel { user_id: i32, channel: Channel, enabled: bool, target: String },
+    GetNotificationPreferences { user_id: i32 },
+    SetNotificationPreferences { user_id: i32, preferences: NotificationPreferences },
 }
 
 #[derive(Debug)]
@@ -36,8 +104,26 @@ pub enum ServerResponse {
     User(User),
     Users(Vec<User>),
     Groups(Vec<Group>),
+    GroupMembers(Vec<User>),
+    GroupDetail(GroupDetail),
+    GroupRoles(Vec<(i32, GroupRole)>),
+    Invitations(Vec<Invitation>),
+    DebtTransfers(Vec<DebtTransfer>),
+    InviteCode(String),
     Expenses(Vec<Expenses>),
+    GroupExpenses(Vec<Expense>),
     Notifications(Vec<Notification>),
+    UnreadCount(i64),
+    ReminderDays(Vec<i64>),
+    NotificationChannels(Vec<ChannelPreference>),
+    NotificationPreferences(NotificationPreferences),
+    Settlement { balances: Vec<Balance>, transfers: Vec<Transfer> },
+    Counterparties(Vec<Balance>),
+    Payments(Vec<Payment>),
+    Rates(Vec<ExchangeRate>),
+    RecurringExpenses(Vec<RecurringExpense>),
+    /// Pushed by the scheduler without a command asking for it.
+    Alerts(Vec<Alert>),
 }
 
 pub fn start_backend() -> (Sender<ServerCommand>, Receiver<ServerResponse>) {
@@ -46,9 +132,27 @@ pub fn start_backend() -> (Sender<ServerCommand>, Receiver<ServerResponse>) {
 
     thread::spawn(move || {
         let conn = init_db().expect("Failed to initialize DB");
+        let mut scheduler = Scheduler::with_default_jobs(Instant::now());
+
+        // Email and webhook deliveries wait on the network, so they run on their
+        // own connection and thread instead of holding up commands.
+        let delivery_conn = init_db().expect("Failed to initialize DB");
+        thread::spawn(move || {
+            let mut deliveries = Scheduler::default()
+                .every("notification delivery", Duration::from_secs(60), send_notifications, Instant::now());
+            loop {
+                deliveries.run_due(&delivery_conn, Instant::now());
+                thread::sleep(deliveries.time_until_next(Instant::now()));
+            }
+        });
 
         loop {
-            match rx_cmd.recv() {
+            let alerts = scheduler.run_due(&conn, Instant::now());
+            if !alerts.is_empty() && let Err(e) = tx_resp.send(ServerResponse::Alerts(alerts)) {
+                eprintln!("Failed to send alerts: {}", e);
+            }
+
+            match rx_cmd.recv_timeout(scheduler.time_until_next(Instant::now())) {
                 Ok(cmd) => {
                     let response = match cmd {
                         ServerCommand::Register { username, email, password } => {
@@ -66,9 +170,9 @@ pub fn start_backend() -> (Sender<ServerCommand>, Receiver<ServerResponse>) {
                                 .map(ServerResponse::Users)
                                 .unwrap_or_else(ServerResponse::Err)
                         }
-                        ServerCommand::CreateGroup { name, owner_id, members } => {
-                            create_group(&conn, &name, owner_id, &members)
-                                .map(|_| ServerResponse::Ok("Групата е създадена успешно!".into()))
+                        ServerCommand::CreateGroup { name, owner_id, members, currency } => {
+                            create_group_with_invites(&conn, &name, owner_id, &currency, &members)
+                                .map(|_| ServerResponse::Ok("Групата е създадена. Поканите са изпратени.".into()))
                                 .unwrap_or_else(ServerResponse::Err)
                         }
                         ServerCommand::GetUser { owner_id } => {
@@ -81,33 +185,281 @@ pub fn start_backend() -> (Sender<ServerCommand>, Receiver<ServerResponse>) {
                                 .map(ServerResponse::Groups)
                                 .unwrap_or_else(ServerResponse::Err)
                         }
-                        ServerCommand::AddExpenses { user_id, group_id, amount, description, due_date } => {
-                            add_expenses(&conn, user_id, group_id, amount, &description, &due_date)
+                        ServerCommand::AddExpenses { user_id, group_id, amount, currency, description, due_date, split } => {
+                            add_expenses(&conn, user_id, group_id, amount, Some(&currency), &description, &due_date, &split)
                                 .map(|_| ServerResponse::Ok("Успешно добавихте разход!".into()))
                                 .unwrap_or_else(ServerResponse::Err)
                         }
+                        ServerCommand::EditExpense { user_id, expense_id, amount, currency, description, due_date, split } => {
+                            edit_expense(&conn, user_id, expense_id, amount, Some(&currency), &description, &due_date, &split)
+                                .map(|_| ServerResponse::Ok("Разходът е променен.".into()))
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::SetGroupCurrency { user_id, group_id, currency } => {
+                            set_group_currency(&conn, user_id, group_id, &currency)
+                                .map(|_| ServerResponse::Ok("Валутата на групата е променена.".into()))
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::ShowRates => {
+                            get_exchange_rates(&conn)
+                                .map(ServerResponse::Rates)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::ImportRates { path } => {
+                            std::fs::read_to_string(&path)
+                                .map_err(|e| format!("Файлът не може да бъде прочетен: {}", e))
+                                .and_then(|csv| import_exchange_rates(&conn, &csv))
+                                .map(|count| ServerResponse::Ok(format!("Заредени са {} курса.", count)))
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::DeleteExpense { user_id, expense_id } => {
+                            delete_expense(&conn, user_id, expense_id)
+                                .map(|_| ServerResponse::Ok("Разходът е изтрит.".into()))
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::AddRecurringExpense { user_id, group_id, amount, currency, description, interval, start_date, end_date, split } => {
+                            let end_date = Some(end_date.trim()).filter(|date| !date.is_empty());
+                            add_recurring_expense(&conn, user_id, group_id, amount, Some(&currency), &description, interval, &start_date, end_date, &split)
+                                .and_then(|_| materialize_recurring_expenses(&conn))
+                                .map(|_| ServerResponse::Ok("Повтарящият се разход е добавен.".into()))
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::ShowRecurringExpenses { user_id, group_id } => {
+                            get_group_recurring_expenses(&conn, user_id, group_id)
+                                .map(ServerResponse::RecurringExpenses)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::SetRecurringStatus { user_id, recurring_id, status } => {
+                            set_recurring_status(&conn, user_id, recurring_id, status)
+                                .map(|_| ServerResponse::Ok(format!("Статус: {}.", status.label())))
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::ShowGroupMembers { user_id, group_id } => {
+                            get_group_members(&conn, user_id, group_id)
+                                .map(ServerResponse::GroupMembers)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::ShowGroupDetail { user_id, group_id } => {
+                            get_group_detail(&conn, user_id, group_id)
+                                .map(ServerResponse::GroupDetail)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::ShowArchivedGroups { user_id } => {
+                            get_archived_groups(&conn, user_id)
+                                .map(ServerResponse::Groups)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::RenameGroup { user_id, group_id, name } => {
+                            rename_group(&conn, user_id, group_id, &name)
+                                .map(|_| ServerResponse::Ok("Групата е преименувана.".to_string()))
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::SetGroupArchived { user_id, group_id, archived } => {
+                            set_group_archived(&conn, user_id, group_id, archived)
+                                .map(|_| ServerResponse::Ok(if archived { "Групата е архивирана." } else { "Групата е възстановена." }.to_string()))
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::DeleteGroup { user_id, group_id } => {
+                            delete_group(&conn, user_id, group_id)
+                                .map(|_| ServerResponse::Ok("Групата е изтрита.".to_string()))
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::InviteToGroup { user_id, group_id, invitee_id } => {
+                            invite_to_group(&conn, user_id, group_id, invitee_id)
+                                .map(ServerResponse::Ok)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::ShowInvitations { user_id } => {
+                            get_pending_invitations(&conn, user_id)
+                                .map(ServerResponse::Invitations)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::RespondToInvitation { user_id, invitation_id, accept } => {
+                            respond_to_invitation(&conn, user_id, invitation_id, accept)
+                                .map(ServerResponse::Ok)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::CreateInviteCode { user_id, group_id } => {
+                            create_invite_code(&conn, user_id, group_id)
+                                .map(ServerResponse::InviteCode)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::JoinGroupWithCode { user_id, code } => {
+                            join_group_with_code(&conn, user_id, &code)
+                                .map(ServerResponse::Ok)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::RemoveGroupMember { user_id, group_id, member_id } => {
+                            remove_group_member(&conn, user_id, group_id, member_id)
+                                .map(ServerResponse::Ok)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::LeaveGroup { user_id, group_id } => {
+                            leave_group(&conn, user_id, group_id)
+                                .map(ServerResponse::Ok)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::RequestDebtTransfer { user_id, group_id, to_id } => {
+                            request_debt_transfer(&conn, user_id, group_id, to_id)
+                                .map(ServerResponse::Ok)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::ShowDebtTransfers { user_id } => {
+                            get_pending_debt_transfers(&conn, user_id)
+                                .map(ServerResponse::DebtTransfers)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::RespondToDebtTransfer { user_id, transfer_id, accept } => {
+                            respond_to_debt_transfer(&conn, user_id, transfer_id, accept)
+                                .map(ServerResponse::Ok)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::ShowGroupRoles { user_id, group_id } => {
+                            get_group_roles(&conn, user_id, group_id)
+                                .map(ServerResponse::GroupRoles)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::SetMemberRole { user_id, group_id, member_id, role } => {
+                            set_member_role(&conn, user_id, group_id, member_id, role)
+                                .map(|_| ServerResponse::Ok(format!("Новата роля е {}.", role.label())))
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::TransferOwnership { user_id, group_id, new_owner_id } => {
+                            transfer_group_ownership(&conn, user_id, group_id, new_owner_id)
+                                .map(|_| ServerResponse::Ok("Собствеността е прехвърлена.".to_string()))
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::ShowGroupExpenses { user_id, group_id } => {
+                            get_group_expenses(&conn, user_id, group_id)
+                                .map(ServerResponse::GroupExpenses)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
                         ServerCommand::ShowDebtsOrCredits { user_id, is_debt } => {
                             get_user_debts_or_credits(&conn, user_id, is_debt)
                                 .map(ServerResponse::Expenses)
                                 .unwrap_or_else(ServerResponse::Err)
                         }
+                        ServerCommand::ShowSettlement { user_id, group_id } => {
+                            get_group_balances(&conn, user_id, group_id)
+                                .and_then(|balances| {
+                                    let transfers = suggest_settlement(&conn, user_id, group_id)?;
+                                    Ok(ServerResponse::Settlement { balances, transfers })
+                                })
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::ApplySettlement { user_id, group_id } => {
+                            apply_settlement(&conn, user_id, group_id)
+                                .map(|_| ServerResponse::Ok("Дълговете в групата са преразпределени.".into()))
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::ShowCounterparties { user_id } => {
+                            get_counterparty_balances(&conn, user_id)
+                                .map(ServerResponse::Counterparties)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::SettleUp { user_id, counterparty_id } => {
+                            settle_up_with(&conn, user_id, counterparty_id)
+                                .map(ServerResponse::Ok)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
                         ServerCommand::PaymentConfirmation { user_id, debt_id } => {
                             payment_confirmation(&conn, user_id, debt_id)
                                 .map(ServerResponse::Ok)
                                 .unwrap_or_else(ServerResponse::Err)
                         }
+                        ServerCommand::NudgeDebtor { user_id, debt_id } => {
+                            nudge_debtor(&conn, user_id, debt_id)
+                                .map(ServerResponse::Ok)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::RecordPayment { user_id, debt_id, amount, method, paid_on } => {
+                            record_partial_payment(&conn, user_id, debt_id, amount, method, &paid_on)
+                                .map(ServerResponse::Ok)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::ConfirmPayment { user_id, payment_id } => {
+                            confirm_partial_payment(&conn, user_id, payment_id)
+                                .map(ServerResponse::Ok)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::ShowUserPayments { user_id } => {
+                            get_user_payments(&conn, user_id)
+                                .map(ServerResponse::Payments)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::ShowGroupPayments { user_id, group_id } => {
+                            get_group_payments(&conn, user_id, group_id)
+                                .map(ServerResponse::Payments)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
                         ServerCommand::ShowNotification { user_id } => {
                             get_user_notifications(&conn, user_id)
                                 .map(ServerResponse::Notifications)
                                 .unwrap_or_else(ServerResponse::Err)
                         }
+                        ServerCommand::ShowArchivedNotifications { user_id } => {
+                            get_archived_notifications(&conn, user_id)
+                                .map(ServerResponse::Notifications)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::CountUnreadNotifications { user_id } => {
+                            count_unread_notifications(&conn, user_id)
+                                .map(ServerResponse::UnreadCount)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::SetNotificationRead { user_id, notification_id, read } => {
+                            set_notification_read(&conn, user_id, notification_id, read)
+                                .map(|_| ServerResponse::Ok(if read { "Известието е прочетено." } else { "Известието е отбелязано като непрочетено." }.into()))
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::SetNotificationArchived { user_id, notification_id, archived } => {
+                            set_notification_archived(&conn, user_id, notification_id, archived)
+                                .map(|_| ServerResponse::Ok(if archived { "Известието е архивирано." } else { "Известието е върнато във входящите." }.into()))
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::MarkAllNotificationsRead { user_id } => {
+                            mark_all_notifications_read(&conn, user_id)
+                                .map(|count| ServerResponse::Ok(format!("Прочетени известия: {}.", count)))
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::GetReminderDays { user_id } => {
+                            get_reminder_days(&conn, user_id)
+                                .map(ServerResponse::ReminderDays)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::SetReminderDays { user_id, days } => {
+                            set_reminder_days(&conn, user_id, &days)
+                                .map(|_| ServerResponse::Ok("Напомнянията са запазени.".into()))
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::GetNotificationChannels { user_id } => {
+                            get_notification_channels(&conn, user_id)
+                                .map(ServerResponse::NotificationChannels)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::SetNotificationChannel { user_id, channel, enabled, target } => {
+                            set_notification_channel(&conn, user_id, channel, enabled, &target)
+                                .map(|_| ServerResponse::Ok(format!("Каналът „{}“ е запазен.", channel.label())))
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::GetNotificationPreferences { user_id } => {
+                            get_notification_preferences(&conn, user_id)
+                                .map(ServerResponse::NotificationPreferences)
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
+                        ServerCommand::SetNotificationPreferences { user_id, preferences } => {
+                            set_notification_preferences(&conn, user_id, &preferences)
+                                .map(|_| ServerResponse::Ok("Настройките на известията са запазени.".into()))
+                                .unwrap_or_else(ServerResponse::Err)
+                        }
                     };
 
                     if let Err(e) = tx_resp.send(response) {
                         eprintln!("Failed to send response: {}", e);
                     }
                 }
-                Err(RecvError) => {
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
                     eprintln!("Command channel disconnected. Shutting down server loop.");
                     break;
                 }
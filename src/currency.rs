@@ -0,0 +1,171 @@
+use crate::money::Money;
+
+/// Currency of groups and expenses that do not name one.
+pub const DEFAULT_CURRENCY: &str = "EUR";
+
+/// Leva per euro in millionths, the rate fixed when Bulgaria adopted the euro.
+pub const BGN_PER_EUR: i64 = 1_955_830;
+
+/// Exchange rates are quoted against the euro, as in the ECB reference rates.
+pub const BASE_CURRENCY: &str = "EUR";
+
+/// Rates are stored as units of a currency per one euro, in millionths.
+pub const RATE_SCALE: i64 = 1_000_000;
+
+/// How many units of `currency` one euro bought on `rate_date`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExchangeRate {
+    currency: String,
+    rate_date: String,
+    rate: i64,
+}
+
+impl ExchangeRate {
+    pub fn new(currency: String, rate_date: String, rate: i64) -> Self {
+        Self { currency, rate_date, rate }
+    }
+
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+
+    pub fn rate_date(&self) -> &str {
+        &self.rate_date
+    }
+
+    /// The rate in millionths, see `RATE_SCALE`.
+    pub fn rate(&self) -> i64 {
+        self.rate
+    }
+}
+
+/// Checks that `input` is a three-letter currency code such as `EUR` and returns it in upper case.
+pub fn parse_code(input: &str) -> Result<String, String> {
+    let code = input.trim().to_ascii_uppercase();
+    if code.len() != 3 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(format!("Невалиден код на валута: {}.", input.trim()));
+    }
+    Ok(code)
+}
+
+/// Parses a rate such as `1.9558` into millionths.
+pub fn parse_rate(input: &str) -> Result<i64, String> {
+    let invalid = || format!("Невалиден курс: {}.", input.trim());
+    let input = input.trim();
+
+    let (whole, fraction) = input.split_once('.').unwrap_or((input, ""));
+    if whole.is_empty() || fraction.len() > 6
+        || !whole.chars().all(|c| c.is_ascii_digit())
+        || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+
+    let whole: i64 = whole.parse().map_err(|_| invalid())?;
+    let fraction: i64 = format!("{:0<6}", fraction).parse().map_err(|_| invalid())?;
+    let rate = whole.checked_mul(RATE_SCALE).and_then(|w| w.checked_add(fraction)).ok_or_else(invalid)?;
+
+    if rate == 0 {
+        return Err(invalid());
+    }
+    Ok(rate)
+}
+
+/// Reads reference rates in the ECB CSV format: a `Date` column followed by one
+/// column per currency, with one row per day. Dates may be ISO (`2025-10-17`)
+/// or written out (`17 October 2025`); empty and `N/A` cells are skipped.
+pub fn parse_ecb_csv(content: &str) -> Result<Vec<ExchangeRate>, String> {
+    let mut lines = content.lines().filter(|line| !line.trim().is_empty());
+
+    let header = lines.next().ok_or_else(|| "Файлът с курсове е празен.".to_string())?;
+    let columns: Vec<&str> = header.split(',').map(str::trim).collect();
+    if !columns.first().is_some_and(|c| c.eq_ignore_ascii_case("date")) {
+        return Err("Първата колона във файла с курсове трябва да е Date.".to_string());
+    }
+
+    let mut rates = Vec::new();
+    for line in lines {
+        let cells: Vec<&str> = line.split(',').map(str::trim).collect();
+        let rate_date = parse_date(cells[0])?;
+
+        for (column, cell) in columns.iter().zip(&cells).skip(1) {
+            if column.is_empty() || cell.is_empty() || cell.eq_ignore_ascii_case("N/A") {
+                continue;
+            }
+            rates.push(ExchangeRate::new(parse_code(column)?, rate_date.clone(), parse_rate(cell)?));
+        }
+    }
+
+    if rates.is_empty() {
+        return Err("Във файла няма курсове.".to_string());
+    }
+    Ok(rates)
+}
+
+fn parse_date(input: &str) -> Result<String, String> {
+    const MONTHS: [&str; 12] = [
+        "january", "february", "march", "april", "may", "june",
+        "july", "august", "september", "october", "november", "december",
+    ];
+    let invalid = || format!("Невалидна дата във файла с курсове: {}.", input);
+
+    let (year, month, day) = match input.split('-').collect::<Vec<_>>()[..] {
+        [year, month, day] if year.len() == 4 && month.len() == 2 && day.len() == 2 => (
+            year.parse::<u32>().map_err(|_| invalid())?,
+            month.parse::<u32>().map_err(|_| invalid())?,
+            day.parse::<u32>().map_err(|_| invalid())?,
+        ),
+        _ => {
+            let parts: Vec<&str> = input.split_whitespace().collect();
+            let [day, month, year] = parts[..] else {
+                return Err(invalid());
+            };
+            let month = MONTHS.iter()
+                .position(|m| m.eq_ignore_ascii_case(month))
+                .ok_or_else(invalid)? + 1;
+            (year.parse().map_err(|_| invalid())?, month as u32, day.parse().map_err(|_| invalid())?)
+        }
+    };
+
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return Err(invalid());
+    }
+    Ok(format!("{:04}-{:02}-{:02}", year, month, day))
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Converts `amount` from a currency quoted at `from_rate` to one quoted at
+/// `to_rate`, rounding half away from zero to whole minor units.
+pub fn convert(amount: Money, from_rate: i64, to_rate: i64) -> Money {
+    if from_rate == to_rate {
+        return amount;
+    }
+
+    let numerator = amount.minor() as i128 * to_rate as i128;
+    let denominator = from_rate as i128;
+    let mut result = numerator / denominator;
+    if 2 * (numerator % denominator).abs() >= denominator {
+        result += numerator.signum();
+    }
+
+    Money::from_minor(result as i64)
+}
+
+/// Shows an amount with the currency's usual sign, or its code when it has none.
+pub fn format_amount(amount: Money, currency: &str) -> String {
+    let symbol = match currency {
+        "BGN" => "лв.",
+        "EUR" => "€",
+        "USD" => "$",
+        "GBP" => "£",
+        other => other,
+    };
+    format!("{} {}", amount, symbol)
+}
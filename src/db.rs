@@ -1,17 +1,34 @@
+use std::collections::HashMap;
 use rusqlite::{params, Connection, Result};
 use argon2::{Argon2, PasswordHasher};
 use password_hash::{SaltString, PasswordHash, PasswordVerifier};
 use rand_core::OsRng;
 use regex::Regex;
 use crate::user::{User};
-use crate::group::Group;
+use crate::group::{Group, GroupActivity, GroupDetail, GroupRole};
 use rusqlite::OptionalExtension;
-use crate::expenses::Expenses;
-use crate::notification::Notification;
+use crate::expenses::{Expense, ExpenseShare, Expenses};
+use crate::notification::{parse_reminder_days, parse_time_of_day, Notification, NotificationKind, NotificationPreferences, DEFAULT_DIGEST_TIME, MAX_REMINDER_DAYS, NUDGE_INTERVAL_HOURS};
+use crate::payment::{Payment, PaymentMethod};
+use crate::money::Money;
+use crate::split::{split_by_weight, Split, SplitMode};
+use crate::settlement::{simplify, Balance, Transfer};
+use crate::recurring::{RecurrenceInterval, RecurringExpense, RecurringStatus};
+use crate::delivery::{retry_delay, Channel, ChannelPreference, Delivery, DeliveryChannel, MAX_DELIVERY_ATTEMPTS};
+use crate::invitation::{generate_invite_code, parse_invite_code, DebtTransfer, Invitation, InvitationStatus, INVITE_CODE_VALID_DAYS};
+use crate::currency::{convert, format_amount, parse_code, parse_ecb_csv, ExchangeRate, BGN_PER_EUR, RATE_SCALE};
 
 pub fn init_db() -> Result<Connection> {
     let conn = Connection::open("Database.db")?;
+    // The backend and the delivery thread each hold a connection.
+    conn.busy_timeout(std::time::Duration::from_secs(5))?;
+    init_schema(&conn)?;
 
+    Ok(conn)
+}
+
+/// Creates the original tables and then brings them up to date with `MIGRATIONS`.
+pub fn init_schema(conn: &Connection) -> Result<()> {
     conn.execute_batch(
         "
         CREATE TABLE IF NOT EXISTS users (
@@ -65,7 +82,648 @@ pub fn init_db() -> Result<Connection> {
       "
     )?;
 
-    Ok(conn)
+    run_migrations(conn)
+}
+
+/// Schema changes applied in order on top of the original tables.
+/// `PRAGMA user_version` stores how many of them a database has already seen.
+const MIGRATIONS: &[fn(&Connection) -> Result<()>] = &[
+    migrate_debt_amounts_to_minor_units,
+    migrate_expense_records,
+    migrate_expense_deletion,
+    migrate_partial_payments,
+    migrate_payment_ledger,
+    migrate_currencies,
+    migrate_bgn_to_eur,
+    migrate_recurring_expenses,
+    migrate_notification_events,
+    migrate_notification_inbox,
+    migrate_reminder_days,
+    migrate_debt_nudges,
+    migrate_delivery_channels,
+    migrate_notification_preferences,
+    migrate_group_roles,
+    migrate_group_invitations,
+    migrate_group_archive,
+    migrate_digest_time,
+    migrate_debt_transfers,
+    migrate_debt_netting,
+    migrate_debt_replacements,
+    migrate_expense_group_currency,
+    migrate_debt_transfer_links,
+];
+
+fn run_migrations(conn: &Connection) -> Result<()> {
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    // Table rebuilds drop and recreate tables that other rows point to,
+    // so foreign keys are only enforced again once every migration has been applied.
+    let foreign_keys: bool = conn.query_row("PRAGMA foreign_keys", [], |row| row.get(0))?;
+    conn.pragma_update(None, "foreign_keys", false)?;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let tx = conn.unchecked_transaction()?;
+        migration(&tx)?;
+        tx.pragma_update(None, "user_version", index as i64 + 1)?;
+        tx.commit()?;
+    }
+
+    conn.pragma_update(None, "foreign_keys", foreign_keys)
+}
+
+fn migrate_debt_amounts_to_minor_units(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE debts_minor (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            from_id INTEGER NOT NULL,
+            to_id INTEGER NOT NULL,
+            amount INTEGER NOT NULL,
+            group_id INTEGER NOT NULL,
+            due_date TEXT NOT NULL,
+            description TEXT NOT NULL,
+            confirmed_by_debtor BOOLEAN DEFAULT 0,
+            confirmed_by_creditor BOOLEAN DEFAULT 0,
+            settled BOOLEAN DEFAULT 0,
+            FOREIGN KEY(from_id) REFERENCES users(id),
+            FOREIGN KEY(to_id) REFERENCES users(id),
+            FOREIGN KEY(group_id) REFERENCES groups(id)
+        );
+
+        INSERT INTO debts_minor (id, from_id, to_id, amount, group_id, due_date, description,
+                                 confirmed_by_debtor, confirmed_by_creditor, settled)
+        SELECT id, from_id, to_id, CAST(ROUND(amount * 100) AS INTEGER), group_id, due_date, description,
+               confirmed_by_debtor, confirmed_by_creditor, settled
+        FROM debts;
+
+        DROP TABLE debts;
+        ALTER TABLE debts_minor RENAME TO debts;
+        "
+    )
+}
+
+fn migrate_expense_records(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS expenses (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            group_id INTEGER NOT NULL,
+            payer_id INTEGER NOT NULL,
+            amount INTEGER NOT NULL,
+            description TEXT NOT NULL,
+            due_date TEXT NOT NULL,
+            split_mode TEXT NOT NULL,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(group_id) REFERENCES groups(id),
+            FOREIGN KEY(payer_id) REFERENCES users(id)
+        );
+
+        CREATE TABLE IF NOT EXISTS expense_shares (
+            expense_id INTEGER NOT NULL,
+            user_id INTEGER NOT NULL,
+            split_value INTEGER NOT NULL,
+            amount INTEGER NOT NULL,
+            PRIMARY KEY (expense_id, user_id),
+            FOREIGN KEY(expense_id) REFERENCES expenses(id),
+            FOREIGN KEY(user_id) REFERENCES users(id)
+        );
+
+        CREATE TABLE IF NOT EXISTS debt_expenses (
+            debt_id INTEGER NOT NULL,
+            expense_id INTEGER NOT NULL,
+            PRIMARY KEY (debt_id, expense_id),
+            FOREIGN KEY(debt_id) REFERENCES debts(id),
+            FOREIGN KEY(expense_id) REFERENCES expenses(id)
+        );
+        "
+    )
+}
+
+fn migrate_expense_deletion(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE expenses ADD COLUMN deleted BOOLEAN DEFAULT 0;")
+}
+
+fn migrate_partial_payments(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        ALTER TABLE debts ADD COLUMN paid INTEGER NOT NULL DEFAULT 0;
+
+        CREATE TABLE IF NOT EXISTS payments (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            debt_id INTEGER NOT NULL,
+            amount INTEGER NOT NULL,
+            paid_on TEXT NOT NULL,
+            confirmed_by_debtor BOOLEAN DEFAULT 0,
+            confirmed_by_creditor BOOLEAN DEFAULT 0,
+            FOREIGN KEY(debt_id) REFERENCES debts(id)
+        );
+        "
+    )
+}
+
+fn migrate_payment_ledger(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE payments_ledger (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            debt_id INTEGER NOT NULL,
+            amount INTEGER NOT NULL,
+            method TEXT NOT NULL DEFAULT 'other',
+            paid_on TEXT NOT NULL,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+            confirmed_by_debtor BOOLEAN DEFAULT 0,
+            confirmed_by_creditor BOOLEAN DEFAULT 0,
+            FOREIGN KEY(debt_id) REFERENCES debts(id)
+        );
+
+        INSERT INTO payments_ledger (id, debt_id, amount, paid_on, created_at, confirmed_by_debtor, confirmed_by_creditor)
+        SELECT id, debt_id, amount, paid_on, paid_on, confirmed_by_debtor, confirmed_by_creditor
+        FROM payments;
+
+        DROP TABLE payments;
+        ALTER TABLE payments_ledger RENAME TO payments;
+        "
+    )
+}
+
+/// Everything recorded so far was in leva. Debts keep their own currency so that
+/// settled history still reads correctly after a group changes its currency.
+fn migrate_currencies(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        ALTER TABLE groups ADD COLUMN currency TEXT NOT NULL DEFAULT 'BGN';
+        ALTER TABLE debts ADD COLUMN currency TEXT NOT NULL DEFAULT 'BGN';
+        ALTER TABLE expenses ADD COLUMN currency TEXT NOT NULL DEFAULT 'BGN';
+        ALTER TABLE expenses ADD COLUMN group_amount INTEGER;
+        UPDATE expenses SET group_amount = amount;
+
+        CREATE TABLE IF NOT EXISTS exchange_rates (
+            currency TEXT NOT NULL,
+            rate_date TEXT NOT NULL,
+            rate INTEGER NOT NULL,
+            PRIMARY KEY (currency, rate_date)
+        );
+
+        INSERT OR IGNORE INTO exchange_rates (currency, rate_date, rate) VALUES ('EUR', '1999-01-01', 1000000);
+        INSERT OR IGNORE INTO exchange_rates (currency, rate_date, rate) VALUES ('BGN', '1999-01-01', 1955830);
+        "
+    )
+}
+
+/// Moves all data kept in leva to euro and makes euro the default for new groups.
+fn migrate_bgn_to_eur(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS currency_conversions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            table_name TEXT NOT NULL,
+            row_id INTEGER NOT NULL,
+            column_name TEXT NOT NULL,
+            original_amount INTEGER NOT NULL,
+            original_currency TEXT NOT NULL,
+            converted_amount INTEGER NOT NULL,
+            converted_currency TEXT NOT NULL,
+            converted_at TEXT DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE TABLE groups_eur (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            owner_id INTEGER NOT NULL,
+            currency TEXT NOT NULL DEFAULT 'EUR',
+            FOREIGN KEY(owner_id) REFERENCES users(id)
+        );
+
+        INSERT INTO groups_eur (id, name, owner_id, currency)
+        SELECT id, name, owner_id, currency FROM groups;
+
+        DROP TABLE groups;
+        ALTER TABLE groups_eur RENAME TO groups;
+        "
+    )?;
+
+    convert_stored_leva(conn, false).map(|_| ())
+}
+
+fn migrate_recurring_expenses(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS recurring_expenses (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            group_id INTEGER NOT NULL,
+            payer_id INTEGER NOT NULL,
+            amount INTEGER NOT NULL,
+            currency TEXT NOT NULL,
+            description TEXT NOT NULL,
+            interval TEXT NOT NULL,
+            start_date TEXT NOT NULL,
+            end_date TEXT,
+            occurrences INTEGER NOT NULL DEFAULT 0,
+            next_date TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'active',
+            split_mode TEXT NOT NULL,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(group_id) REFERENCES groups(id),
+            FOREIGN KEY(payer_id) REFERENCES users(id)
+        );
+
+        CREATE TABLE IF NOT EXISTS recurring_expense_shares (
+            recurring_id INTEGER NOT NULL,
+            user_id INTEGER NOT NULL,
+            split_value INTEGER NOT NULL,
+            PRIMARY KEY (recurring_id, user_id),
+            FOREIGN KEY(recurring_id) REFERENCES recurring_expenses(id),
+            FOREIGN KEY(user_id) REFERENCES users(id)
+        );
+
+        ALTER TABLE expenses ADD COLUMN recurring_id INTEGER REFERENCES recurring_expenses(id);
+        "
+    )
+}
+
+/// Every notification now belongs to an event, and `event_key` keeps a user from
+/// being told about the same event twice. The only notifications so far were
+/// overdue reminders, which are created again from the debts when still due.
+fn migrate_notification_events(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE notifications_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            event_key TEXT NOT NULL,
+            message TEXT NOT NULL,
+            debt_id INTEGER,
+            group_id INTEGER,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+            shown BOOLEAN DEFAULT 0,
+            UNIQUE (user_id, event_key),
+            FOREIGN KEY(user_id) REFERENCES users(id)
+        );
+
+        DROP TABLE notifications;
+        ALTER TABLE notifications_events RENAME TO notifications;
+        "
+    )
+}
+
+/// Notifications stay in the inbox after they are read; `shown` marks them as
+/// read and `archived` takes them out of the inbox.
+fn migrate_notification_inbox(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE notifications ADD COLUMN archived BOOLEAN DEFAULT 0;")
+}
+
+/// How many days before a due date each user is reminded, see `parse_reminder_days`.
+fn migrate_reminder_days(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE users ADD COLUMN reminder_days TEXT NOT NULL DEFAULT '3,1';")
+}
+
+/// Every time a creditor reminded a debtor of a debt.
+fn migrate_debt_nudges(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE debt_nudges (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            debt_id INTEGER NOT NULL,
+            from_id INTEGER NOT NULL REFERENCES users(id),
+            to_id INTEGER NOT NULL REFERENCES users(id),
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE INDEX idx_debt_nudges_debt ON debt_nudges(debt_id, created_at);"
+    )
+}
+
+/// The channels each user wants notifications on besides the inbox, and every
+/// notification waiting to be sent on one of them.
+fn migrate_delivery_channels(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE notification_channels (
+            user_id INTEGER NOT NULL REFERENCES users(id),
+            channel TEXT NOT NULL,
+            target TEXT NOT NULL,
+            PRIMARY KEY (user_id, channel)
+        );
+
+        CREATE TABLE notification_deliveries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            notification_id INTEGER NOT NULL REFERENCES notifications(id) ON DELETE CASCADE,
+            channel TEXT NOT NULL,
+            target TEXT NOT NULL,
+            attempts INTEGER NOT NULL DEFAULT 0,
+            next_attempt_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            delivered_at TEXT,
+            last_error TEXT,
+            UNIQUE (notification_id, channel)
+        );"
+    )
+}
+
+/// What each user wants to be told about, see `NotificationPreferences`.
+/// Notifications `held` for the daily digest stay out of the inbox until it is sent.
+fn migrate_notification_preferences(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE notification_preferences (
+            user_id INTEGER PRIMARY KEY REFERENCES users(id),
+            muted_kinds TEXT NOT NULL DEFAULT '',
+            quiet_start TEXT,
+            quiet_end TEXT,
+            digest BOOLEAN NOT NULL DEFAULT 0,
+            last_digest_on TEXT
+        );
+
+        CREATE TABLE notification_group_mutes (
+            user_id INTEGER NOT NULL REFERENCES users(id),
+            group_id INTEGER NOT NULL REFERENCES groups(id),
+            PRIMARY KEY (user_id, group_id)
+        );
+
+        ALTER TABLE notifications ADD COLUMN held BOOLEAN NOT NULL DEFAULT 0;"
+    )
+}
+
+/// Admins and plain members. The owner stays recorded in `groups.owner_id`.
+fn migrate_group_roles(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE group_members ADD COLUMN role TEXT NOT NULL DEFAULT 'member';")
+}
+
+/// Invitations sent to one user, and one-time codes anyone can join with.
+fn migrate_group_invitations(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE group_invitations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            group_id INTEGER NOT NULL REFERENCES groups(id),
+            inviter_id INTEGER NOT NULL REFERENCES users(id),
+            invitee_id INTEGER NOT NULL REFERENCES users(id),
+            status TEXT NOT NULL DEFAULT 'pending',
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            responded_at TEXT
+        );
+
+        CREATE INDEX idx_group_invitations_invitee ON group_invitations(invitee_id, status);
+
+        CREATE TABLE group_invite_codes (
+            code TEXT PRIMARY KEY,
+            group_id INTEGER NOT NULL REFERENCES groups(id),
+            created_by INTEGER NOT NULL REFERENCES users(id),
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            expires_at TEXT NOT NULL,
+            used_by INTEGER REFERENCES users(id),
+            used_at TEXT
+        );"
+    )
+}
+
+fn migrate_group_archive(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE groups ADD COLUMN archived_at TEXT;")
+}
+
+/// The local time after which the daily digest may be sent, as `HH:MM`.
+fn migrate_digest_time(conn: &Connection) -> Result<()> {
+    conn.execute_batch(&format!(
+        "ALTER TABLE notification_preferences ADD COLUMN digest_time TEXT NOT NULL DEFAULT '{}';",
+        DEFAULT_DIGEST_TIME
+    ))
+}
+
+/// Requests for one member to take over another's open debts in a group.
+fn migrate_debt_transfers(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE debt_transfers (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            group_id INTEGER NOT NULL REFERENCES groups(id),
+            from_id INTEGER NOT NULL REFERENCES users(id),
+            to_id INTEGER NOT NULL REFERENCES users(id),
+            status TEXT NOT NULL DEFAULT 'pending',
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            responded_at TEXT
+        );"
+    )
+}
+
+/// Debts cancelled against a debt in the other direction are settled without
+/// being paid in full; `netted` tells them apart from debts that were paid off.
+/// So far only such debts were settled without both sides confirming.
+fn migrate_debt_netting(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE debts ADD COLUMN netted BOOLEAN NOT NULL DEFAULT 0;
+        UPDATE debts SET netted = 1 WHERE settled = 1 AND confirmed_by_debtor = 0 AND confirmed_by_creditor = 0;"
+    )
+}
+
+/// The debts that took over what was left of a debt when it was closed
+/// without being paid, such as by a group settlement.
+fn migrate_debt_replacements(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE debt_replacements (
+            debt_id INTEGER NOT NULL REFERENCES debts(id),
+            replaced_by INTEGER NOT NULL REFERENCES debts(id),
+            PRIMARY KEY (debt_id, replaced_by)
+        );"
+    )
+}
+
+/// The currency each expense was charged in, which is the group currency at the
+/// time. Existing expenses take it from the first debt they caused, or from their
+/// group if they caused none.
+fn migrate_expense_group_currency(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE expenses ADD COLUMN group_currency TEXT NOT NULL DEFAULT 'EUR';
+        UPDATE expenses SET group_currency = COALESCE(
+            (SELECT d.currency FROM debt_expenses de JOIN debts d ON d.id = de.debt_id
+             WHERE de.expense_id = expenses.id ORDER BY d.id LIMIT 1),
+            (SELECT currency FROM groups WHERE id = expenses.group_id)
+        );"
+    )
+}
+
+/// The accepted transfer that handed a debt over to another member.
+fn migrate_debt_transfer_links(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE debts ADD COLUMN transfer_id INTEGER REFERENCES debt_transfers(id);"
+    )
+}
+
+/// Converts every amount kept in leva to euro at the fixed rate `BGN_PER_EUR`,
+/// rounding each amount to the nearest cent. Expense shares are spread over the
+/// converted total, and debts made up of shares are rebuilt from the converted
+/// ones, so the two still agree to the cent. The original of every changed amount
+/// is kept in `currency_conversions`. Only rows still in leva are touched, so
+/// running it again changes nothing. Returns how many amounts were converted.
+pub fn convert_leva_to_euro(conn: &Connection) -> Result<usize> {
+    convert_stored_leva(conn, true)
+}
+
+/// Does the work of `convert_leva_to_euro`. Expenses record the currency they
+/// were charged in only since `migrate_expense_group_currency`; until then
+/// `recorded_group_currency` is false and it is taken to be the group's.
+fn convert_stored_leva(conn: &Connection, recorded_group_currency: bool) -> Result<usize> {
+    let to_euro = |amount: Money| convert(amount, BGN_PER_EUR, RATE_SCALE);
+    let mut converted = 0;
+    let mut audit = |table: &str, row_id: i32, column: &str, original: Money, amount: Money| -> Result<()> {
+        conn.execute(
+            "INSERT INTO currency_conversions
+                 (table_name, row_id, column_name, original_amount, original_currency, converted_amount, converted_currency)
+             VALUES (?1, ?2, ?3, ?4, 'BGN', ?5, 'EUR')",
+            params![table, row_id, column, original, amount],
+        )?;
+        converted += 1;
+        Ok(())
+    };
+
+    let payments: Vec<(i32, Money)> = conn.prepare(
+        "SELECT p.id, p.amount FROM payments p JOIN debts d ON d.id = p.debt_id WHERE d.currency = 'BGN'"
+    )?.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?.collect::<Result<_>>()?;
+
+    for (payment_id, amount) in payments {
+        let euro = to_euro(amount);
+        conn.execute("UPDATE payments SET amount = ?1 WHERE id = ?2", params![euro, payment_id])?;
+        audit("payments", payment_id, "amount", amount, euro)?;
+    }
+
+    let group_currency = if recorded_group_currency { "e.group_currency" } else { "g.currency" };
+    let expenses: Vec<(i32, i32, Money, String, Money, String, SplitMode)> = conn.prepare(&format!(
+        "SELECT e.id, e.payer_id, e.amount, e.currency, e.group_amount, {0}, e.split_mode
+         FROM expenses e
+         JOIN groups g ON g.id = e.group_id
+         WHERE e.currency = 'BGN' OR {0} = 'BGN'",
+        group_currency
+    ))?.query_map([], |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?, row.get(6)?))
+    })?.collect::<Result<_>>()?;
+
+    // The payer and the shares in the group currency, before and after the
+    // conversion, of every expense whose debts are in leva.
+    type Shares = Vec<(i32, Money)>;
+    let mut charged: HashMap<i32, (i32, Shares, Shares)> = HashMap::new();
+
+    for (expense_id, payer_id, amount, currency, group_amount, group_currency, split_mode) in expenses {
+        let shares: Vec<(i32, Money)> = conn.prepare(
+            "SELECT user_id, amount FROM expense_shares WHERE expense_id = ?1"
+        )?.query_map([expense_id], |row| Ok((row.get(0)?, row.get(1)?)))?.collect::<Result<_>>()?;
+        let mut converted_shares = shares.clone();
+        let mut euro = amount;
+
+        if currency == "BGN" {
+            euro = to_euro(amount);
+
+            // The shares are spread over the converted total so they still add up to it.
+            let weights: Vec<(i32, i64)> = shares.iter().map(|&(user_id, share)| (user_id, share.minor())).collect();
+            converted_shares = split_by_weight(euro, &weights, payer_id);
+
+            for &(user_id, share) in &converted_shares {
+                conn.execute(
+                    "UPDATE expense_shares
+                     SET amount = ?1, split_value = CASE WHEN ?2 = 'exact' THEN ?1 ELSE split_value END
+                     WHERE expense_id = ?3 AND user_id = ?4",
+                    params![share, split_mode, expense_id, user_id],
+                )?;
+            }
+
+            conn.execute("UPDATE expenses SET amount = ?1, currency = 'EUR' WHERE id = ?2", params![euro, expense_id])?;
+            audit("expenses", expense_id, "amount", amount, euro)?;
+        }
+
+        if group_currency == "BGN" {
+            let group_euro = if currency == "BGN" { euro } else { to_euro(group_amount) };
+            let update = if recorded_group_currency {
+                "UPDATE expenses SET group_amount = ?1, group_currency = 'EUR' WHERE id = ?2"
+            } else {
+                "UPDATE expenses SET group_amount = ?1 WHERE id = ?2"
+            };
+            conn.execute(update, params![group_euro, expense_id])?;
+            audit("expenses", expense_id, "group_amount", group_amount, group_euro)?;
+
+            charged.insert(expense_id, (
+                payer_id,
+                to_group_shares(&shares, group_amount, payer_id),
+                to_group_shares(&converted_shares, group_euro, payer_id),
+            ));
+        }
+    }
+
+    // What an expense added to a debt from `from_id` to `to_id`: the debtor's share
+    // when the creditor paid, less the creditor's share when the debtor paid.
+    let contribution = |shares: &[(i32, Money)], payer_id: i32, from_id: i32, to_id: i32| -> Money {
+        let share = |user_id: i32| shares.iter().find(|&&(id, _)| id == user_id).map(|&(_, share)| share).unwrap_or_default();
+        let mut amount = Money::ZERO;
+        if payer_id == to_id {
+            amount += share(from_id);
+        }
+        if payer_id == from_id {
+            amount -= share(to_id);
+        }
+        amount
+    };
+
+    let debts: Vec<(i32, i32, i32, Money, Money)> = conn.prepare(
+        "SELECT id, from_id, to_id, amount, paid FROM debts WHERE currency = 'BGN'"
+    )?.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)))?.collect::<Result<_>>()?;
+
+    for (debt_id, from_id, to_id, amount, paid) in debts {
+        let expense_ids: Vec<i32> = conn.prepare(
+            "SELECT expense_id FROM debt_expenses WHERE debt_id = ?1"
+        )?.query_map([debt_id], |row| row.get(0))?.collect::<Result<_>>()?;
+
+        // A debt that is exactly the shares of its expenses is rebuilt from the
+        // converted shares, so it still matches them to the cent. Any other debt
+        // is converted on its own.
+        let mut from_shares = !expense_ids.is_empty();
+        let (mut before, mut after) = (Money::ZERO, Money::ZERO);
+        for expense_id in expense_ids {
+            match charged.get(&expense_id) {
+                Some((payer_id, shares, converted_shares)) => {
+                    before += contribution(shares, *payer_id, from_id, to_id);
+                    after += contribution(converted_shares, *payer_id, from_id, to_id);
+                }
+                None => from_shares = false,
+            }
+        }
+        let euro = if from_shares && before == amount { after } else { to_euro(amount) };
+
+        // A fully paid debt stays fully paid; otherwise the paid part is what its
+        // converted payments add up to, so the ledger and the debt still agree.
+        let paid_euro = if paid == amount {
+            euro
+        }
+        else {
+            conn.query_row(
+                "SELECT COALESCE(SUM(amount), 0) FROM payments
+                 WHERE debt_id = ?1 AND confirmed_by_debtor = 1 AND confirmed_by_creditor = 1",
+                [debt_id],
+                |row| row.get(0),
+            )?
+        };
+
+        conn.execute(
+            "UPDATE debts SET amount = ?1, paid = ?2, currency = 'EUR' WHERE id = ?3",
+            params![euro, paid_euro, debt_id],
+        )?;
+        audit("debts", debt_id, "amount", amount, euro)?;
+        if !paid.is_zero() {
+            audit("debts", debt_id, "paid", paid, paid_euro)?;
+        }
+    }
+
+    conn.execute("UPDATE groups SET currency = 'EUR' WHERE currency = 'BGN'", [])?;
+
+    Ok(converted)
+}
+
+/// Runs `operation` inside a savepoint, so it is applied completely or not at all.
+/// Savepoints nest, which lets such operations call each other.
+fn with_savepoint<T>(conn: &Connection, operation: impl FnOnce() -> std::result::Result<T, String>) -> std::result::Result<T, String> {
+    conn.execute_batch("SAVEPOINT operation").map_err(|e| e.to_string())?;
+
+    match operation() {
+        Ok(value) => {
+            conn.execute_batch("RELEASE operation").map_err(|e| e.to_string())?;
+            Ok(value)
+        }
+        Err(e) => {
+            conn.execute_batch("ROLLBACK TO operation; RELEASE operation").map_err(|e| e.to_string())?;
+            Err(e)
+        }
+    }
 }
 
 pub fn register_user(conn: &Connection, username: &str, email: &str, password: &str) -> std::result::Result<(), String> {
@@ -146,61 +804,176 @@ pub fn login_user(conn: &Connection, email: &str, password: &str) -> std::result
     }
 }
 
-pub fn create_group(conn: &Connection, name: &str, owner_id: i32, members: &[i32]) -> std::result::Result<(), String> {
+/// Creates the group and returns its id. `members` join it straight away, so
+/// the app only passes the owner and invites everyone else with `invite_to_group`.
+pub fn create_group(conn: &Connection, name: &str, owner_id: i32, members: &[i32]) -> std::result::Result<i32, String> {
     conn.execute(
         "INSERT INTO groups (name, owner_id) VALUES (?1, ?2)",
         params![name, owner_id],
     )
         .map_err(|e| e.to_string())?;
 
-    let group_id = conn.last_insert_rowid();
+    let group_id = conn.last_insert_rowid() as i32;
+    let message = format!("Добавени сте в група {}", name);
 
     for &user_id in members {
         conn.execute(
             "INSERT INTO group_members (group_id, user_id) VALUES (?1, ?2)",
             (group_id, user_id),
         ).map_err(|e| e.to_string())?;
+
+        if user_id != owner_id {
+            notify(conn, user_id, NotificationKind::AddedToGroup, &format!("added_to_group:{}", group_id), &message, None, Some(group_id))?;
+        }
     }
 
-    Ok(())
+    Ok(group_id)
 }
 
-pub fn search_users(conn: &Connection, query: &str) -> std::result::Result<Vec<User>, String> {
-    let pattern = format!("%{}%", query);
-    let mut stmt = conn
-        .prepare("SELECT id, username, email, loyal_payer FROM users WHERE username LIKE ?1 OR email LIKE ?1")
-        .map_err(|e| e.to_string())?;
+/// Creates a group with the owner as its only member, sets its currency and
+/// invites `invitees`. Nothing is kept if any step fails.
+pub fn create_group_with_invites(conn: &Connection, name: &str, owner_id: i32, currency: &str, invitees: &[i32]) -> std::result::Result<i32, String> {
+    with_savepoint(conn, || {
+        let group_id = create_group(conn, name, owner_id, &[owner_id])?;
+        set_group_currency(conn, owner_id, group_id, currency)?;
+        for &invitee_id in invitees.iter().filter(|&&id| id != owner_id) {
+            invite_to_group(conn, owner_id, group_id, invitee_id)?;
+        }
+        Ok(group_id)
+    })
+}
 
-    let users = stmt
-        .query_map([pattern], |row| {
-            Ok(User::from_loyal_payer (
-                row.get(0)?,
-                row.get(1)?,
-                row.get(2)?,
-                row.get(3)?,
-            ))
-        })
+pub fn get_group_currency(conn: &Connection, group_id: i32) -> std::result::Result<String, String> {
+    conn.query_row("SELECT currency FROM groups WHERE id = ?1", [group_id], |row| row.get(0))
+        .optional()
         .map_err(|e| e.to_string())?
-        .collect::<std::result::Result<Vec<_>, _>>()
-        .map_err(|e| e.to_string())?;
+        .ok_or_else(|| "Групата не е намерена.".to_string())
+}
 
-    if users.is_empty() {
-        return Err("Няма такъв потребител!".to_string());
+/// Changes the currency the group's debts are kept and settled in.
+/// Open debts are not converted, so this is only allowed once they are all settled.
+pub fn set_group_currency(conn: &Connection, user_id: i32, group_id: i32, currency: &str) -> std::result::Result<(), String> {
+    let currency = parse_code(currency)?;
+
+    if !member_role(conn, group_id, user_id)?.can_manage_group() {
+        return Err("Само собственикът или администратор може да променя групата.".to_string());
     }
 
-    Ok(users)
-}
+    if get_group_currency(conn, group_id)? == currency {
+        return Ok(());
+    }
 
-pub fn get_user_by_id(conn: &Connection, user_id: i32) -> std::result::Result<User, String> {
-    let mut stmt = conn
-        .prepare("SELECT id, username, email FROM users WHERE id = ?1")
-        .map_err(|e| e.to_string())?;
+    get_exchange_rate(conn, &currency)?;
 
-    let mut rows = stmt.query(params![user_id]).map_err(|e| e.to_string())?;
+    let open_debts: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM debts WHERE group_id = ?1 AND settled = 0)",
+        [group_id],
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
 
-    if let Some(row) = rows.next().map_err(|e| e.to_string())? {
-        Ok(User::new (
-            row.get(0).unwrap(),
+    if open_debts {
+        return Err("Валутата на групата може да се смени само когато всички дългове в нея са уредени.".to_string());
+    }
+
+    conn.execute("UPDATE groups SET currency = ?1 WHERE id = ?2", params![currency, group_id])
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// The latest known rate of `currency`, in millionths of a unit per euro.
+pub fn get_exchange_rate(conn: &Connection, currency: &str) -> std::result::Result<i64, String> {
+    conn.query_row(
+        "SELECT rate FROM exchange_rates WHERE currency = ?1 ORDER BY rate_date DESC LIMIT 1",
+        [currency],
+        |row| row.get(0),
+    ).optional().map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Няма зареден курс за {}.", currency))
+}
+
+/// The latest rate of every known currency.
+pub fn get_exchange_rates(conn: &Connection) -> std::result::Result<Vec<ExchangeRate>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT currency, MAX(rate_date), rate
+         FROM exchange_rates
+         GROUP BY currency
+         ORDER BY currency"
+    ).map_err(|e| e.to_string())?;
+
+    let rates = stmt.query_map([], |row| {
+        Ok(ExchangeRate::new(
+            row.get(0)?,
+            row.get(1)?,
+            row.get(2)?,
+        ))
+    }).map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(rates)
+}
+
+/// Stores the rates from an ECB-style CSV export, see `parse_ecb_csv`.
+/// Rates already stored for the same day are replaced. Returns how many were read.
+pub fn import_exchange_rates(conn: &Connection, csv: &str) -> std::result::Result<usize, String> {
+    let rates = parse_ecb_csv(csv)?;
+
+    with_savepoint(conn, || {
+        for rate in &rates {
+            conn.execute(
+                "INSERT OR REPLACE INTO exchange_rates (currency, rate_date, rate) VALUES (?1, ?2, ?3)",
+                params![rate.currency(), rate.rate_date(), rate.rate()],
+            ).map_err(|e| e.to_string())?;
+        }
+        Ok(rates.len())
+    })
+}
+
+/// Converts `amount` between two currencies using the latest stored rates.
+pub fn convert_amount(conn: &Connection, amount: Money, from: &str, to: &str) -> std::result::Result<Money, String> {
+    if from == to {
+        return Ok(amount);
+    }
+
+    Ok(convert(amount, get_exchange_rate(conn, from)?, get_exchange_rate(conn, to)?))
+}
+
+pub fn search_users(conn: &Connection, query: &str) -> std::result::Result<Vec<User>, String> {
+    let pattern = format!("%{}%", query);
+    let mut stmt = conn
+        .prepare("SELECT id, username, email, loyal_payer FROM users WHERE username LIKE ?1 OR email LIKE ?1")
+        .map_err(|e| e.to_string())?;
+
+    let users = stmt
+        .query_map([pattern], |row| {
+            Ok(User::from_loyal_payer (
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    if users.is_empty() {
+        return Err("Няма такъв потребител!".to_string());
+    }
+
+    Ok(users)
+}
+
+pub fn get_user_by_id(conn: &Connection, user_id: i32) -> std::result::Result<User, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, username, email FROM users WHERE id = ?1")
+        .map_err(|e| e.to_string())?;
+
+    let mut rows = stmt.query(params![user_id]).map_err(|e| e.to_string())?;
+
+    if let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        Ok(User::new (
+            row.get(0).unwrap(),
             row.get(1).unwrap(),
             row.get(2).unwrap(),
 
@@ -213,142 +986,313 @@ pub fn get_user_by_id(conn: &Connection, user_id: i32) -> std::result::Result<Us
 }
 
 
+/// The groups the user is in, without the archived ones.
 pub fn get_user_groups(conn: &Connection, user_id: i32) -> std::result::Result<Vec<Group>, String> {
+    let groups = query_user_groups(conn, user_id, false)?;
+
+    if groups.is_empty() {
+        return Err("Нямате групи!".to_string());
+    }
+
+    Ok(groups)
+}
+
+/// The archived groups the user is in.
+pub fn get_archived_groups(conn: &Connection, user_id: i32) -> std::result::Result<Vec<Group>, String> {
+    query_user_groups(conn, user_id, true)
+}
+
+fn query_user_groups(conn: &Connection, user_id: i32, archived: bool) -> std::result::Result<Vec<Group>, String> {
     let mut stmt = conn
-        .prepare("SELECT g.id, g.name, g.owner_id
+        .prepare("SELECT g.id, g.name, g.owner_id, g.currency, gm.role
              FROM groups g
              JOIN group_members gm ON g.id = gm.group_id
-             WHERE gm.user_id = ?1",)
+             WHERE gm.user_id = ?1 AND (g.archived_at IS NOT NULL) = ?2",)
         .map_err(|e| e.to_string())?;
 
     let groups = stmt
-        .query_map( [user_id], |row| {
+        .query_map(params![user_id, archived], |row| {
+            let owner_id: i32 = row.get(2)?;
+            let role = if owner_id == user_id { GroupRole::Owner } else { row.get(4)? };
             Ok(Group::new (
                 row.get(0)?,
                 row.get(1)?,
-            ))
+            ).with_currency(row.get(3)?).with_role(role).with_archived(archived))
         })
         .map_err(|e| e.to_string())?
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
 
-    if groups.is_empty() {
-        return Err("Нямате групи!".to_string());
+    Ok(groups)
+}
+
+/// Gives the group a new name.
+pub fn rename_group(conn: &Connection, user_id: i32, group_id: i32, name: &str) -> std::result::Result<(), String> {
+    if !member_role(conn, group_id, user_id)?.can_manage_group() {
+        return Err("Само собственикът или администратор може да променя групата.".to_string());
     }
 
-    Ok(groups)
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Името на групата не може да е празно.".to_string());
+    }
+
+    conn.execute("UPDATE groups SET name = ?2 WHERE id = ?1", params![group_id, name])
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Archives or restores a group. Only groups with every debt settled can be
+/// archived, and their recurring expenses are paused so nothing new is owed.
+pub fn set_group_archived(conn: &Connection, user_id: i32, group_id: i32, archived: bool) -> std::result::Result<(), String> {
+    if !member_role(conn, group_id, user_id)?.can_manage_group() {
+        return Err("Само собственикът или администратор може да променя групата.".to_string());
+    }
+
+    if archived {
+        let open_debts: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM debts WHERE group_id = ?1 AND settled = 0)",
+            [group_id],
+            |row| row.get(0),
+        ).map_err(|e| e.to_string())?;
+
+        if open_debts {
+            return Err("Групата може да бъде архивирана само когато всички дългове са уредени.".to_string());
+        }
+    }
+
+    with_savepoint(conn, || {
+        conn.execute(
+            "UPDATE groups SET archived_at = CASE WHEN ?2 THEN COALESCE(archived_at, CURRENT_TIMESTAMP) END WHERE id = ?1",
+            params![group_id, archived],
+        ).map_err(|e| e.to_string())?;
+
+        if archived {
+            conn.execute(
+                "UPDATE recurring_expenses SET status = 'paused' WHERE group_id = ?1 AND status = 'active'",
+                [group_id],
+            ).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    })
+}
+
+/// Deletes an empty group for good: the owner has to be its only member and
+/// nothing may be owed in it. Settled debts, expenses, payments and
+/// notifications that belong to the group are deleted with it.
+pub fn delete_group(conn: &Connection, user_id: i32, group_id: i32) -> std::result::Result<(), String> {
+    if !member_role(conn, group_id, user_id)?.is_owner() {
+        return Err("Само собственикът може да изтрие групата.".to_string());
+    }
+
+    let (other_members, open_debts): (bool, bool) = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM group_members WHERE group_id = ?1 AND user_id != ?2),
+                EXISTS(SELECT 1 FROM debts WHERE group_id = ?1 AND settled = 0)",
+        params![group_id, user_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).map_err(|e| e.to_string())?;
+
+    if other_members {
+        return Err("Групата може да бъде изтрита само когато в нея не са останали други членове.".to_string());
+    }
+    if open_debts {
+        return Err("Групата може да бъде изтрита само когато всички дългове са уредени.".to_string());
+    }
+
+    with_savepoint(conn, || {
+        for statement in [
+            "DELETE FROM notification_deliveries WHERE notification_id IN (
+                SELECT id FROM notifications
+                WHERE group_id = ?1 OR debt_id IN (SELECT id FROM debts WHERE group_id = ?1)
+            )",
+            "DELETE FROM notifications WHERE group_id = ?1 OR debt_id IN (SELECT id FROM debts WHERE group_id = ?1)",
+            "DELETE FROM debt_nudges WHERE debt_id IN (SELECT id FROM debts WHERE group_id = ?1)",
+            "DELETE FROM payments WHERE debt_id IN (SELECT id FROM debts WHERE group_id = ?1)",
+            "DELETE FROM debt_expenses WHERE debt_id IN (SELECT id FROM debts WHERE group_id = ?1)",
+            "DELETE FROM debt_replacements WHERE debt_id IN (SELECT id FROM debts WHERE group_id = ?1)",
+            "DELETE FROM debts WHERE group_id = ?1",
+            "DELETE FROM expense_shares WHERE expense_id IN (SELECT id FROM expenses WHERE group_id = ?1)",
+            "DELETE FROM expenses WHERE group_id = ?1",
+            "DELETE FROM recurring_expense_shares WHERE recurring_id IN (SELECT id FROM recurring_expenses WHERE group_id = ?1)",
+            "DELETE FROM recurring_expenses WHERE group_id = ?1",
+            "DELETE FROM group_invitations WHERE group_id = ?1",
+            "DELETE FROM group_invite_codes WHERE group_id = ?1",
+            "DELETE FROM debt_transfers WHERE group_id = ?1",
+            "DELETE FROM notification_group_mutes WHERE group_id = ?1",
+            "DELETE FROM group_members WHERE group_id = ?1",
+            "DELETE FROM groups WHERE id = ?1",
+        ] {
+            conn.execute(statement, [group_id]).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    })
+}
+
+fn insert_debt(
+    conn: &Connection,
+    from_id: i32,
+    to_id: i32,
+    group_id: i32,
+    amount: Money,
+    due_date: &str,
+    description: &str,
+) -> Result<i32, String> {
+    conn.execute(
+        "INSERT INTO debts (from_id, to_id, group_id, amount, due_date, description, currency)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, (SELECT currency FROM groups WHERE id = ?3))",
+        params![from_id, to_id, group_id, amount, due_date, description],
+    ).map_err(|e| e.to_string())?;
+
+    Ok(conn.last_insert_rowid() as i32)
+}
+
+fn link_debt_to_expense(conn: &Connection, debt_id: i32, expense_id: Option<i32>) -> Result<(), String> {
+    if let Some(expense_id) = expense_id {
+        conn.execute(
+            "INSERT OR IGNORE INTO debt_expenses (debt_id, expense_id) VALUES (?1, ?2)",
+            params![debt_id, expense_id],
+        ).map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
 }
 
+/// Records that `from_id` owes `to_id` a further `amount`, netting it against
+/// an open debt in the opposite direction when neither side has confirmed it yet.
+/// A debt the new amount cancels completely is marked settled and netted rather
+/// than deleted, and whatever is left over becomes a new debt that carries on its
+/// expenses. Every debt row touched is linked back to `expense_id`. Returns the
+/// open debt left between the two, or `None` if the amounts cancelled out.
+#[allow(clippy::too_many_arguments)]
 pub fn add_or_update_debt(
     conn: &Connection,
     from_id: i32,
     to_id: i32,
     group_id: i32,
-    amount: f32,
+    amount: Money,
     due_date: &str,
     description: &str,
-) -> Result<(), String> {
+    expense_id: Option<i32>,
+) -> Result<Option<i32>, String> {
 
     let mut stmt = conn.prepare(
-        "SELECT id, amount, confirmed_by_debtor, confirmed_by_creditor
+        "SELECT id, amount, confirmed_by_debtor, confirmed_by_creditor,
+                EXISTS(SELECT 1 FROM payments p WHERE p.debt_id = debts.id)
          FROM debts
          WHERE from_id = ?1 AND to_id = ?2 AND group_id = ?3 AND settled = 0"
     ).map_err(|e| e.to_string())?;
 
-    let existing: Option<(i32, f32, bool, bool)> = stmt.query_row(
+    let existing: Option<(i32, Money, bool, bool, bool)> = stmt.query_row(
         params![from_id, to_id, group_id],
-        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
     ).optional().map_err(|e| e.to_string())?;
 
-    if let Some((debt_id, old_amount, confirmed_debtor, confirmed_creditor)) = existing {
+    if let Some((debt_id, old_amount, confirmed_debtor, confirmed_creditor, has_payments)) = existing {
 
-        if !confirmed_debtor && !confirmed_creditor {
+        if !confirmed_debtor && !confirmed_creditor && !has_payments {
 
             let new_amount = old_amount + amount;
             conn.execute(
-                "UPDATE debts SET amount = ?1, due_date = ?2, description = ?3 WHERE id = ?4",
+                "UPDATE debts SET amount = ?1, due_date = ?2, description = description || '; ' || ?3 WHERE id = ?4",
                 params![new_amount, due_date, description, debt_id],
             ).map_err(|e| e.to_string())?;
+            link_debt_to_expense(conn, debt_id, expense_id)?;
+            Ok(Some(debt_id))
         }
         else {
 
-            conn.execute(
-                "INSERT INTO debts (from_id, to_id, group_id, amount, due_date, description)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-                params![from_id, to_id, group_id, amount, due_date, description],
-            ).map_err(|e| e.to_string())?;
+            let debt_id = insert_debt(conn, from_id, to_id, group_id, amount, due_date, description)?;
+            link_debt_to_expense(conn, debt_id, expense_id)?;
+            Ok(Some(debt_id))
         }
     }
     else {
 
         let mut stmt2 = conn.prepare(
-            "SELECT id, amount, confirmed_by_debtor, confirmed_by_creditor
+            "SELECT id, amount, confirmed_by_debtor, confirmed_by_creditor,
+                    EXISTS(SELECT 1 FROM payments p WHERE p.debt_id = debts.id)
              FROM debts
              WHERE from_id = ?1 AND to_id = ?2 AND group_id = ?3 AND settled = 0"
         ).map_err(|e| e.to_string())?;
 
-        let reverse: Option<(i32, f32, bool, bool)> = stmt2.query_row(
+        let reverse: Option<(i32, Money, bool, bool, bool)> = stmt2.query_row(
             params![to_id, from_id, group_id],
-            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
         ).optional().map_err(|e| e.to_string())?;
 
-        if let Some((rev_id, rev_amount, confirmed_debtor, confirmed_creditor)) = reverse {
-            if !confirmed_debtor && !confirmed_creditor {
+        if let Some((rev_id, rev_amount, confirmed_debtor, confirmed_creditor, has_payments)) = reverse {
+            if !confirmed_debtor && !confirmed_creditor && !has_payments {
 
                 if amount > rev_amount {
                     let diff = amount - rev_amount;
 
-                    conn.execute("DELETE FROM debts WHERE id = ?1", params![rev_id])
-                        .map_err(|e| e.to_string())?;
+                    let debt_id = insert_debt(conn, from_id, to_id, group_id, diff, due_date, description)?;
                     conn.execute(
-                        "INSERT INTO debts (from_id, to_id, group_id, amount, due_date, description) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-                        params![from_id, to_id, group_id, diff, due_date, description],
+                        "INSERT OR IGNORE INTO debt_expenses (debt_id, expense_id)
+                         SELECT ?1, expense_id FROM debt_expenses WHERE debt_id = ?2",
+                        params![debt_id, rev_id],
                     ).map_err(|e| e.to_string())?;
+                    conn.execute("UPDATE debts SET settled = 1, netted = 1 WHERE id = ?1", params![rev_id])
+                        .map_err(|e| e.to_string())?;
+                    link_debt_to_expense(conn, debt_id, expense_id)?;
+                    link_debt_to_expense(conn, rev_id, expense_id)?;
+                    Ok(Some(debt_id))
                 }
                 else if amount < rev_amount {
                     let diff = rev_amount - amount;
 
                     conn.execute(
-                        "UPDATE debts SET amount = ?1, due_date = ?2, description = ?3 WHERE id = ?4",
-                        params![diff, due_date, description, rev_id],
+                        "UPDATE debts SET amount = ?1 WHERE id = ?2",
+                        params![diff, rev_id],
                     ).map_err(|e| e.to_string())?;
+                    link_debt_to_expense(conn, rev_id, expense_id)?;
+                    Ok(Some(rev_id))
                 }
                 else {
-
-                    conn.execute("DELETE FROM debts WHERE id = ?1", params![rev_id])
+                    // Keep the cancelled debt so the expenses behind it can still be traced.
+                    conn.execute("UPDATE debts SET settled = 1, netted = 1 WHERE id = ?1", params![rev_id])
                         .map_err(|e| e.to_string())?;
+                    link_debt_to_expense(conn, rev_id, expense_id)?;
+                    Ok(None)
                 }
             }
             else {
 
-                conn.execute(
-                    "INSERT INTO debts (from_id, to_id, group_id, amount, due_date, description)
-                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-                    params![from_id, to_id, group_id, amount, due_date, description],
-                ).map_err(|e| e.to_string())?;
+                let debt_id = insert_debt(conn, from_id, to_id, group_id, amount, due_date, description)?;
+                link_debt_to_expense(conn, debt_id, expense_id)?;
+                Ok(Some(debt_id))
             }
         }
         else {
 
-            conn.execute(
-                "INSERT INTO debts (from_id, to_id, group_id, amount, due_date, description)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-                params![from_id, to_id, group_id, amount, due_date, description],
-            ).map_err(|e| e.to_string())?;
+            let debt_id = insert_debt(conn, from_id, to_id, group_id, amount, due_date, description)?;
+            link_debt_to_expense(conn, debt_id, expense_id)?;
+            Ok(Some(debt_id))
         }
     }
-
-    Ok(())
 }
 
 
-pub fn add_expenses(conn: &Connection, payer_id: i32, group_id: i32, amount: f32, description: &str, due_date: &str) -> std::result::Result<(), String> {
+/// Validates the expense fields and works out every participant's share.
+/// An equal split without participants is expanded to the whole group.
+/// Participants must be group members, except for those in `kept`, who already
+/// took part in the expense and may have left the group since.
+fn resolve_split(
+    conn: &Connection,
+    payer_id: i32,
+    group_id: i32,
+    amount: Money,
+    due_date: &str,
+    split: &Split,
+    kept: &[i32],
+) -> std::result::Result<(Split, Vec<(i32, Money)>), String> {
     let re = Regex::new(r"^\d{4}-\d{2}-\d{2}$").unwrap();
     if !re.is_match(due_date) && !due_date.is_empty() {
         return Err("Невалиден формат на дата. Използвайте YYYY-MM-DD.".to_string());
     }
 
-    if amount <= 0.0 {
+    if !amount.is_positive() {
         return Err("Сумата трябва да е положително число.".to_string());
     }
 
@@ -362,203 +1306,2313 @@ pub fn add_expenses(conn: &Connection, payer_id: i32, group_id: i32, amount: f32
         .collect::<Result<Vec<i32>, _>>()
         .map_err(|e| e.to_string())?;
 
-    let share = amount / members.len() as f32;
+    let split = if split.mode() == SplitMode::Equal && split.entries().is_empty() {
+        Split::equal(members.clone())
+    }
+    else {
+        split.clone()
+    };
+
+    if split.participants().iter().any(|id| !members.contains(id) && !kept.contains(id)) {
+        return Err("Участникът не е член на групата.".to_string());
+    }
+
+    let shares = split.allocate(amount, payer_id)?;
+    Ok((split, shares))
+}
+
+fn save_expense_shares(conn: &Connection, expense_id: i32, split: &Split, shares: &[(i32, Money)]) -> std::result::Result<(), String> {
+    for &(user_id, value) in split.entries() {
+        let share = shares.iter().find(|&&(id, _)| id == user_id).map(|&(_, share)| share).unwrap_or_default();
+        conn.execute(
+            "INSERT INTO expense_shares (expense_id, user_id, split_value, amount) VALUES (?1, ?2, ?3, ?4)",
+            params![expense_id, user_id, value, share],
+        ).map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
 
-    for member_id in members {
-        if member_id == payer_id {
+/// Charges every participant their share, owed to the payer.
+fn charge_shares(
+    conn: &Connection,
+    expense_id: i32,
+    payer_id: i32,
+    group_id: i32,
+    shares: &[(i32, Money)],
+    due_date: &str,
+    description: &str,
+) -> std::result::Result<(), String> {
+    for &(member_id, share) in shares {
+        if member_id == payer_id || share.is_zero() {
             continue;
         }
 
-        add_or_update_debt(conn, member_id, payer_id, group_id, share, due_date, description)?;
+        add_or_update_debt(conn, member_id, payer_id, group_id, share, due_date, description, Some(expense_id))?;
     }
 
     Ok(())
 }
 
-pub fn get_user_debts_or_credits(conn: &Connection, user_id: i32, is_debt: bool) -> Result<Vec<Expenses>, String> {
-    let condition2 = if is_debt { "d.from_id = ?" } else { "d.to_id = ?" };
-    let condition1 = if is_debt { "d.to_id" } else { "d.from_id" };
-
-    let query = format!(
-        "SELECT d.id, u.username, d.amount, g.name, d.due_date, d.description
-         FROM debts d
-         JOIN users u ON {} = u.id
-         JOIN groups g ON d.group_id = g.id
-         WHERE {}
-         AND d.settled = 0
-         ORDER BY d.due_date ASC;",
-        condition1, condition2
-    );
+/// Undoes the debts an expense created by charging the payer the same shares back.
+/// Open debts are netted away; debts that are already confirmed or settled stay
+/// untouched and get a compensating debt from the payer to the participant instead.
+/// The shares are taken in the currency the expense was charged in and converted
+/// when the group has changed its currency since.
+fn reverse_expense(conn: &Connection, expense: &Expense) -> std::result::Result<(), String> {
+    let description = format!("Корекция: {}", expense.description());
+    let shares: Vec<(i32, Money)> = expense.shares().iter().map(|share| (share.user_id(), share.amount())).collect();
+    let group_currency = get_group_currency(conn, expense.group_id())?;
+    let group_amount = convert_amount(conn, expense.group_amount(), expense.group_currency(), &group_currency)?;
+
+    for (user_id, share) in to_group_shares(&shares, group_amount, expense.payer_id()) {
+        if user_id == expense.payer_id() || share.is_zero() {
+            continue;
+        }
 
-    let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
+        add_or_update_debt(
+            conn,
+            expense.payer_id(),
+            user_id,
+            expense.group_id(),
+            share,
+            expense.due_date(),
+            &description,
+            Some(expense.id()),
+        )?;
+    }
 
-    let expenses = stmt
-        .query_map([user_id], |row| {
-            Ok(Expenses::new(
-                row.get(0)?,
-                row.get(1)?,
-                row.get(2)?,
-                row.get(3)?,
-                row.get(4)?,
-                row.get(5)?,
-            ))
-        })
-        .map_err(|e| e.to_string())?
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| e.to_string())?;
+    Ok(())
+}
 
-    if expenses.is_empty() {
-        return Err("Нямате дългове или вземания!".to_string());
+/// Spreads the expense total converted into the group currency over the
+/// participants in proportion to their shares, so the converted shares still
+/// add up exactly to the converted total.
+fn to_group_shares(shares: &[(i32, Money)], group_amount: Money, payer_id: i32) -> Vec<(i32, Money)> {
+    let total: Money = shares.iter().map(|&(_, share)| share).sum();
+    if total == group_amount {
+        return shares.to_vec();
     }
 
-    Ok(expenses)
+    let weights: Vec<(i32, i64)> = shares.iter().map(|&(id, share)| (id, share.minor())).collect();
+    split_by_weight(group_amount, &weights, payer_id)
 }
 
-pub fn payment_confirmation(conn: &Connection, user_id: i32, debt_id: i32) -> std::result::Result<String, String> {
-    let mut stmt = conn.prepare(
-        "SELECT from_id, to_id, confirmed_by_debtor, confirmed_by_creditor
-         FROM debts WHERE id = ?1 AND settled = 0"
-    ).map_err(|e| e.to_string())?;
+/// Converts an expense amount into the group currency, checking the currency on the way.
+/// Returns the expense currency, the group currency and the converted amount.
+fn expense_group_amount(conn: &Connection, group_id: i32, amount: Money, currency: Option<&str>) -> std::result::Result<(String, String, Money), String> {
+    let group_currency = get_group_currency(conn, group_id)?;
+    let currency = match currency {
+        Some(currency) => parse_code(currency)?,
+        None => group_currency.clone(),
+    };
+
+    let group_amount = convert_amount(conn, amount, &currency, &group_currency)?;
+    Ok((currency, group_currency, group_amount))
+}
 
-    let (from_id, to_id, mut debtor_conf, mut creditor_conf): (i32, i32, bool, bool) =
-        stmt.query_row([debt_id], |row| {
-            Ok(
-                (row.get(0)?,
-                 row.get(1)?,
-                 row.get(2)?,
-                 row.get(3)?)
-            )
-        }).map_err(|e| e.to_string())?;
+/// Records the expense together with every participant's share and charges
+/// each participant their share, owed to the payer.
+/// The payer does not have to be one of the participants.
+/// The expense is kept in `currency`, or in the group currency when it is `None`,
+/// and the debts are converted into the group currency at the latest rate.
+/// Returns the id of the new expense.
+#[allow(clippy::too_many_arguments)]
+pub fn add_expenses(
+    conn: &Connection,
+    payer_id: i32,
+    group_id: i32,
+    amount: Money,
+    currency: Option<&str>,
+    description: &str,
+    due_date: &str,
+    split: &Split,
+) -> std::result::Result<i32, String> {
+    check_group_member(conn, group_id, payer_id)?;
+    check_group_active(conn, group_id)?;
+    let (split, shares) = resolve_split(conn, payer_id, group_id, amount, due_date, split, &[])?;
+    let (currency, group_currency, group_amount) = expense_group_amount(conn, group_id, amount, currency)?;
+
+    with_savepoint(conn, || {
+        conn.execute(
+            "INSERT INTO expenses (group_id, payer_id, amount, currency, group_currency, group_amount, description, due_date, split_mode)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![group_id, payer_id, amount, currency, group_currency, group_amount, description, due_date, split.mode()],
+        ).map_err(|e| e.to_string())?;
 
-    if user_id == from_id {
-        debtor_conf = true;
-    }
-    else if user_id == to_id {
-        creditor_conf = true;
-    }
-    else {
-        return Err("Потребителят не е участник в този дълг.".to_string());
+        let expense_id = conn.last_insert_rowid() as i32;
+
+        save_expense_shares(conn, expense_id, &split, &shares)?;
+        let group_shares = to_group_shares(&shares, group_amount, payer_id);
+        charge_shares(conn, expense_id, payer_id, group_id, &group_shares, due_date, description)?;
+        notify_expense_added(conn, expense_id)?;
+
+        Ok(expense_id)
+    })
+}
+
+/// Replaces the amount, currency, description, due date and split of an expense
+/// and recalculates the debts it caused.
+#[allow(clippy::too_many_arguments)]
+pub fn edit_expense(
+    conn: &Connection,
+    user_id: i32,
+    expense_id: i32,
+    amount: Money,
+    currency: Option<&str>,
+    description: &str,
+    due_date: &str,
+    split: &Split,
+) -> std::result::Result<(), String> {
+    let expense = get_expense(conn, expense_id)?;
+    if expense.payer_id() != user_id && !member_role(conn, expense.group_id(), user_id)?.can_edit_any_expense() {
+        return Err("Само платилият или администратор на групата може да променя разхода.".to_string());
     }
+    check_group_active(conn, expense.group_id())?;
+
+    let kept: Vec<i32> = expense.shares().iter().map(|share| share.user_id()).collect();
+    let (split, shares) = resolve_split(conn, expense.payer_id(), expense.group_id(), amount, due_date, split, &kept)?;
+    let (currency, group_currency, group_amount) = expense_group_amount(conn, expense.group_id(), amount, currency)?;
+
+    with_savepoint(conn, || {
+        reverse_expense(conn, &expense)?;
 
-    if debtor_conf && creditor_conf {
         conn.execute(
-            "UPDATE debts
-             SET confirmed_by_debtor = 1,
-                 confirmed_by_creditor = 1,
-                 settled = 1
-             WHERE id = ?1",
-            [debt_id],
+            "UPDATE expenses
+             SET amount = ?1, currency = ?2, group_currency = ?3, group_amount = ?4, description = ?5, due_date = ?6, split_mode = ?7
+             WHERE id = ?8",
+            params![amount, currency, group_currency, group_amount, description, due_date, split.mode(), expense_id],
         ).map_err(|e| e.to_string())?;
+        conn.execute("DELETE FROM expense_shares WHERE expense_id = ?1", [expense_id])
+            .map_err(|e| e.to_string())?;
 
-        let mut stmt_check = conn.prepare(
-            "SELECT CASE
-                        WHEN due_date IS NULL THEN -1
-                        WHEN due_date >= date('now') THEN 1
-                        ELSE 0
-                    END
-                FROM debts
-                WHERE id = ?1;"
-        ).map_err(|e| e.to_string())?;
+        save_expense_shares(conn, expense_id, &split, &shares)?;
+        let group_shares = to_group_shares(&shares, group_amount, expense.payer_id());
+        charge_shares(conn, expense_id, expense.payer_id(), expense.group_id(), &group_shares, due_date, description)
+    })
+}
+
+/// Marks an expense as deleted and reverses the debts it caused.
+/// The record itself is kept so the group's history stays complete.
+pub fn delete_expense(conn: &Connection, user_id: i32, expense_id: i32) -> std::result::Result<(), String> {
+    let expense = get_expense(conn, expense_id)?;
+    if expense.payer_id() != user_id && !member_role(conn, expense.group_id(), user_id)?.can_edit_any_expense() {
+        return Err("Само платилият или администратор на групата може да изтрие разхода.".to_string());
+    }
+    check_group_active(conn, expense.group_id())?;
 
-        let on_time: i32 = stmt_check.query_row([debt_id], |row| row.get(0))
+    with_savepoint(conn, || {
+        reverse_expense(conn, &expense)?;
+        conn.execute("UPDATE expenses SET deleted = 1 WHERE id = ?1", [expense_id])
             .map_err(|e| e.to_string())?;
+        Ok(())
+    })
+}
 
-        if on_time == 1 {
-            conn.execute(
-                "UPDATE users
-                 SET on_time_payments = on_time_payments + 1
-                 WHERE id = ?1",
-                [from_id],
-            ).map_err(|e| e.to_string())?;
+/// Loads a single expense with its shares.
+pub fn get_expense(conn: &Connection, expense_id: i32) -> std::result::Result<Expense, String> {
+    let mut stmt = conn.prepare(
+        "SELECT e.id, e.group_id, e.payer_id, u.username, e.amount, e.description, e.due_date, e.split_mode, e.created_at,
+                e.currency, e.group_currency, e.group_amount
+         FROM expenses e
+         JOIN users u ON e.payer_id = u.id
+         WHERE e.id = ?1 AND e.deleted = 0"
+    ).map_err(|e| e.to_string())?;
 
-            let mut stmt_user = conn.prepare(
-                "SELECT on_time_payments FROM users WHERE id = ?1"
-            ).map_err(|e| e.to_string())?;
-            let payments: i32 = stmt_user.query_row([from_id], |row| row.get(0))
-                .map_err(|e| e.to_string())?;
+    let expense = stmt.query_row([expense_id], |row| {
+        Ok(Expense::new(
+            row.get(0)?,
+            row.get(1)?,
+            row.get(2)?,
+            row.get(3)?,
+            row.get(4)?,
+            row.get(5)?,
+            row.get(6)?,
+            row.get(7)?,
+            row.get(8)?,
+            Vec::new(),
+        ).with_currency(row.get(9)?, row.get(10)?, row.get(11)?))
+    }).optional().map_err(|e| e.to_string())?
+        .ok_or_else(|| "Разходът не е намерен.".to_string())?;
+
+    let shares = get_expense_shares(conn, expense_id)?;
+    Ok(expense.with_shares(shares))
+}
 
-            if payments >= 20 {
+fn get_expense_shares(conn: &Connection, expense_id: i32) -> std::result::Result<Vec<ExpenseShare>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT s.user_id, u.username, s.split_value, s.amount
+         FROM expense_shares s
+         JOIN users u ON s.user_id = u.id
+         WHERE s.expense_id = ?1
+         ORDER BY s.user_id"
+    ).map_err(|e| e.to_string())?;
+
+    let shares = stmt.query_map([expense_id], |row| {
+        Ok(ExpenseShare::new(
+            row.get(0)?,
+            row.get(1)?,
+            row.get(2)?,
+            row.get(3)?,
+        ))
+    }).map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(shares)
+}
+
+/// Returns the group's expenses in the order they were recorded.
+pub fn get_group_expenses(conn: &Connection, user_id: i32, group_id: i32) -> std::result::Result<Vec<Expense>, String> {
+    check_group_member(conn, group_id, user_id)?;
+    let mut stmt = conn.prepare(
+        "SELECT id FROM expenses WHERE group_id = ?1 AND deleted = 0 ORDER BY created_at, id"
+    ).map_err(|e| e.to_string())?;
+
+    let ids: Vec<i32> = stmt.query_map([group_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    ids.into_iter().map(|id| get_expense(conn, id)).collect()
+}
+
+fn check_group_active(conn: &Connection, group_id: i32) -> std::result::Result<(), String> {
+    let archived: bool = conn.query_row(
+        "SELECT archived_at IS NOT NULL FROM groups WHERE id = ?1",
+        [group_id],
+        |row| row.get(0),
+    ).optional().map_err(|e| e.to_string())?
+        .ok_or_else(|| "Групата не е намерена.".to_string())?;
+
+    if archived {
+        return Err("Групата е архивирана. Възстановете я, за да променяте разходите.".to_string());
+    }
+    Ok(())
+}
+
+fn check_group_member(conn: &Connection, group_id: i32, user_id: i32) -> std::result::Result<(), String> {
+    let is_member: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM group_members WHERE group_id = ?1 AND user_id = ?2)",
+        params![group_id, user_id],
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+
+    if !is_member {
+        return Err("Не сте член на тази група.".to_string());
+    }
+    Ok(())
+}
+
+/// The role `user_id` has in the group. The owner is whoever `groups.owner_id` names.
+fn member_role(conn: &Connection, group_id: i32, user_id: i32) -> std::result::Result<GroupRole, String> {
+    conn.query_row(
+        "SELECT CASE WHEN g.owner_id = gm.user_id THEN 'owner' ELSE gm.role END
+         FROM group_members gm
+         JOIN groups g ON g.id = gm.group_id
+         WHERE gm.group_id = ?1 AND gm.user_id = ?2",
+        params![group_id, user_id],
+        |row| row.get(0),
+    ).optional().map_err(|e| e.to_string())?
+        .ok_or_else(|| "Не сте член на тази група.".to_string())
+}
+
+/// The date of the `n`-th occurrence of a schedule, counting the start date as the 0th.
+fn occurrence_date(conn: &Connection, start_date: &str, interval: RecurrenceInterval, n: i64) -> std::result::Result<String, String> {
+    conn.query_row("SELECT date(?1, ?2, 'floor')", params![start_date, interval.modifier(n)], |row| row.get(0))
+        .map_err(|e| e.to_string())
+}
+
+/// Sets up an expense that the payer pays again every `interval`, from
+/// `start_date` until `end_date` if there is one. Occurrences are recorded as
+/// ordinary expenses by `materialize_recurring_expenses` once they come due,
+/// each with its occurrence date as the due date.
+/// Returns the id of the new schedule.
+#[allow(clippy::too_many_arguments)]
+pub fn add_recurring_expense(
+    conn: &Connection,
+    payer_id: i32,
+    group_id: i32,
+    amount: Money,
+    currency: Option<&str>,
+    description: &str,
+    interval: RecurrenceInterval,
+    start_date: &str,
+    end_date: Option<&str>,
+    split: &Split,
+) -> std::result::Result<i32, String> {
+    check_group_member(conn, group_id, payer_id)?;
+
+    if start_date.is_empty() {
+        return Err("Въведете начална дата.".to_string());
+    }
+    resolve_split(conn, payer_id, group_id, amount, start_date, split, &[])?;
+    let (currency, _, _) = expense_group_amount(conn, group_id, amount, currency)?;
+
+    let valid_dates: bool = conn.query_row(
+        "SELECT date(?1) IS ?1 AND (?2 IS NULL OR (date(?2) IS ?2 AND ?2 >= ?1))",
+        params![start_date, end_date],
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+
+    if !valid_dates {
+        return Err("Невалиден период. Крайната дата трябва да е след началната.".to_string());
+    }
+
+    with_savepoint(conn, || {
+        conn.execute(
+            "INSERT INTO recurring_expenses
+                 (group_id, payer_id, amount, currency, description, interval, start_date, end_date, next_date, split_mode)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?7, ?9)",
+            params![group_id, payer_id, amount, currency, description, interval, start_date, end_date, split.mode()],
+        ).map_err(|e| e.to_string())?;
+
+        let recurring_id = conn.last_insert_rowid() as i32;
+
+        for &(user_id, value) in split.entries() {
+            conn.execute(
+                "INSERT INTO recurring_expense_shares (recurring_id, user_id, split_value) VALUES (?1, ?2, ?3)",
+                params![recurring_id, user_id, value],
+            ).map_err(|e| e.to_string())?;
+        }
+
+        Ok(recurring_id)
+    })
+}
+
+/// Loads a single schedule with its split.
+pub fn get_recurring_expense(conn: &Connection, recurring_id: i32) -> std::result::Result<RecurringExpense, String> {
+    let recurring = conn.query_row(
+        "SELECT r.id, r.group_id, r.payer_id, u.username, r.amount, r.currency, r.description,
+                r.interval, r.start_date, r.end_date, r.next_date, r.status, r.split_mode
+         FROM recurring_expenses r
+         JOIN users u ON r.payer_id = u.id
+         WHERE r.id = ?1",
+        [recurring_id],
+        |row| {
+            let recurring = RecurringExpense::new(
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+                row.get(5)?,
+                row.get(6)?,
+                row.get(7)?,
+                row.get(8)?,
+                row.get(9)?,
+                row.get(10)?,
+                row.get(11)?,
+            );
+            let mode: SplitMode = row.get(12)?;
+            Ok((recurring, mode))
+        },
+    ).optional().map_err(|e| e.to_string())?;
+
+    let (recurring, mode) = recurring.ok_or_else(|| "Повтарящият се разход не е намерен.".to_string())?;
+
+    let mut stmt = conn.prepare(
+        "SELECT user_id, split_value FROM recurring_expense_shares WHERE recurring_id = ?1 ORDER BY user_id"
+    ).map_err(|e| e.to_string())?;
+
+    let entries = stmt.query_map([recurring_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<(i32, i64)>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(recurring.with_split(Split::new(mode, entries)))
+}
+
+/// Returns the group's schedules that are still running or paused, oldest first.
+pub fn get_group_recurring_expenses(conn: &Connection, user_id: i32, group_id: i32) -> std::result::Result<Vec<RecurringExpense>, String> {
+    check_group_member(conn, group_id, user_id)?;
+    let mut stmt = conn.prepare(
+        "SELECT id FROM recurring_expenses WHERE group_id = ?1 AND status IN ('active', 'paused') ORDER BY id"
+    ).map_err(|e| e.to_string())?;
+
+    let ids: Vec<i32> = stmt.query_map([group_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    if ids.is_empty() {
+        return Err("В групата няма повтарящи се разходи.".to_string());
+    }
+
+    ids.into_iter().map(|id| get_recurring_expense(conn, id)).collect()
+}
+
+/// Pauses, resumes or cancels a schedule. Any member of the group may do so.
+/// Occurrences that fell due while the schedule was paused are skipped, so a
+/// resumed schedule continues with its next occurrence from today on.
+pub fn set_recurring_status(conn: &Connection, user_id: i32, recurring_id: i32, status: RecurringStatus) -> std::result::Result<(), String> {
+    let recurring = get_recurring_expense(conn, recurring_id)?;
+    check_group_member(conn, recurring.group_id(), user_id)?;
+
+    if recurring.status().is_closed() {
+        return Err("Повтарящият се разход вече е приключил.".to_string());
+    }
+    if status == RecurringStatus::Finished {
+        return Err("Повтарящият се разход приключва сам след крайната си дата.".to_string());
+    }
+
+    with_savepoint(conn, || {
+        conn.execute("UPDATE recurring_expenses SET status = ?1 WHERE id = ?2", params![status, recurring_id])
+            .map_err(|e| e.to_string())?;
+
+        if recurring.status() == RecurringStatus::Paused && status == RecurringStatus::Active {
+            let mut occurrences: i64 = conn.query_row(
+                "SELECT occurrences FROM recurring_expenses WHERE id = ?1",
+                [recurring_id],
+                |row| row.get(0),
+            ).map_err(|e| e.to_string())?;
+
+            let today = today(conn)?;
+            let mut next_date = recurring.next_date().to_string();
+            while next_date < today {
+                occurrences += 1;
+                next_date = occurrence_date(conn, recurring.start_date(), recurring.interval(), occurrences)?;
+            }
+
+            conn.execute(
+                "UPDATE recurring_expenses SET occurrences = ?1, next_date = ?2 WHERE id = ?3",
+                params![occurrences, next_date, recurring_id],
+            ).map_err(|e| e.to_string())?;
+        }
+
+        Ok(())
+    })
+}
+
+fn today(conn: &Connection) -> std::result::Result<String, String> {
+    conn.query_row("SELECT date('now')", [], |row| row.get(0)).map_err(|e| e.to_string())
+}
+
+/// Records every occurrence of the active schedules that has come due, including
+/// ones missed while the application was not running, and finishes schedules
+/// that have passed their end date. A schedule whose occurrence can no longer be
+/// recorded, for example because a participant has left the group, is paused so
+/// the group can look at it. Returns the ids of the recorded expenses.
+pub fn materialize_recurring_expenses(conn: &Connection) -> std::result::Result<Vec<i32>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT id FROM recurring_expenses WHERE status = 'active' AND next_date <= date('now') ORDER BY id"
+    ).map_err(|e| e.to_string())?;
+
+    let ids: Vec<i32> = stmt.query_map([], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let today = today(conn)?;
+    let mut recorded = Vec::new();
+
+    for recurring_id in ids {
+        let recurring = get_recurring_expense(conn, recurring_id)?;
+        let mut next_date = recurring.next_date().to_string();
+
+        while next_date <= today {
+            if recurring.end_date().is_some_and(|end_date| next_date.as_str() > end_date) {
+                break;
+            }
+
+            let result = with_savepoint(conn, || {
+                let expense_id = add_expenses(
+                    conn,
+                    recurring.payer_id(),
+                    recurring.group_id(),
+                    recurring.amount(),
+                    Some(recurring.currency()),
+                    recurring.description(),
+                    &next_date,
+                    recurring.split(),
+                )?;
+                conn.execute("UPDATE expenses SET recurring_id = ?1 WHERE id = ?2", params![recurring_id, expense_id])
+                    .map_err(|e| e.to_string())?;
+
+                let next = conn.query_row(
+                    "UPDATE recurring_expenses SET occurrences = occurrences + 1 WHERE id = ?1 RETURNING occurrences",
+                    [recurring_id],
+                    |row| row.get(0),
+                ).map_err(|e| e.to_string())?;
+                let next = occurrence_date(conn, recurring.start_date(), recurring.interval(), next)?;
+                conn.execute("UPDATE recurring_expenses SET next_date = ?1 WHERE id = ?2", params![next, recurring_id])
+                    .map_err(|e| e.to_string())?;
+
+                Ok((expense_id, next))
+            });
+
+            match result {
+                Ok((expense_id, next)) => {
+                    next_date = next;
+                    recorded.push(expense_id);
+                }
+                Err(_) => {
+                    conn.execute("UPDATE recurring_expenses SET status = 'paused' WHERE id = ?1", [recurring_id])
+                        .map_err(|e| e.to_string())?;
+                    break;
+                }
+            }
+        }
+    }
+
+    conn.execute(
+        "UPDATE recurring_expenses SET status = 'finished'
+         WHERE status = 'active' AND end_date IS NOT NULL AND next_date > end_date",
+        [],
+    ).map_err(|e| e.to_string())?;
+
+    Ok(recorded)
+}
+
+pub fn get_group_members(conn: &Connection, user_id: i32, group_id: i32) -> std::result::Result<Vec<User>, String> {
+    check_group_member(conn, group_id, user_id)?;
+    group_members(conn, group_id)
+}
+
+fn group_members(conn: &Connection, group_id: i32) -> std::result::Result<Vec<User>, String> {
+    let mut stmt = conn
+        .prepare("SELECT u.id, u.username, u.email, u.loyal_payer
+             FROM users u
+             JOIN group_members gm ON u.id = gm.user_id
+             WHERE gm.group_id = ?1
+             ORDER BY u.username")
+        .map_err(|e| e.to_string())?;
+
+    let members = stmt
+        .query_map([group_id], |row| {
+            Ok(User::from_loyal_payer (
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(members)
+}
+
+/// Invites `invitee_id` to the group. They only become a member once they accept.
+pub fn invite_to_group(conn: &Connection, user_id: i32, group_id: i32, invitee_id: i32) -> std::result::Result<String, String> {
+    if !member_role(conn, group_id, user_id)?.can_manage_members() {
+        return Err("Само собственикът или администратор може да кани членове.".to_string());
+    }
+
+    let (username, inviter, group_name): (String, String, String) = conn.query_row(
+        "SELECT u.username, i.username, g.name FROM users u, users i, groups g WHERE u.id = ?1 AND i.id = ?2 AND g.id = ?3",
+        params![invitee_id, user_id, group_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    ).optional().map_err(|e| e.to_string())?
+        .ok_or_else(|| "Потребителят не е намерен.".to_string())?;
+
+    if check_group_member(conn, group_id, invitee_id).is_ok() {
+        return Err(format!("{} вече е в групата.", username));
+    }
+
+    let pending: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM group_invitations WHERE group_id = ?1 AND invitee_id = ?2 AND status = ?3)",
+        params![group_id, invitee_id, InvitationStatus::Pending],
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+    if pending {
+        return Err(format!("{} вече има покана за тази група.", username));
+    }
+
+    with_savepoint(conn, || {
+        let invitation_id: i32 = conn.query_row(
+            "INSERT INTO group_invitations (group_id, inviter_id, invitee_id) VALUES (?1, ?2, ?3) RETURNING id",
+            params![group_id, user_id, invitee_id],
+            |row| row.get(0),
+        ).map_err(|e| e.to_string())?;
+
+        let message = format!("{} ви кани в група {}", inviter, group_name);
+        notify(conn, invitee_id, NotificationKind::GroupInvitation, &format!("invitation:{}", invitation_id), &message, None, Some(group_id))?;
+
+        Ok(format!("Изпратихте покана на {}.", username))
+    })
+}
+
+/// Invitations the user has not answered yet, newest first.
+pub fn get_pending_invitations(conn: &Connection, user_id: i32) -> std::result::Result<Vec<Invitation>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT gi.id, gi.group_id, g.name, u.username, gi.created_at
+         FROM group_invitations gi
+         JOIN groups g ON g.id = gi.group_id
+         JOIN users u ON u.id = gi.inviter_id
+         WHERE gi.invitee_id = ?1 AND gi.status = ?2
+         ORDER BY gi.created_at DESC, gi.id DESC"
+    ).map_err(|e| e.to_string())?;
+
+    let invitations = stmt.query_map(params![user_id, InvitationStatus::Pending], |row| {
+        Ok(Invitation::new(row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+    })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(invitations)
+}
+
+/// Accepts or declines an invitation. Accepting makes the user a member of the group.
+pub fn respond_to_invitation(conn: &Connection, user_id: i32, invitation_id: i32, accept: bool) -> std::result::Result<String, String> {
+    let (group_id, group_name, inviter_id, status): (i32, String, i32, InvitationStatus) = conn.query_row(
+        "SELECT gi.group_id, g.name, gi.inviter_id, gi.status
+         FROM group_invitations gi
+         JOIN groups g ON g.id = gi.group_id
+         WHERE gi.id = ?1 AND gi.invitee_id = ?2",
+        params![invitation_id, user_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    ).optional().map_err(|e| e.to_string())?
+        .ok_or_else(|| "Поканата не е намерена.".to_string())?;
+
+    if status != InvitationStatus::Pending {
+        return Err("Вече отговорихте на тази покана.".to_string());
+    }
+
+    let username: String = conn.query_row("SELECT username FROM users WHERE id = ?1", [user_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    let status = if accept { InvitationStatus::Accepted } else { InvitationStatus::Declined };
+
+    with_savepoint(conn, || {
+        conn.execute(
+            "UPDATE group_invitations SET status = ?2, responded_at = CURRENT_TIMESTAMP WHERE id = ?1",
+            params![invitation_id, status],
+        ).map_err(|e| e.to_string())?;
+
+        let message = if accept {
+            conn.execute(
+                "INSERT OR IGNORE INTO group_members (group_id, user_id) VALUES (?1, ?2)",
+                params![group_id, user_id],
+            ).map_err(|e| e.to_string())?;
+            format!("{} прие поканата за група {}", username, group_name)
+        } else {
+            format!("{} отказа поканата за група {}", username, group_name)
+        };
+        notify(conn, inviter_id, NotificationKind::GroupInvitation, &format!("invitation_{}:{}", status.as_str(), invitation_id), &message, None, Some(group_id))?;
+
+        Ok(if accept {
+            format!("Присъединихте се към група {}.", group_name)
+        } else {
+            format!("Отказахте поканата за група {}.", group_name)
+        })
+    })
+}
+
+/// A new one-time code anyone can join the group with in the next
+/// `INVITE_CODE_VALID_DAYS` days.
+pub fn create_invite_code(conn: &Connection, user_id: i32, group_id: i32) -> std::result::Result<String, String> {
+    if !member_role(conn, group_id, user_id)?.can_manage_members() {
+        return Err("Само собственикът или администратор може да кани членове.".to_string());
+    }
+
+    loop {
+        let code = generate_invite_code();
+        let created = conn.execute(
+            "INSERT OR IGNORE INTO group_invite_codes (code, group_id, created_by, expires_at)
+             VALUES (?1, ?2, ?3, datetime('now', ?4))",
+            params![code, group_id, user_id, format!("+{} days", INVITE_CODE_VALID_DAYS)],
+        ).map_err(|e| e.to_string())?;
+
+        if created == 1 {
+            return Ok(code);
+        }
+    }
+}
+
+/// Joins the group an invite code was made for and uses the code up.
+pub fn join_group_with_code(conn: &Connection, user_id: i32, code: &str) -> std::result::Result<String, String> {
+    let code = parse_invite_code(code)?;
+
+    let (group_id, group_name, created_by, used, expired): (i32, String, i32, bool, bool) = conn.query_row(
+        "SELECT c.group_id, g.name, c.created_by, c.used_by IS NOT NULL, c.expires_at <= CURRENT_TIMESTAMP
+         FROM group_invite_codes c
+         JOIN groups g ON g.id = c.group_id
+         WHERE c.code = ?1",
+        [&code],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+    ).optional().map_err(|e| e.to_string())?
+        .ok_or_else(|| "Кодът за покана не е намерен.".to_string())?;
+
+    if used {
+        return Err("Кодът за покана вече е използван.".to_string());
+    }
+    if expired {
+        return Err("Кодът за покана е изтекъл.".to_string());
+    }
+    if check_group_member(conn, group_id, user_id).is_ok() {
+        return Err("Вече сте член на тази група.".to_string());
+    }
+
+    let username: String = conn.query_row("SELECT username FROM users WHERE id = ?1", [user_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+
+    with_savepoint(conn, || {
+        conn.execute(
+            "UPDATE group_invite_codes SET used_by = ?2, used_at = CURRENT_TIMESTAMP WHERE code = ?1",
+            params![code, user_id],
+        ).map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT INTO group_members (group_id, user_id) VALUES (?1, ?2)",
+            params![group_id, user_id],
+        ).map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE group_invitations SET status = ?3, responded_at = CURRENT_TIMESTAMP
+             WHERE group_id = ?1 AND invitee_id = ?2 AND status = ?4",
+            params![group_id, user_id, InvitationStatus::Accepted, InvitationStatus::Pending],
+        ).map_err(|e| e.to_string())?;
+
+        let message = format!("{} се присъедини към група {} с код за покана", username, group_name);
+        notify(conn, created_by, NotificationKind::GroupInvitation, &format!("invite_code:{}", code), &message, None, Some(group_id))?;
+
+        Ok(format!("Присъединихте се към група {}.", group_name))
+    })
+}
+
+/// Takes `member_id` out of a group. Anyone may leave, admins may remove
+/// members and only the owner may remove an admin. Members can only go once
+/// none of their debts in the group are open, and the owner has to hand over
+/// the group first.
+pub fn remove_group_member(conn: &Connection, user_id: i32, group_id: i32, member_id: i32) -> std::result::Result<String, String> {
+    let role = member_role(conn, group_id, user_id)?;
+    let member = member_role(conn, group_id, member_id)
+        .map_err(|_| "Потребителят не е член на тази група.".to_string())?;
+
+    if member.is_owner() {
+        return Err(if member_id == user_id {
+            "Прехвърлете собствеността на друг член, преди да напуснете групата.".to_string()
+        } else {
+            "Собственикът на групата не може да бъде премахнат.".to_string()
+        });
+    }
+    if member_id != user_id && !role.can_manage_members() {
+        return Err("Само собственикът или администратор може да премахва членове.".to_string());
+    }
+    if member_id != user_id && member == GroupRole::Admin && !role.is_owner() {
+        return Err("Само собственикът може да премахне администратор.".to_string());
+    }
+
+    let username: String = conn.query_row("SELECT username FROM users WHERE id = ?1", [member_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+
+    let open_debts: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM debts WHERE group_id = ?1 AND settled = 0 AND (from_id = ?2 OR to_id = ?2)",
+        params![group_id, member_id],
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+    if open_debts > 0 {
+        return Err(format!(
+            "{} има неуредени дългове в групата. Те трябва да бъдат изплатени или прехвърлени на друг член.",
+            username
+        ));
+    }
+
+    conn.execute(
+        "DELETE FROM group_members WHERE group_id = ?1 AND user_id = ?2",
+        params![group_id, member_id],
+    ).map_err(|e| e.to_string())?;
+
+    if member_id == user_id {
+        Ok("Напуснахте групата.".to_string())
+    }
+    else {
+        Ok(format!("Премахнахте {} от групата.", username))
+    }
+}
+
+pub fn leave_group(conn: &Connection, user_id: i32, group_id: i32) -> std::result::Result<String, String> {
+    remove_group_member(conn, user_id, group_id, user_id)
+}
+
+/// Asks `to_id` to take over `user_id`'s side of every open debt in the group,
+/// so that `user_id` can leave. Nothing moves until `to_id` accepts with
+/// `respond_to_debt_transfer`.
+pub fn request_debt_transfer(conn: &Connection, user_id: i32, group_id: i32, to_id: i32) -> std::result::Result<String, String> {
+    check_debt_transfer(conn, user_id, group_id, to_id)?;
+
+    let open_debts: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM debts WHERE group_id = ?1 AND settled = 0 AND (from_id = ?2 OR to_id = ?2))",
+        params![group_id, user_id],
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+    if !open_debts {
+        return Err("Нямате неуредени дългове в тази група.".to_string());
+    }
+
+    let pending: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM debt_transfers WHERE group_id = ?1 AND from_id = ?2 AND status = ?3)",
+        params![group_id, user_id, InvitationStatus::Pending],
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+    if pending {
+        return Err("Вече чакате отговор за прехвърляне на дълговете си в тази група.".to_string());
+    }
+
+    let (username, receiver, group_name): (String, String, String) = conn.query_row(
+        "SELECT u.username, r.username, g.name FROM users u, users r, groups g WHERE u.id = ?1 AND r.id = ?2 AND g.id = ?3",
+        params![user_id, to_id, group_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    ).map_err(|e| e.to_string())?;
+
+    with_savepoint(conn, || {
+        let transfer_id: i32 = conn.query_row(
+            "INSERT INTO debt_transfers (group_id, from_id, to_id) VALUES (?1, ?2, ?3) RETURNING id",
+            params![group_id, user_id, to_id],
+            |row| row.get(0),
+        ).map_err(|e| e.to_string())?;
+
+        let message = format!("{} иска да ви прехвърли дълговете си в група {}", username, group_name);
+        notify(conn, to_id, NotificationKind::DebtTransfer, &format!("debt_transfer:{}", transfer_id), &message, None, Some(group_id))?;
+
+        Ok(format!("Изпратихте молба за прехвърляне на {}.", receiver))
+    })
+}
+
+/// Both sides must be members and must not owe each other anything, or the
+/// receiver would end up owing themselves. Payments waiting for confirmation
+/// have to be finished first, since the debts they belong to are closed.
+fn check_debt_transfer(conn: &Connection, user_id: i32, group_id: i32, to_id: i32) -> std::result::Result<(), String> {
+    check_group_member(conn, group_id, user_id)?;
+    check_group_member(conn, group_id, to_id)
+        .map_err(|_| "Дълговете могат да бъдат прехвърлени само на член на групата.".to_string())?;
+
+    if to_id == user_id {
+        return Err("Изберете друг член на групата.".to_string());
+    }
+
+    let shared: bool = conn.query_row(
+        "SELECT EXISTS(
+             SELECT 1 FROM debts
+             WHERE group_id = ?1 AND settled = 0
+               AND ((from_id = ?2 AND to_id = ?3) OR (from_id = ?3 AND to_id = ?2))
+         )",
+        params![group_id, user_id, to_id],
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+    if shared {
+        return Err("Имате неуреден дълг точно с този член. Уредете го, преди да прехвърлите останалите.".to_string());
+    }
+
+    let unconfirmed: bool = conn.query_row(
+        "SELECT EXISTS(
+             SELECT 1 FROM payments p
+             JOIN debts d ON d.id = p.debt_id
+             WHERE d.group_id = ?1 AND d.settled = 0 AND (d.from_id = ?2 OR d.to_id = ?2)
+               AND NOT (p.confirmed_by_debtor = 1 AND p.confirmed_by_creditor = 1)
+         )",
+        params![group_id, user_id],
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+    if unconfirmed {
+        return Err("Има непотвърдени плащания по дълговете за прехвърляне. Приключете ги първо.".to_string());
+    }
+
+    Ok(())
+}
+
+/// Debt transfers waiting for the user's answer, newest first.
+pub fn get_pending_debt_transfers(conn: &Connection, user_id: i32) -> std::result::Result<Vec<DebtTransfer>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT dt.id, dt.group_id, g.name, u.username, dt.created_at
+         FROM debt_transfers dt
+         JOIN groups g ON g.id = dt.group_id
+         JOIN users u ON u.id = dt.from_id
+         WHERE dt.to_id = ?1 AND dt.status = ?2
+         ORDER BY dt.created_at DESC, dt.id DESC"
+    ).map_err(|e| e.to_string())?;
+
+    let transfers = stmt.query_map(params![user_id, InvitationStatus::Pending], |row| {
+        Ok(DebtTransfer::new(row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+    })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(transfers)
+}
+
+/// Accepts or declines a debt transfer. Accepting closes each of the
+/// requester's open debts in the group, keeping what was paid on it, and
+/// records the rest as owed by or to the user through `add_or_update_debt`,
+/// so it nets against what the user and the other member already owe each
+/// other. The new debts start unpaid and unconfirmed.
+pub fn respond_to_debt_transfer(conn: &Connection, user_id: i32, transfer_id: i32, accept: bool) -> std::result::Result<String, String> {
+    let (group_id, group_name, from_id, requester, status): (i32, String, i32, String, InvitationStatus) = conn.query_row(
+        "SELECT dt.group_id, g.name, dt.from_id, u.username, dt.status
+         FROM debt_transfers dt
+         JOIN groups g ON g.id = dt.group_id
+         JOIN users u ON u.id = dt.from_id
+         WHERE dt.id = ?1 AND dt.to_id = ?2",
+        params![transfer_id, user_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+    ).optional().map_err(|e| e.to_string())?
+        .ok_or_else(|| "Молбата за прехвърляне не е намерена.".to_string())?;
+
+    if status != InvitationStatus::Pending {
+        return Err("Вече отговорихте на тази молба.".to_string());
+    }
+    if accept {
+        check_debt_transfer(conn, from_id, group_id, user_id)?;
+    }
+
+    let username: String = conn.query_row("SELECT username FROM users WHERE id = ?1", [user_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    let status = if accept { InvitationStatus::Accepted } else { InvitationStatus::Declined };
+
+    with_savepoint(conn, || {
+        conn.execute(
+            "UPDATE debt_transfers SET status = ?2, responded_at = CURRENT_TIMESTAMP WHERE id = ?1",
+            params![transfer_id, status],
+        ).map_err(|e| e.to_string())?;
+
+        let message = if accept {
+            let debts: Vec<(i32, i32, i32, Money, String, String)> = conn.prepare(
+                "SELECT id, from_id, to_id, amount - paid, due_date, description
+                 FROM debts
+                 WHERE group_id = ?1 AND settled = 0 AND (from_id = ?2 OR to_id = ?2)
+                 ORDER BY id"
+            ).map_err(|e| e.to_string())?
+                .query_map(params![group_id, from_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?)))
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+
+            for (debt_id, debtor_id, creditor_id, remaining, due_date, description) in debts {
+                conn.execute(
+                    "UPDATE debts SET settled = 1, netted = 1, transfer_id = ?2 WHERE id = ?1",
+                    params![debt_id, transfer_id],
+                ).map_err(|e| e.to_string())?;
+
+                let (debtor_id, creditor_id) = if debtor_id == from_id { (user_id, creditor_id) } else { (debtor_id, user_id) };
+                if !remaining.is_positive() {
+                    continue;
+                }
+                let Some(taken_over) = add_or_update_debt(conn, debtor_id, creditor_id, group_id, remaining, &due_date, &description, None)? else {
+                    continue;
+                };
+                conn.execute(
+                    "INSERT OR IGNORE INTO debt_replacements (debt_id, replaced_by) VALUES (?1, ?2)",
+                    params![debt_id, taken_over],
+                ).map_err(|e| e.to_string())?;
+                conn.execute(
+                    "INSERT OR IGNORE INTO debt_expenses (debt_id, expense_id)
+                     SELECT ?1, expense_id FROM debt_expenses WHERE debt_id = ?2",
+                    params![taken_over, debt_id],
+                ).map_err(|e| e.to_string())?;
+            }
+            format!("{} пое дълговете ви в група {}", username, group_name)
+        } else {
+            format!("{} отказа да поеме дълговете ви в група {}", username, group_name)
+        };
+        notify(conn, from_id, NotificationKind::DebtTransfer, &format!("debt_transfer_{}:{}", status.as_str(), transfer_id), &message, None, Some(group_id))?;
+
+        Ok(if accept {
+            format!("Поехте дълговете на {} в група {}.", requester, group_name)
+        } else {
+            format!("Отказахте да поемете дълговете на {} в група {}.", requester, group_name)
+        })
+    })
+}
+
+/// The role of every member of the group.
+pub fn get_group_roles(conn: &Connection, user_id: i32, group_id: i32) -> std::result::Result<Vec<(i32, GroupRole)>, String> {
+    check_group_member(conn, group_id, user_id)?;
+    let mut stmt = conn.prepare(
+        "SELECT gm.user_id, CASE WHEN g.owner_id = gm.user_id THEN 'owner' ELSE gm.role END
+         FROM group_members gm
+         JOIN groups g ON g.id = gm.group_id
+         WHERE gm.group_id = ?1
+         ORDER BY gm.user_id"
+    ).map_err(|e| e.to_string())?;
+
+    let roles = stmt.query_map([group_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(roles)
+}
+
+/// Makes a member an admin or a plain member again. Only the owner may do this;
+/// ownership itself changes hands with `transfer_group_ownership`.
+pub fn set_member_role(conn: &Connection, user_id: i32, group_id: i32, member_id: i32, role: GroupRole) -> std::result::Result<(), String> {
+    if !member_role(conn, group_id, user_id)?.is_owner() {
+        return Err("Само собственикът може да променя ролите.".to_string());
+    }
+    if role.is_owner() {
+        return Err("Собствеността се прехвърля отделно.".to_string());
+    }
+    if member_role(conn, group_id, member_id)
+        .map_err(|_| "Потребителят не е член на тази група.".to_string())?
+        .is_owner() {
+        return Err("Собственикът не може да смени собствената си роля.".to_string());
+    }
+
+    conn.execute(
+        "UPDATE group_members SET role = ?3 WHERE group_id = ?1 AND user_id = ?2",
+        params![group_id, member_id, role],
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Hands the group over to another member. The previous owner stays on as an admin.
+pub fn transfer_group_ownership(conn: &Connection, user_id: i32, group_id: i32, new_owner_id: i32) -> std::result::Result<(), String> {
+    if !member_role(conn, group_id, user_id)?.is_owner() {
+        return Err("Само собственикът може да прехвърли групата.".to_string());
+    }
+    if new_owner_id == user_id {
+        return Err("Вече сте собственик на групата.".to_string());
+    }
+    member_role(conn, group_id, new_owner_id)
+        .map_err(|_| "Групата може да бъде прехвърлена само на неин член.".to_string())?;
+
+    with_savepoint(conn, || {
+        conn.execute("UPDATE groups SET owner_id = ?2 WHERE id = ?1", params![group_id, new_owner_id])
+            .map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE group_members SET role = CASE WHEN user_id = ?2 THEN ?3 ELSE ?4 END
+             WHERE group_id = ?1 AND user_id IN (?2, ?5)",
+            params![group_id, user_id, GroupRole::Admin, GroupRole::Member, new_owner_id],
+        ).map_err(|e| e.to_string())?;
+        Ok(())
+    })
+}
+
+/// Net balance of every group member over the group's open debts.
+pub fn get_group_balances(conn: &Connection, user_id: i32, group_id: i32) -> std::result::Result<Vec<Balance>, String> {
+    check_group_member(conn, group_id, user_id)?;
+    let mut stmt = conn.prepare(
+        "SELECT u.id, u.username,
+                COALESCE((SELECT SUM(amount - paid) FROM debts WHERE group_id = ?1 AND settled = 0 AND to_id = u.id), 0)
+              - COALESCE((SELECT SUM(amount - paid) FROM debts WHERE group_id = ?1 AND settled = 0 AND from_id = u.id), 0),
+                g.currency
+         FROM users u
+         JOIN group_members gm ON u.id = gm.user_id
+         JOIN groups g ON g.id = gm.group_id
+         WHERE gm.group_id = ?1
+         ORDER BY u.id"
+    ).map_err(|e| e.to_string())?;
+
+    let balances = stmt.query_map([group_id], |row| {
+        Ok(Balance::new(
+            row.get(0)?,
+            row.get(1)?,
+            row.get(2)?,
+        ).with_currency(row.get(3)?))
+    }).map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(balances)
+}
+
+/// The shortest list of payments that settles every open debt in the group.
+pub fn suggest_settlement(conn: &Connection, user_id: i32, group_id: i32) -> std::result::Result<Vec<Transfer>, String> {
+    Ok(simplify(&get_group_balances(conn, user_id, group_id)?))
+}
+
+/// The group screen: members, their balances, the suggested settlement and
+/// every expense and payment in the order they were recorded.
+pub fn get_group_detail(conn: &Connection, user_id: i32, group_id: i32) -> std::result::Result<GroupDetail, String> {
+    let role = member_role(conn, group_id, user_id)?;
+    let group = conn.query_row(
+        "SELECT id, name, currency, archived_at IS NOT NULL FROM groups WHERE id = ?1",
+        [group_id],
+        |row| Ok(Group::new(row.get(0)?, row.get(1)?).with_currency(row.get(2)?).with_archived(row.get(3)?)),
+    ).map_err(|e| e.to_string())?
+        .with_role(role);
+
+    let balances = get_group_balances(conn, user_id, group_id)?;
+    let settlement = simplify(&balances);
+
+    let mut history: Vec<GroupActivity> = get_group_expenses(conn, user_id, group_id)?
+        .into_iter()
+        .map(GroupActivity::Expense)
+        .chain(query_payments(conn, "d.group_id = ?1", group_id)?.into_iter().rev().map(GroupActivity::Payment))
+        .collect();
+    history.sort_by(|a, b| a.created_at().cmp(b.created_at()));
+
+    Ok(GroupDetail::new(group, group_members(conn, group_id)?)
+        .with_balances(balances, settlement)
+        .with_history(history))
+}
+
+/// Replaces the group's open debts with the suggested transfers. The old debts
+/// are closed as netted and recorded as replaced by the transfers between the
+/// people they involve, which also take over their expenses. Only the
+/// owner and admins can do this, and only while the group is active.
+/// Debts that either side has already confirmed are left alone, so the
+/// settlement is refused until those are fully settled.
+pub fn apply_settlement(conn: &Connection, user_id: i32, group_id: i32) -> std::result::Result<(), String> {
+    if !member_role(conn, group_id, user_id)?.can_manage_group() {
+        return Err("Само собственикът или администратор може да променя групата.".to_string());
+    }
+    check_group_active(conn, group_id)?;
+
+    let confirmed: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM debts
+                       WHERE group_id = ?1 AND settled = 0
+                         AND (confirmed_by_debtor = 1 OR confirmed_by_creditor = 1
+                              OR EXISTS(SELECT 1 FROM payments p WHERE p.debt_id = debts.id)))",
+        [group_id],
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+
+    if confirmed {
+        return Err("Има започнати плащания в групата. Приключете ги преди оптимизиране.".to_string());
+    }
+
+    let transfers = suggest_settlement(conn, user_id, group_id)?;
+
+    with_savepoint(conn, || {
+        let due_date: String = conn.query_row(
+            "SELECT COALESCE(MAX(due_date), '') FROM debts WHERE group_id = ?1 AND settled = 0",
+            [group_id],
+            |row| row.get(0),
+        ).map_err(|e| e.to_string())?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, from_id, to_id FROM debts WHERE group_id = ?1 AND settled = 0"
+        ).map_err(|e| e.to_string())?;
+
+        let old_debts: Vec<(i32, i32, i32)> = stmt.query_map([group_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+
+        conn.execute("UPDATE debts SET settled = 1, netted = 1 WHERE group_id = ?1 AND settled = 0", [group_id])
+            .map_err(|e| e.to_string())?;
+
+        for transfer in &transfers {
+            let debt_id = insert_debt(
+                conn,
+                transfer.from_id(),
+                transfer.to_id(),
+                group_id,
+                transfer.amount(),
+                &due_date,
+                "Оптимизирано разпределение",
+            )?;
+
+            // A transfer carries the balances of the two people it is between,
+            // which every old debt of either of them went into.
+            let parties = [transfer.from_id(), transfer.to_id()];
+            for &(old_id, from_id, to_id) in &old_debts {
+                if !parties.contains(&from_id) && !parties.contains(&to_id) {
+                    continue;
+                }
+
+                conn.execute(
+                    "INSERT INTO debt_replacements (debt_id, replaced_by) VALUES (?1, ?2)",
+                    params![old_id, debt_id],
+                ).map_err(|e| e.to_string())?;
                 conn.execute(
-                    "UPDATE users SET loyal_payer = 1 WHERE id = ?1",
-                    [from_id],
+                    "INSERT OR IGNORE INTO debt_expenses (debt_id, expense_id)
+                     SELECT ?1, expense_id FROM debt_expenses WHERE debt_id = ?2",
+                    params![debt_id, old_id],
                 ).map_err(|e| e.to_string())?;
             }
         }
-        else if on_time == 0 {
 
+        Ok(())
+    })
+}
+
+/// The user's net balance with every person they share open debts with, across all groups.
+/// Debts in different currencies are netted separately, one balance per currency.
+/// A positive amount means the other person owes the user.
+pub fn get_counterparty_balances(conn: &Connection, user_id: i32) -> std::result::Result<Vec<Balance>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT u.id, u.username,
+                SUM(CASE WHEN d.to_id = ?1 THEN d.amount - d.paid ELSE d.paid - d.amount END),
+                d.currency
+         FROM debts d
+         JOIN users u ON u.id = CASE WHEN d.to_id = ?1 THEN d.from_id ELSE d.to_id END
+         WHERE (d.from_id = ?1 OR d.to_id = ?1) AND d.settled = 0
+         GROUP BY u.id, u.username, d.currency
+         ORDER BY u.username, d.currency"
+    ).map_err(|e| e.to_string())?;
+
+    let balances = stmt.query_map([user_id], |row| {
+        Ok(Balance::new(
+            row.get(0)?,
+            row.get(1)?,
+            row.get(2)?,
+        ).with_currency(row.get(3)?))
+    }).map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    if balances.is_empty() {
+        return Err("Нямате неуредени сметки.".to_string());
+    }
+
+    Ok(balances)
+}
+
+/// Confirms, on behalf of `user_id`, every open debt between the user and
+/// `counterparty_id` in every group, all in one step. Once both people have
+/// settled up, all of those debts are closed together by `close_settled_up`.
+pub fn settle_up_with(conn: &Connection, user_id: i32, counterparty_id: i32) -> std::result::Result<String, String> {
+    let mut stmt = conn.prepare(
+        "SELECT id, amount - paid FROM debts
+         WHERE settled = 0
+           AND ((from_id = ?1 AND to_id = ?2) OR (from_id = ?2 AND to_id = ?1))
+         ORDER BY id"
+    ).map_err(|e| e.to_string())?;
+
+    let debts: Vec<(i32, Money)> = stmt.query_map(params![user_id, counterparty_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    if debts.is_empty() {
+        return Err("Нямате неуредени сметки с този потребител.".to_string());
+    }
+
+    let pending: bool = conn.query_row(
+        "SELECT EXISTS(
+             SELECT 1 FROM payments p
+             JOIN debts d ON d.id = p.debt_id
+             WHERE d.settled = 0
+               AND ((d.from_id = ?1 AND d.to_id = ?2) OR (d.from_id = ?2 AND d.to_id = ?1))
+               AND NOT (p.confirmed_by_debtor = 1 AND p.confirmed_by_creditor = 1)
+         )",
+        params![user_id, counterparty_id],
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+    if pending {
+        return Err("Има плащания, които чакат потвърждение. Потвърдете ги, преди да уредите сметките.".to_string());
+    }
+
+    with_savepoint(conn, || {
+        conn.execute(
+            "UPDATE debts
+             SET confirmed_by_debtor = confirmed_by_debtor OR from_id = ?1,
+                 confirmed_by_creditor = confirmed_by_creditor OR to_id = ?1
+             WHERE settled = 0
+               AND ((from_id = ?1 AND to_id = ?2) OR (from_id = ?2 AND to_id = ?1))",
+            params![user_id, counterparty_id],
+        ).map_err(|e| e.to_string())?;
+
+        for &(debt_id, remaining) in &debts {
+            notify_payment_confirmed(conn, user_id, debt_id, remaining, &format!("debt:{}", debt_id))?;
+        }
+
+        let waiting: bool = conn.query_row(
+            "SELECT EXISTS(
+                 SELECT 1 FROM debts
+                 WHERE settled = 0
+                   AND ((from_id = ?1 AND to_id = ?2) OR (from_id = ?2 AND to_id = ?1))
+                   AND NOT (confirmed_by_debtor = 1 AND confirmed_by_creditor = 1)
+             )",
+            params![user_id, counterparty_id],
+            |row| row.get(0),
+        ).map_err(|e| e.to_string())?;
+
+        if waiting {
+            return Ok("Потвърдено. Очаква се другата страна да уреди сметките.".to_string());
+        }
+
+        close_settled_up(conn, user_id, counterparty_id)?;
+        Ok("Всички сметки с този потребител са уредени.".to_string())
+    })
+}
+
+/// Closes every open debt between two people once both have settled up. In each
+/// currency the debts are netted against each other and replaced by one debt for
+/// the difference, owed by whoever owed more, in the group of their first debt.
+/// That debt is paid off with a single payment, which is the only money that moved.
+fn close_settled_up(conn: &Connection, user_id: i32, counterparty_id: i32) -> std::result::Result<(), String> {
+    let mut stmt = conn.prepare(
+        "SELECT id, from_id, group_id, amount - paid, due_date, currency FROM debts
+         WHERE settled = 0
+           AND ((from_id = ?1 AND to_id = ?2) OR (from_id = ?2 AND to_id = ?1))
+         ORDER BY id"
+    ).map_err(|e| e.to_string())?;
+
+    let debts: Vec<(i32, i32, i32, Money, String, String)> = stmt.query_map(params![user_id, counterparty_id], |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?))
+    })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut currencies: Vec<&str> = debts.iter().map(|debt| debt.5.as_str()).collect();
+    currencies.sort();
+    currencies.dedup();
+
+    for currency in currencies {
+        let debts: Vec<_> = debts.iter().filter(|debt| debt.5 == currency).collect();
+
+        // Positive when the counterparty owes the user.
+        let balance: Money = debts.iter()
+            .map(|&&(_, from_id, _, remaining, _, _)| if from_id == user_id { -remaining } else { remaining })
+            .sum();
+
+        for &&(debt_id, ..) in &debts {
+            conn.execute("UPDATE debts SET settled = 1, netted = 1 WHERE id = ?1", [debt_id])
+                .map_err(|e| e.to_string())?;
+        }
+
+        if balance.is_zero() {
+            continue;
+        }
+
+        let (from_id, to_id) = if balance.is_positive() { (counterparty_id, user_id) } else { (user_id, counterparty_id) };
+        let owed: Vec<_> = debts.iter().filter(|debt| debt.1 == from_id).collect();
+        let group_id = owed[0].2;
+        let due_date = owed.iter().map(|debt| debt.4.as_str()).max().unwrap_or_default();
+
+        let net_id: i32 = conn.query_row(
+            "INSERT INTO debts (from_id, to_id, group_id, amount, paid, due_date, description, currency)
+             VALUES (?1, ?2, ?3, ?4, ?4, ?5, 'Уреждане на сметките', ?6)
+             RETURNING id",
+            params![from_id, to_id, group_id, balance.abs(), due_date, currency],
+            |row| row.get(0),
+        ).map_err(|e| e.to_string())?;
+
+        for &&(debt_id, ..) in &debts {
+            conn.execute(
+                "INSERT INTO debt_replacements (debt_id, replaced_by) VALUES (?1, ?2)",
+                params![debt_id, net_id],
+            ).map_err(|e| e.to_string())?;
+            conn.execute(
+                "INSERT OR IGNORE INTO debt_expenses (debt_id, expense_id)
+                 SELECT ?1, expense_id FROM debt_expenses WHERE debt_id = ?2",
+                params![net_id, debt_id],
+            ).map_err(|e| e.to_string())?;
+        }
+
+        conn.execute(
+            "INSERT INTO payments (debt_id, amount, method, paid_on, confirmed_by_debtor, confirmed_by_creditor)
+             VALUES (?1, ?2, ?3, date('now'), 1, 1)",
+            params![net_id, balance.abs(), PaymentMethod::Other],
+        ).map_err(|e| e.to_string())?;
+        close_debt(conn, net_id, from_id)?;
+    }
+
+    Ok(())
+}
+
+pub fn get_user_debts_or_credits(conn: &Connection, user_id: i32, is_debt: bool) -> Result<Vec<Expenses>, String> {
+    let condition2 = if is_debt { "d.from_id = ?" } else { "d.to_id = ?" };
+    let condition1 = if is_debt { "d.to_id" } else { "d.from_id" };
+
+    let query = format!(
+        "SELECT d.id, u.username, d.amount, g.name, d.due_date, d.description, d.paid, d.currency
+         FROM debts d
+         JOIN users u ON {} = u.id
+         JOIN groups g ON d.group_id = g.id
+         WHERE {}
+         AND d.settled = 0
+         ORDER BY d.due_date ASC;",
+        condition1, condition2
+    );
+
+    let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
+
+    let expenses = stmt
+        .query_map([user_id], |row| {
+            Ok((Expenses::new(
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+                row.get(5)?,
+            ).with_currency(row.get(7)?), row.get::<_, Money>(6)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|(debt, paid)| {
+            let payments = get_debt_payments(conn, debt.id())?;
+            Ok(debt.with_payments(paid, payments))
+        })
+        .collect::<std::result::Result<Vec<_>, String>>()?;
+
+    if expenses.is_empty() {
+        return Err("Нямате дългове или вземания!".to_string());
+    }
+
+    Ok(expenses)
+}
+
+/// Confirms on behalf of `user_id` that the rest of the debt was paid. Once both
+/// sides have confirmed, what was left is recorded as one payment and the debt is
+/// closed. Partial payments still waiting for confirmation have to be confirmed first.
+pub fn payment_confirmation(conn: &Connection, user_id: i32, debt_id: i32) -> std::result::Result<String, String> {
+    let mut stmt = conn.prepare(
+        "SELECT from_id, to_id, confirmed_by_debtor, confirmed_by_creditor
+         FROM debts WHERE id = ?1 AND settled = 0"
+    ).map_err(|e| e.to_string())?;
+
+    let (from_id, to_id, mut debtor_conf, mut creditor_conf): (i32, i32, bool, bool) =
+        stmt.query_row([debt_id], |row| {
+            Ok(
+                (row.get(0)?,
+                 row.get(1)?,
+                 row.get(2)?,
+                 row.get(3)?)
+            )
+        }).map_err(|e| e.to_string())?;
+
+    if user_id == from_id {
+        debtor_conf = true;
+    }
+    else if user_id == to_id {
+        creditor_conf = true;
+    }
+    else {
+        return Err("Потребителят не е участник в този дълг.".to_string());
+    }
+
+    let pending: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM payments
+                       WHERE debt_id = ?1 AND NOT (confirmed_by_debtor = 1 AND confirmed_by_creditor = 1))",
+        [debt_id],
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+    if pending {
+        return Err("Има плащания, които чакат потвърждение. Потвърдете ги, преди да приключите дълга.".to_string());
+    }
+
+    let remaining: Money = conn.query_row("SELECT amount - paid FROM debts WHERE id = ?1", [debt_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+
+    if debtor_conf && creditor_conf {
+        with_savepoint(conn, || {
+            notify_payment_confirmed(conn, user_id, debt_id, remaining, &format!("debt:{}", debt_id))?;
+            conn.execute(
+                "INSERT INTO payments (debt_id, amount, method, paid_on, confirmed_by_debtor, confirmed_by_creditor)
+                 SELECT id, amount - paid, ?2, date('now'), 1, 1 FROM debts WHERE id = ?1 AND amount > paid",
+                params![debt_id, PaymentMethod::Other],
+            ).map_err(|e| e.to_string())?;
+            conn.execute("UPDATE debts SET paid = amount WHERE id = ?1", [debt_id])
+                .map_err(|e| e.to_string())?;
+            close_debt(conn, debt_id, from_id)
+        })?;
+        Ok("Дългът е напълно изплатен и приключен.".to_string())
+    }
+    else {
+        conn.execute(
+            "UPDATE debts
+             SET confirmed_by_debtor = ?1,
+                 confirmed_by_creditor = ?2
+             WHERE id = ?3",
+            params![debtor_conf, creditor_conf, debt_id],
+        ).map_err(|e| e.to_string())?;
+        notify_payment_confirmed(conn, user_id, debt_id, remaining, &format!("debt:{}", debt_id))?;
+        Ok("Потвърдено. Очаква се другата страна да потвърди.".to_string())
+    }
+}
+
+/// Marks the debt as settled and updates the debtor's on-time payment record.
+fn close_debt(conn: &Connection, debt_id: i32, from_id: i32) -> std::result::Result<(), String> {
+    conn.execute(
+        "UPDATE debts
+         SET confirmed_by_debtor = 1,
+             confirmed_by_creditor = 1,
+             settled = 1
+         WHERE id = ?1",
+        [debt_id],
+    ).map_err(|e| e.to_string())?;
+
+    let mut stmt_check = conn.prepare(
+        "SELECT CASE
+                    WHEN due_date IS NULL THEN -1
+                    WHEN due_date >= date('now') THEN 1
+                    ELSE 0
+                END
+            FROM debts
+            WHERE id = ?1;"
+    ).map_err(|e| e.to_string())?;
+
+    let on_time: i32 = stmt_check.query_row([debt_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+
+    if on_time == 1 {
+        conn.execute(
+            "UPDATE users
+             SET on_time_payments = on_time_payments + 1
+             WHERE id = ?1",
+            [from_id],
+        ).map_err(|e| e.to_string())?;
+
+        let mut stmt_user = conn.prepare(
+            "SELECT on_time_payments FROM users WHERE id = ?1"
+        ).map_err(|e| e.to_string())?;
+        let payments: i32 = stmt_user.query_row([from_id], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+
+        if payments >= 20 {
+            conn.execute(
+                "UPDATE users SET loyal_payer = 1 WHERE id = ?1",
+                [from_id],
+            ).map_err(|e| e.to_string())?;
+        }
+    }
+    else if on_time == 0 {
+
+        conn.execute(
+            "UPDATE users
+             SET loyal_payer = 0
+             WHERE id = ?1",
+            [from_id],
+        ).map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+/// Records that part of a debt was paid on `paid_on`. The person recording the
+/// payment confirms it right away; it counts towards the debt once the other
+/// side confirms it too.
+pub fn record_partial_payment(
+    conn: &Connection,
+    user_id: i32,
+    debt_id: i32,
+    amount: Money,
+    method: PaymentMethod,
+    paid_on: &str,
+) -> std::result::Result<String, String> {
+    let (from_id, to_id, debt_amount, paid, currency): (i32, i32, Money, Money, String) = conn.query_row(
+        "SELECT from_id, to_id, amount, paid, currency FROM debts WHERE id = ?1 AND settled = 0",
+        [debt_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+    ).optional().map_err(|e| e.to_string())?
+        .ok_or_else(|| "Дългът не е намерен или вече е изплатен.".to_string())?;
+
+    if user_id != from_id && user_id != to_id {
+        return Err("Потребителят не е участник в този дълг.".to_string());
+    }
+
+    if !amount.is_positive() {
+        return Err("Сумата на плащането трябва да е положителна.".to_string());
+    }
+
+    let valid_date: bool = conn.query_row("SELECT date(?1) IS ?1", [paid_on], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if !valid_date {
+        return Err("Невалидна дата на плащане. Използвайте формат ГГГГ-ММ-ДД.".to_string());
+    }
+
+    let pending: Money = conn.query_row(
+        "SELECT COALESCE(SUM(amount), 0) FROM payments
+         WHERE debt_id = ?1 AND NOT (confirmed_by_debtor = 1 AND confirmed_by_creditor = 1)",
+        [debt_id],
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+
+    let open = debt_amount - paid - pending;
+    if amount > open {
+        return Err(format!("Плащането надвишава оставащата сума от {}", format_amount(open, &currency)));
+    }
+
+    conn.execute(
+        "INSERT INTO payments (debt_id, amount, method, paid_on, confirmed_by_debtor, confirmed_by_creditor)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![debt_id, amount, method, paid_on, user_id == from_id, user_id == to_id],
+    ).map_err(|e| e.to_string())?;
+
+    Ok("Плащането е записано. Очаква се другата страна да потвърди.".to_string())
+}
+
+/// Confirms a partial payment on behalf of `user_id`. Once both sides have
+/// confirmed it, the payment is taken off the debt, and a debt that is paid
+/// off completely is closed.
+pub fn confirm_partial_payment(conn: &Connection, user_id: i32, payment_id: i32) -> std::result::Result<String, String> {
+    let (debt_id, amount, mut debtor_conf, mut creditor_conf, from_id, to_id): (i32, Money, bool, bool, i32, i32) = conn.query_row(
+        "SELECT p.debt_id, p.amount, p.confirmed_by_debtor, p.confirmed_by_creditor, d.from_id, d.to_id
+         FROM payments p
+         JOIN debts d ON d.id = p.debt_id
+         WHERE p.id = ?1 AND d.settled = 0",
+        [payment_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?)),
+    ).optional().map_err(|e| e.to_string())?
+        .ok_or_else(|| "Плащането не е намерено.".to_string())?;
+
+    if debtor_conf && creditor_conf {
+        return Err("Плащането вече е потвърдено.".to_string());
+    }
+
+    if user_id == from_id {
+        debtor_conf = true;
+    }
+    else if user_id == to_id {
+        creditor_conf = true;
+    }
+    else {
+        return Err("Потребителят не е участник в този дълг.".to_string());
+    }
+
+    with_savepoint(conn, || {
+        conn.execute(
+            "UPDATE payments SET confirmed_by_debtor = ?1, confirmed_by_creditor = ?2 WHERE id = ?3",
+            params![debtor_conf, creditor_conf, payment_id],
+        ).map_err(|e| e.to_string())?;
+        notify_payment_confirmed(conn, user_id, debt_id, amount, &format!("payment:{}", payment_id))?;
+
+        if !(debtor_conf && creditor_conf) {
+            return Ok("Потвърдено. Очаква се другата страна да потвърди.".to_string());
+        }
+
+        conn.execute(
+            "UPDATE debts SET paid = paid + ?1 WHERE id = ?2",
+            params![amount, debt_id],
+        ).map_err(|e| e.to_string())?;
+
+        let (remaining, currency): (Money, String) = conn.query_row(
+            "SELECT amount - paid, currency FROM debts WHERE id = ?1",
+            [debt_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).map_err(|e| e.to_string())?;
+
+        if remaining.is_positive() {
+            Ok(format!("Плащането е потвърдено. Остават {}", format_amount(remaining, &currency)))
+        }
+        else {
+            close_debt(conn, debt_id, from_id)?;
+            Ok("Дългът е напълно изплатен и приключен.".to_string())
+        }
+    })
+}
+
+/// The payments made against a debt, oldest first.
+pub fn get_debt_payments(conn: &Connection, debt_id: i32) -> std::result::Result<Vec<Payment>, String> {
+    let mut payments = query_payments(conn, "p.debt_id = ?1", debt_id)?;
+    payments.reverse();
+    Ok(payments)
+}
+
+/// Every payment the user made or received, newest first.
+pub fn get_user_payments(conn: &Connection, user_id: i32) -> std::result::Result<Vec<Payment>, String> {
+    let payments = query_payments(conn, "(d.from_id = ?1 OR d.to_id = ?1)", user_id)?;
+
+    if payments.is_empty() {
+        return Err("Нямате плащания!".to_string());
+    }
+
+    Ok(payments)
+}
+
+/// Every payment made against the group's debts, newest first.
+pub fn get_group_payments(conn: &Connection, user_id: i32, group_id: i32) -> std::result::Result<Vec<Payment>, String> {
+    check_group_member(conn, group_id, user_id)?;
+    let payments = query_payments(conn, "d.group_id = ?1", group_id)?;
+
+    if payments.is_empty() {
+        return Err("В групата няма плащания.".to_string());
+    }
+
+    Ok(payments)
+}
+
+fn query_payments(conn: &Connection, condition: &str, id: i32) -> std::result::Result<Vec<Payment>, String> {
+    let query = format!(
+        "SELECT p.id, p.debt_id, p.amount, p.method, p.paid_on, p.created_at,
+                p.confirmed_by_debtor, p.confirmed_by_creditor,
+                debtor.username, creditor.username, g.name, d.currency
+         FROM payments p
+         JOIN debts d ON d.id = p.debt_id
+         JOIN users debtor ON debtor.id = d.from_id
+         JOIN users creditor ON creditor.id = d.to_id
+         JOIN groups g ON g.id = d.group_id
+         WHERE {}
+         ORDER BY p.paid_on DESC, p.id DESC",
+        condition
+    );
+
+    let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
+
+    let payments = stmt.query_map([id], |row| {
+        Ok(Payment::new(
+            row.get(0)?,
+            row.get(1)?,
+            row.get(2)?,
+            row.get(3)?,
+            row.get(4)?,
+            row.get(5)?,
+            row.get(6)?,
+            row.get(7)?,
+        ).with_parties(row.get(8)?, row.get(9)?, row.get(10)?, row.get(11)?))
+    }).map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(payments)
+}
+
+/// Records a notification about an event. Each event is identified by
+/// `event_key`, so a user hears about it only once however often it is reported.
+///
+/// The user's preferences decide what happens next: muted kinds and groups are
+/// dropped, in digest mode the notification is held for the daily digest, and
+/// during quiet hours it reaches the inbox but is sent elsewhere only once they
+/// are over. Returns whether the user should be told about it right away.
+fn notify(
+    conn: &Connection,
+    user_id: i32,
+    kind: NotificationKind,
+    event_key: &str,
+    message: &str,
+    debt_id: Option<i32>,
+    group_id: Option<i32>,
+) -> std::result::Result<bool, String> {
+    let preferences = get_notification_preferences(conn, user_id)?;
+    if kind != NotificationKind::Digest && preferences.mutes(kind, group_id) {
+        return Ok(false);
+    }
+
+    let held = preferences.digest() && kind != NotificationKind::Digest;
+    let created = conn.execute(
+        "INSERT OR IGNORE INTO notifications (user_id, kind, event_key, message, debt_id, group_id, held)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![user_id, kind, event_key, message, debt_id, group_id, held],
+    ).map_err(|e| e.to_string())?;
+    if created == 0 || held {
+        return Ok(false);
+    }
+    let notification_id = conn.last_insert_rowid();
+
+    let now: String = conn.query_row("SELECT strftime('%H:%M', 'now', 'localtime')", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    let quiet_until: Option<String> = match preferences.quiet_hours() {
+        Some((_, end)) if preferences.is_quiet_at(&now) => Some(conn.query_row(
+            "SELECT CASE
+                 WHEN datetime(date('now', 'localtime') || ' ' || ?1, 'utc') > datetime('now')
+                 THEN datetime(date('now', 'localtime') || ' ' || ?1, 'utc')
+                 ELSE datetime(date('now', 'localtime') || ' ' || ?1, 'utc', '+1 day')
+             END",
+            [end],
+            |row| row.get(0),
+        ).map_err(|e| e.to_string())?),
+        _ => None,
+    };
+
+    conn.execute(
+        "INSERT INTO notification_deliveries (notification_id, channel, target, next_attempt_at)
+         SELECT ?1, channel, target, COALESCE(?3, CURRENT_TIMESTAMP) FROM notification_channels WHERE user_id = ?2",
+        params![notification_id, user_id, quiet_until],
+    ).map_err(|e| e.to_string())?;
+
+    Ok(quiet_until.is_none())
+}
+
+pub fn get_notification_preferences(conn: &Connection, user_id: i32) -> Result<NotificationPreferences, String> {
+    let muted_groups: Vec<i32> = conn.prepare("SELECT group_id FROM notification_group_mutes WHERE user_id = ?1 ORDER BY group_id")
+        .map_err(|e| e.to_string())?
+        .query_map([user_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    let preferences = NotificationPreferences::new().with_muted_groups(muted_groups);
+
+    let row: Option<(String, NotificationPreferences)> = conn.query_row(
+        "SELECT muted_kinds, quiet_start, quiet_end, digest, digest_time FROM notification_preferences WHERE user_id = ?1",
+        [user_id],
+        |row| {
+            let quiet_start: Option<String> = row.get(1)?;
+            Ok((row.get(0)?, preferences.clone()
+                .with_quiet_hours(quiet_start.zip(row.get(2)?))
+                .with_digest(row.get(3)?)
+                .with_digest_time(row.get(4)?)))
+        },
+    ).optional().map_err(|e| e.to_string())?;
+
+    let Some((muted_kinds, preferences)) = row else {
+        return Ok(preferences);
+    };
+
+    let muted_kinds = NotificationKind::ALL
+        .into_iter()
+        .filter(|kind| muted_kinds.split(',').any(|muted| muted == kind.as_str()))
+        .collect();
+
+    Ok(preferences.with_muted_kinds(muted_kinds))
+}
+
+/// Replaces the user's notification preferences. Groups can only be muted by
+/// their members. Turning the digest off releases everything it was holding
+/// into the inbox.
+pub fn set_notification_preferences(conn: &Connection, user_id: i32, preferences: &NotificationPreferences) -> Result<(), String> {
+    let quiet_hours = match preferences.quiet_hours() {
+        Some((start, end)) => {
+            let (start, end) = (parse_time_of_day(start)?, parse_time_of_day(end)?);
+            if start == end {
+                return Err("Тихите часове трябва да започват и свършват в различно време.".to_string());
+            }
+            Some((start, end))
+        }
+        None => None,
+    };
+    let digest_time = parse_time_of_day(preferences.digest_time())?;
+    for &group_id in preferences.muted_groups() {
+        check_group_member(conn, group_id, user_id)?;
+    }
+
+    let muted_kinds = preferences.muted_kinds()
+        .iter()
+        .filter(|kind| **kind != NotificationKind::Digest)
+        .map(|kind| kind.as_str())
+        .collect::<Vec<_>>()
+        .join(",");
+    let (quiet_start, quiet_end) = quiet_hours.unzip();
+
+    with_savepoint(conn, || {
+        conn.execute(
+            "INSERT INTO notification_preferences (user_id, muted_kinds, quiet_start, quiet_end, digest, digest_time)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT (user_id) DO UPDATE SET
+                 muted_kinds = excluded.muted_kinds,
+                 quiet_start = excluded.quiet_start,
+                 quiet_end = excluded.quiet_end,
+                 digest = excluded.digest,
+                 digest_time = excluded.digest_time",
+            params![user_id, muted_kinds, quiet_start, quiet_end, preferences.digest(), digest_time],
+        ).map_err(|e| e.to_string())?;
+
+        conn.execute("DELETE FROM notification_group_mutes WHERE user_id = ?1", [user_id])
+            .map_err(|e| e.to_string())?;
+        for &group_id in preferences.muted_groups() {
             conn.execute(
-                "UPDATE users
-                 SET loyal_payer = 0
-                 WHERE id = ?1",
-                [from_id],
+                "INSERT OR IGNORE INTO notification_group_mutes (user_id, group_id) VALUES (?1, ?2)",
+                params![user_id, group_id],
             ).map_err(|e| e.to_string())?;
         }
-        Ok("Дългът е напълно изплатен и приключен.".to_string())
+
+        if !preferences.digest() {
+            conn.execute("UPDATE notifications SET held = 0 WHERE user_id = ?1 AND held = 1", [user_id])
+                .map_err(|e| e.to_string())?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Sends every user in digest mode one summary of the notifications held for
+/// them, once a day after their digest time has passed. The held notifications
+/// then move to the inbox as read. Returns the user and message of every digest sent now.
+pub fn send_digests(conn: &Connection) -> Result<Vec<(i32, String)>, String> {
+    let user_ids: Vec<i32> = conn.prepare(
+        "SELECT DISTINCT n.user_id
+         FROM notifications n
+         JOIN notification_preferences p ON p.user_id = n.user_id
+         WHERE n.held = 1
+           AND (p.last_digest_on IS NULL OR p.last_digest_on < date('now', 'localtime'))
+           AND p.digest_time <= strftime('%H:%M', 'now', 'localtime')
+         ORDER BY n.user_id"
+    ).map_err(|e| e.to_string())?
+        .query_map([], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut sent = Vec::new();
+    for user_id in user_ids {
+        let messages: Vec<String> = conn.prepare(
+            "SELECT message FROM notifications WHERE user_id = ?1 AND held = 1 ORDER BY created_at, id"
+        ).map_err(|e| e.to_string())?
+            .query_map([user_id], |row| row.get(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+
+        let message = format!(
+            "Обобщение за деня ({} известия):\n{}",
+            messages.len(),
+            messages.iter().map(|message| format!("• {}", message)).collect::<Vec<_>>().join("\n")
+        );
+
+        let now_told = with_savepoint(conn, || {
+            conn.execute("UPDATE notifications SET held = 0, shown = 1 WHERE user_id = ?1 AND held = 1", [user_id])
+                .map_err(|e| e.to_string())?;
+            let today: String = conn.query_row(
+                "UPDATE notification_preferences SET last_digest_on = date('now', 'localtime') WHERE user_id = ?1
+                 RETURNING last_digest_on",
+                [user_id],
+                |row| row.get(0),
+            ).map_err(|e| e.to_string())?;
+            notify(conn, user_id, NotificationKind::Digest, &format!("digest:{}", today), &message, None, None)
+        })?;
+        if now_told {
+            sent.push((user_id, message));
+        }
     }
-    else {
-        conn.execute(
-            "UPDATE debts
-             SET confirmed_by_debtor = ?1,
-                 confirmed_by_creditor = ?2
-             WHERE id = ?3",
-            params![debtor_conf, creditor_conf, debt_id],
-        ).map_err(|e| e.to_string())?;
-        Ok("Потвърдено. Очаква се другата страна да потвърди.".to_string())
+
+    Ok(sent)
+}
+
+/// Tells every member of the group except the payer about a new expense.
+fn notify_expense_added(conn: &Connection, expense_id: i32) -> std::result::Result<(), String> {
+    let expense = get_expense(conn, expense_id)?;
+    let group_name: String = conn.query_row("SELECT name FROM groups WHERE id = ?1", [expense.group_id()], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    let message = format!(
+        "{} добави разход „{}“ от {} в група {}",
+        expense.payer_name(),
+        expense.description(),
+        format_amount(expense.amount(), expense.currency()),
+        group_name
+    );
+
+    for member in group_members(conn, expense.group_id())? {
+        if member.id() != expense.payer_id() {
+            notify(conn, member.id(), NotificationKind::ExpenseAdded, &format!("expense_added:{}", expense_id), &message, None, Some(expense.group_id()))?;
+        }
     }
+
+    Ok(())
 }
 
-pub fn get_user_notifications(conn: &Connection, user_id: i32) -> Result<Vec<Notification>, String> {
+/// Tells the other side of a debt that `user_id` has confirmed a payment.
+/// `subject` tells apart confirming the whole debt from confirming one payment.
+fn notify_payment_confirmed(conn: &Connection, user_id: i32, debt_id: i32, amount: Money, subject: &str) -> std::result::Result<(), String> {
+    let (counterparty_id, username, currency, group_id): (i32, String, String, i32) = conn.query_row(
+        "SELECT CASE WHEN d.from_id = ?2 THEN d.to_id ELSE d.from_id END, u.username, d.currency, d.group_id
+         FROM debts d
+         JOIN users u ON u.id = ?2
+         WHERE d.id = ?1",
+        params![debt_id, user_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    ).map_err(|e| e.to_string())?;
+
+    let message = format!("{} потвърди плащане от {}", username, format_amount(amount, &currency));
+    notify(
+        conn,
+        counterparty_id,
+        NotificationKind::PaymentConfirmed,
+        &format!("payment_confirmed:{}:{}", subject, user_id),
+        &message,
+        Some(debt_id),
+        Some(group_id),
+    )?;
+
+    Ok(())
+}
+
+/// Tells the payer and every participant that an occurrence of a recurring
+/// expense was recorded. Returns the user and message of every notification
+/// that should be shown right away.
+pub fn notify_recurring_expense(conn: &Connection, expense_id: i32) -> std::result::Result<Vec<(i32, String)>, String> {
+    let expense = get_expense(conn, expense_id)?;
+    let message = format!(
+        "Записан е повтарящ се разход „{}“ от {} със срок {}",
+        expense.description(),
+        format_amount(expense.amount(), expense.currency()),
+        expense.due_date()
+    );
+
+    let mut user_ids: Vec<i32> = expense.shares().iter().map(|share| share.user_id()).collect();
+    user_ids.push(expense.payer_id());
+    user_ids.sort_unstable();
+    user_ids.dedup();
+
+    let mut created = Vec::new();
+    for user_id in user_ids {
+        let event_key = format!("recurring_expense:{}", expense_id);
+        if notify(conn, user_id, NotificationKind::ExpenseAdded, &event_key, &message, None, Some(expense.group_id()))? {
+            created.push((user_id, message.clone()));
+        }
+    }
+
+    Ok(created)
+}
+
+/// Notifies every debtor once about each of their debts that is past its due date.
+/// Returns the debtor and message of every notification that was created.
+pub fn notify_overdue_debts(conn: &Connection) -> std::result::Result<Vec<(i32, String)>, String> {
     let mut stmt = conn.prepare(
-        "SELECT amount, due_date
+        "SELECT id, from_id, amount - paid, due_date, currency, group_id
          FROM debts
-         WHERE from_id = ?1
-           AND settled = 0
+         WHERE settled = 0
            AND due_date IS NOT NULL
            AND due_date != ''
            AND due_date < date('now')"
     ).map_err(|e| e.to_string())?;
 
-    let overdue: Vec<(f32, String)> = stmt.query_map([user_id], |row| {
-        Ok(
-            (row.get(0)?,
-             row.get(1)?,)
-        )
+    let overdue: Vec<(i32, i32, Money, String, String, i32)> = stmt.query_map([], |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?))
     }).map_err(|e| e.to_string())?
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
 
-    for (amount, due_date) in overdue {
-        let message = format!("Имате просрочен дълг от {:.2} лв. със срок {}", amount, due_date);
+    let mut created = Vec::new();
+    for (debt_id, user_id, amount, due_date, currency, group_id) in overdue {
+        let message = format!("Имате просрочен дълг от {} със срок {}", format_amount(amount, &currency), due_date);
+        let event_key = format!("debt_overdue:{}:{}", debt_id, due_date);
+        if notify(conn, user_id, NotificationKind::DebtOverdue, &event_key, &message, Some(debt_id), Some(group_id))? {
+            created.push((user_id, message));
+        }
+    }
+
+    Ok(created)
+}
+
+/// Lets the creditor remind the debtor of an open debt with a notification.
+/// A debt can be nudged once every `NUDGE_INTERVAL_HOURS`, and every nudge is
+/// recorded with who sent it to whom.
+pub fn nudge_debtor(conn: &Connection, user_id: i32, debt_id: i32) -> Result<String, String> {
+    let (from_id, to_id, remaining, currency, due_date, group_id, debtor, creditor): (i32, i32, Money, String, String, i32, String, String) = conn.query_row(
+        "SELECT d.from_id, d.to_id, d.amount - d.paid, d.currency, d.due_date, d.group_id, debtor.username, creditor.username
+         FROM debts d
+         JOIN users debtor ON d.from_id = debtor.id
+         JOIN users creditor ON d.to_id = creditor.id
+         WHERE d.id = ?1 AND d.settled = 0",
+        [debt_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?, row.get(6)?, row.get(7)?)),
+    ).optional().map_err(|e| e.to_string())?
+        .ok_or_else(|| "Дългът не е намерен или вече е изплатен.".to_string())?;
+
+    if user_id != to_id {
+        return Err("Само кредиторът може да напомня за този дълг.".to_string());
+    }
+
+    let next_allowed: Option<String> = conn.query_row(
+        "SELECT datetime(MAX(created_at), ?2)
+         FROM debt_nudges
+         WHERE debt_id = ?1 AND created_at > datetime('now', ?3)",
+        params![debt_id, format!("+{} hours", NUDGE_INTERVAL_HOURS), format!("-{} hours", NUDGE_INTERVAL_HOURS)],
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+    if let Some(next_allowed) = next_allowed {
+        return Err(format!("Вече напомнихте за този дълг. Следващото напомняне е възможно след {}.", next_allowed));
+    }
+
+    with_savepoint(conn, || {
+        conn.execute(
+            "INSERT INTO debt_nudges (debt_id, from_id, to_id) VALUES (?1, ?2, ?3)",
+            params![debt_id, to_id, from_id],
+        ).map_err(|e| e.to_string())?;
+        let nudge_id = conn.last_insert_rowid();
+
+        let message = format!(
+            "{} ви напомня за дълга от {} със срок {}",
+            creditor,
+            format_amount(remaining, &currency),
+            due_date
+        );
+        notify(conn, from_id, NotificationKind::Nudge, &format!("nudge:{}", nudge_id), &message, Some(debt_id), Some(group_id))?;
+
+        Ok(format!("Напомнянето е изпратено на {}.", debtor))
+    })
+}
+
+pub fn get_reminder_days(conn: &Connection, user_id: i32) -> Result<Vec<i64>, String> {
+    let days: String = conn.query_row("SELECT reminder_days FROM users WHERE id = ?1", [user_id], |row| row.get(0))
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Потребителят не е намерен.".to_string())?;
+
+    parse_reminder_days(&days)
+}
+
+/// Returns the user's choice for every channel. Notifications always reach the
+/// inbox; email goes to the account's address unless another one was given.
+pub fn get_notification_channels(conn: &Connection, user_id: i32) -> Result<Vec<ChannelPreference>, String> {
+    let email: String = conn.query_row("SELECT email FROM users WHERE id = ?1", [user_id], |row| row.get(0))
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Потребителят не е намерен.".to_string())?;
+
+    let mut preferences = Vec::new();
+    for channel in Channel::ALL {
+        let target: Option<String> = conn.query_row(
+            "SELECT target FROM notification_channels WHERE user_id = ?1 AND channel = ?2",
+            params![user_id, channel],
+            |row| row.get(0),
+        ).optional().map_err(|e| e.to_string())?;
+
+        preferences.push(match (channel, target) {
+            (Channel::InApp, _) => ChannelPreference::new(channel, true, String::new()),
+            (_, Some(target)) => ChannelPreference::new(channel, true, target),
+            (Channel::Email, None) => ChannelPreference::new(channel, false, email.clone()),
+            (_, None) => ChannelPreference::new(channel, false, String::new()),
+        });
+    }
+
+    Ok(preferences)
+}
+
+/// Turns a channel on at `target` or off. Only notifications created while a
+/// channel is on are sent on it.
+pub fn set_notification_channel(
+    conn: &Connection,
+    user_id: i32,
+    channel: Channel,
+    enabled: bool,
+    target: &str,
+) -> Result<(), String> {
+    let target = target.trim();
+
+    if channel == Channel::InApp {
+        return Err("Известията в приложението не могат да се изключат.".to_string());
+    }
+
+    if !enabled {
         conn.execute(
-            "INSERT INTO notifications (user_id, message)
-             VALUES (?1, ?2)",params![user_id, message]
+            "DELETE FROM notification_channels WHERE user_id = ?1 AND channel = ?2",
+            params![user_id, channel],
         ).map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    match channel {
+        Channel::Email => {
+            let email_regex = Regex::new(r"^[\w.-]+@[\w.-]+\.\w+$").unwrap();
+            if !email_regex.is_match(target) {
+                return Err("Невалиден имейл.".to_string());
+            }
+        }
+        Channel::Webhook => {
+            crate::delivery::parse_http_url(target)?;
+        }
+        Channel::InApp => {}
     }
 
     conn.execute(
-        "DELETE FROM notifications WHERE user_id = ?1 AND shown = 1",
-        params![user_id],
+        "INSERT INTO notification_channels (user_id, channel, target) VALUES (?1, ?2, ?3)
+         ON CONFLICT (user_id, channel) DO UPDATE SET target = excluded.target",
+        params![user_id, channel, target],
     ).map_err(|e| e.to_string())?;
 
+    Ok(())
+}
+
+/// Sends every delivery that is due on the channel that handles it. A failed
+/// delivery is tried again after `retry_delay` until `MAX_DELIVERY_ATTEMPTS`
+/// have failed. Returns how many deliveries were sent.
+pub fn deliver_notifications(conn: &Connection, channels: &[Box<dyn DeliveryChannel>]) -> Result<usize, String> {
     let mut stmt = conn.prepare(
-        "SELECT id, message FROM notifications
-         WHERE user_id = ?1 AND shown = 0
-         ORDER BY created_at ASC"
+        "SELECT d.id, n.id, n.user_id, u.username, d.channel, d.target, n.kind, n.message, n.created_at, d.attempts
+         FROM notification_deliveries d
+         JOIN notifications n ON d.notification_id = n.id
+         JOIN users u ON n.user_id = u.id
+         WHERE d.delivered_at IS NULL
+           AND d.attempts < ?1
+           AND d.next_attempt_at <= CURRENT_TIMESTAMP
+         ORDER BY d.id"
     ).map_err(|e| e.to_string())?;
 
-    let notes = stmt.query_map([user_id], |row| {
-        Ok(Notification::new
-            (row.get(0)?,
-             row.get(1)?)
-        )
+    let due = stmt.query_map([MAX_DELIVERY_ATTEMPTS], |row| {
+        Ok(Delivery::new(
+            row.get(0)?,
+            row.get(1)?,
+            row.get(2)?,
+            row.get(3)?,
+            row.get(4)?,
+            row.get(5)?,
+            row.get(6)?,
+            row.get(7)?,
+            row.get(8)?,
+            row.get(9)?,
+        ))
+    }).map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut delivered = 0;
+    for delivery in due {
+        let result = channels
+            .iter()
+            .find(|channel| channel.channel() == delivery.channel())
+            .ok_or_else(|| format!("Каналът „{}“ не е настроен.", delivery.channel().label()))
+            .and_then(|channel| channel.deliver(&delivery));
+
+        match result {
+            Ok(()) => {
+                conn.execute(
+                    "UPDATE notification_deliveries SET delivered_at = CURRENT_TIMESTAMP, last_error = NULL WHERE id = ?1",
+                    [delivery.id()],
+                ).map_err(|e| e.to_string())?;
+                delivered += 1;
+            }
+            Err(e) => {
+                let attempts = delivery.attempts() + 1;
+                conn.execute(
+                    "UPDATE notification_deliveries
+                     SET attempts = ?2, last_error = ?3, next_attempt_at = datetime('now', ?4)
+                     WHERE id = ?1",
+                    params![delivery.id(), attempts, e, format!("+{} seconds", retry_delay(attempts))],
+                ).map_err(|e| e.to_string())?;
+            }
+        }
+    }
+
+    Ok(delivered)
+}
+
+/// Sets how many days before a due date the user is reminded of their debts.
+/// No days at all turns the reminders off.
+pub fn set_reminder_days(conn: &Connection, user_id: i32, days: &[i64]) -> Result<(), String> {
+    if days.iter().any(|day| !(1..=MAX_REMINDER_DAYS).contains(day)) {
+        return Err(format!("Напомнянията може да са от 1 до {} дни преди срока.", MAX_REMINDER_DAYS));
+    }
+
+    let mut days = days.to_vec();
+    days.sort_unstable_by(|a, b| b.cmp(a));
+    days.dedup();
+
+    let days = days.iter().map(|day| day.to_string()).collect::<Vec<_>>().join(",");
+    conn.execute("UPDATE users SET reminder_days = ?1 WHERE id = ?2", params![days, user_id])
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Reminds debtors of debts that are coming due, as many days ahead as each of
+/// them has asked for. Every reminder is sent once; when the day it was meant
+/// for has been missed, it is sent late as long as the debt is not yet due.
+/// Returns the debtor and message of every notification that was created.
+pub fn notify_upcoming_debts(conn: &Connection) -> Result<Vec<(i32, String)>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT d.id, d.from_id, d.amount - d.paid, d.currency, d.due_date, u.username, debtor.reminder_days,
+                CAST(julianday(d.due_date) - julianday(date('now')) AS INTEGER), d.group_id
+         FROM debts d
+         JOIN users u ON d.to_id = u.id
+         JOIN users debtor ON d.from_id = debtor.id
+         WHERE d.settled = 0
+           AND d.due_date > date('now')
+           AND d.due_date <= date('now', ?1)
+         ORDER BY d.due_date, d.id"
+    ).map_err(|e| e.to_string())?;
+
+    let upcoming: Vec<(i32, i32, i32, String, String, i64, String)> = stmt.query_map([format!("+{} days", MAX_REMINDER_DAYS)], |row| {
+        let amount = format_amount(row.get(2)?, &row.get::<_, String>(3)?);
+        let (due_date, creditor, days_left): (String, String, i64) = (row.get(4)?, row.get(5)?, row.get(7)?);
+        let message = if days_left == 1 {
+            format!("Утре е срокът на дълга ви от {} към {}", amount, creditor)
+        }
+        else {
+            format!("След {} дни ({}) е срокът на дълга ви от {} към {}", days_left, due_date, amount, creditor)
+        };
+        Ok((row.get(0)?, row.get(1)?, row.get(8)?, due_date, row.get(6)?, days_left, message))
     }).map_err(|e| e.to_string())?
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
 
-    for note in &notes {
-        conn.execute("UPDATE notifications SET shown = 1 WHERE id = ?1", [note.id()]).map_err(|e| e.to_string())?;
+    let mut created = Vec::new();
+    for (debt_id, user_id, group_id, due_date, reminder_days, days_left, message) in upcoming {
+        let Some(reminder) = parse_reminder_days(&reminder_days)?.into_iter().filter(|&day| day >= days_left).min() else {
+            continue;
+        };
+
+        let event_key = format!("due_reminder:{}:{}:{}", debt_id, due_date, reminder);
+        if notify(conn, user_id, NotificationKind::DueReminder, &event_key, &message, Some(debt_id), Some(group_id))? {
+            created.push((user_id, message));
+        }
     }
 
+    Ok(created)
+}
+
+/// Returns the user's inbox, newest first. Archived notifications are left out.
+pub fn get_user_notifications(conn: &Connection, user_id: i32) -> Result<Vec<Notification>, String> {
+    let notes = query_notifications(conn, user_id, false)?;
     if notes.is_empty() {
         return Err("Нямате известия!".to_string());
     }
@@ -566,3 +3620,73 @@ pub fn get_user_notifications(conn: &Connection, user_id: i32) -> Result<Vec<Not
     Ok(notes)
 }
 
+/// Returns the notifications the user has archived, newest first.
+pub fn get_archived_notifications(conn: &Connection, user_id: i32) -> Result<Vec<Notification>, String> {
+    let notes = query_notifications(conn, user_id, true)?;
+    if notes.is_empty() {
+        return Err("Нямате архивирани известия.".to_string());
+    }
+
+    Ok(notes)
+}
+
+fn query_notifications(conn: &Connection, user_id: i32, archived: bool) -> Result<Vec<Notification>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT id, message, kind, created_at, shown, archived, debt_id, group_id
+         FROM notifications
+         WHERE user_id = ?1 AND archived = ?2 AND held = 0
+         ORDER BY created_at DESC, id DESC"
+    ).map_err(|e| e.to_string())?;
+
+    let notes = stmt.query_map(params![user_id, archived], |row| {
+        Ok(Notification::new(
+            row.get(0)?,
+            row.get(1)?,
+            row.get(2)?,
+            row.get(3)?,
+            row.get(4)?,
+        ).with_archived(row.get(5)?).with_links(row.get(6)?, row.get(7)?))
+    }).map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(notes)
+}
+
+pub fn count_unread_notifications(conn: &Connection, user_id: i32) -> Result<i64, String> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM notifications WHERE user_id = ?1 AND shown = 0 AND archived = 0 AND held = 0",
+        [user_id],
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())
+}
+
+fn update_notification(conn: &Connection, user_id: i32, notification_id: i32, assignment: &str, value: bool) -> Result<(), String> {
+    let updated = conn.execute(
+        &format!("UPDATE notifications SET {} WHERE id = ?2 AND user_id = ?3", assignment),
+        params![value, notification_id, user_id],
+    ).map_err(|e| e.to_string())?;
+
+    if updated == 0 {
+        return Err("Известието не е намерено.".to_string());
+    }
+    Ok(())
+}
+
+pub fn set_notification_read(conn: &Connection, user_id: i32, notification_id: i32, read: bool) -> Result<(), String> {
+    update_notification(conn, user_id, notification_id, "shown = ?1", read)
+}
+
+/// Moves a notification out of the inbox, or back into it. Archiving also marks it as read.
+pub fn set_notification_archived(conn: &Connection, user_id: i32, notification_id: i32, archived: bool) -> Result<(), String> {
+    update_notification(conn, user_id, notification_id, "archived = ?1, shown = shown OR ?1", archived)
+}
+
+/// Marks everything in the inbox as read and returns how many notifications changed.
+pub fn mark_all_notifications_read(conn: &Connection, user_id: i32) -> Result<usize, String> {
+    conn.execute(
+        "UPDATE notifications SET shown = 1 WHERE user_id = ?1 AND shown = 0 AND archived = 0 AND held = 0",
+        [user_id],
+    ).map_err(|e| e.to_string())
+}
+
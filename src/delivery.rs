@@ -0,0 +1,379 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use crate::notification::NotificationKind;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+
+/// How many times a delivery is tried before it is given up.
+pub const MAX_DELIVERY_ATTEMPTS: i64 = 5;
+
+/// The wait before the first retry. It doubles after every failed attempt.
+pub const RETRY_BASE_SECS: i64 = 60;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many seconds to wait after the given number of failed attempts.
+pub fn retry_delay(attempts: i64) -> i64 {
+    RETRY_BASE_SECS << (attempts - 1).clamp(0, MAX_DELIVERY_ATTEMPTS)
+}
+
+/// Where a notification is delivered besides the inbox row it is stored in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Channel {
+    InApp,
+    Email,
+    Webhook,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 3] = [Channel::InApp, Channel::Email, Channel::Webhook];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Channel::InApp => "in_app",
+            Channel::Email => "email",
+            Channel::Webhook => "webhook",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Channel::InApp => "В приложението",
+            Channel::Email => "Имейл",
+            Channel::Webhook => "Webhook",
+        }
+    }
+}
+
+impl ToSql for Channel {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for Channel {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let text = value.as_str()?;
+        Channel::ALL
+            .into_iter()
+            .find(|channel| channel.as_str() == text)
+            .ok_or_else(|| FromSqlError::Other(format!("unknown delivery channel: {}", text).into()))
+    }
+}
+
+/// Whether a user receives notifications on a channel, and at which address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelPreference {
+    channel: Channel,
+    enabled: bool,
+    target: String,
+}
+
+impl ChannelPreference {
+    pub fn new(channel: Channel, enabled: bool, target: String) -> Self {
+        Self { channel, enabled, target }
+    }
+
+    pub fn channel(&self) -> Channel {
+        self.channel
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// The email address or webhook URL. Empty for in-app notifications.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+}
+
+/// One notification waiting to be sent on one channel.
+#[derive(Debug, Clone)]
+pub struct Delivery {
+    id: i32,
+    notification_id: i32,
+    user_id: i32,
+    username: String,
+    channel: Channel,
+    target: String,
+    kind: NotificationKind,
+    message: String,
+    created_at: String,
+    attempts: i64,
+}
+
+impl Delivery {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        id: i32,
+        notification_id: i32,
+        user_id: i32,
+        username: String,
+        channel: Channel,
+        target: String,
+        kind: NotificationKind,
+        message: String,
+        created_at: String,
+        attempts: i64,
+    ) -> Self {
+        Self { id, notification_id, user_id, username, channel, target, kind, message, created_at, attempts }
+    }
+
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn notification_id(&self) -> i32 {
+        self.notification_id
+    }
+
+    pub fn user_id(&self) -> i32 {
+        self.user_id
+    }
+
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    pub fn channel(&self) -> Channel {
+        self.channel
+    }
+
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn kind(&self) -> NotificationKind {
+        self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn created_at(&self) -> &str {
+        &self.created_at
+    }
+
+    /// How many times sending has failed so far.
+    pub fn attempts(&self) -> i64 {
+        self.attempts
+    }
+}
+
+/// Sends notifications somewhere outside the inbox.
+pub trait DeliveryChannel {
+    fn channel(&self) -> Channel;
+
+    fn deliver(&self, delivery: &Delivery) -> Result<(), String>;
+}
+
+/// Every notification is stored in the inbox when it is created, so there is
+/// nothing left to send.
+pub struct InAppChannel;
+
+impl DeliveryChannel for InAppChannel {
+    fn channel(&self) -> Channel {
+        Channel::InApp
+    }
+
+    fn deliver(&self, _delivery: &Delivery) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Sends notifications as plain text emails through an SMTP server that
+/// accepts mail without authentication, such as a local relay.
+pub struct EmailChannel {
+    host: String,
+    port: u16,
+    from: String,
+}
+
+impl EmailChannel {
+    pub fn new(host: String, port: u16, from: String) -> Self {
+        Self { host, port, from }
+    }
+
+    /// Reads `SMTP_HOST`, `SMTP_PORT` (25 by default) and `SMTP_FROM`.
+    /// Returns `None` when no SMTP host is configured.
+    pub fn from_env() -> Option<Self> {
+        let host = std::env::var("SMTP_HOST").ok().filter(|host| !host.trim().is_empty())?;
+        let port = std::env::var("SMTP_PORT").ok().and_then(|port| port.parse().ok()).unwrap_or(25);
+        let from = std::env::var("SMTP_FROM").unwrap_or_else(|_| "split-money-manager@localhost".to_string());
+        Some(Self::new(host, port, from))
+    }
+}
+
+impl DeliveryChannel for EmailChannel {
+    fn channel(&self) -> Channel {
+        Channel::Email
+    }
+
+    fn deliver(&self, delivery: &Delivery) -> Result<(), String> {
+        let stream = connect(&self.host, self.port)?;
+        let mut reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+        let mut writer = stream;
+
+        let mut command = |line: Option<String>, expected: &[u16]| -> Result<(), String> {
+            if let Some(line) = line {
+                writer.write_all(format!("{}\r\n", line).as_bytes()).map_err(|e| e.to_string())?;
+            }
+            let code = read_smtp_reply(&mut reader)?;
+            if !expected.contains(&code) {
+                return Err(format!("SMTP сървърът отговори с {}.", code));
+            }
+            Ok(())
+        };
+
+        command(None, &[220])?;
+        command(Some("HELO localhost".to_string()), &[250])?;
+        command(Some(format!("MAIL FROM:<{}>", self.from)), &[250])?;
+        command(Some(format!("RCPT TO:<{}>", delivery.target())), &[250, 251])?;
+        command(Some("DATA".to_string()), &[354])?;
+
+        let body = delivery.message()
+            .lines()
+            .map(|line| if line.starts_with('.') { format!(".{}", line) } else { line.to_string() })
+            .collect::<Vec<_>>()
+            .join("\r\n");
+        let mail = format!(
+            "From: <{}>\r\nTo: <{}>\r\nSubject: Split Money Manager\r\nMIME-Version: 1.0\r\n\
+             Content-Type: text/plain; charset=UTF-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n{}\r\n.",
+            self.from,
+            delivery.target(),
+            body
+        );
+        command(Some(mail), &[250])?;
+        command(Some("QUIT".to_string()), &[221])
+    }
+}
+
+/// Posts every notification as JSON to the URL the user gave. Only plain
+/// `http://` URLs are supported.
+pub struct WebhookChannel;
+
+impl DeliveryChannel for WebhookChannel {
+    fn channel(&self) -> Channel {
+        Channel::Webhook
+    }
+
+    fn deliver(&self, delivery: &Delivery) -> Result<(), String> {
+        let (host, port, path) = parse_http_url(delivery.target())?;
+        let body = format!(
+            "{{\"id\":{},\"user_id\":{},\"username\":{},\"kind\":{},\"message\":{},\"created_at\":{}}}",
+            delivery.notification_id(),
+            delivery.user_id(),
+            json_string(delivery.username()),
+            json_string(delivery.kind().as_str()),
+            json_string(delivery.message()),
+            json_string(delivery.created_at())
+        );
+        let request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            path,
+            host,
+            port,
+            body.len(),
+            body
+        );
+
+        let mut stream = connect(&host, port)?;
+        stream.write_all(request.as_bytes()).map_err(|e| e.to_string())?;
+
+        let mut status_line = String::new();
+        BufReader::new(&mut stream).read_line(&mut status_line).map_err(|e| e.to_string())?;
+        let status: u16 = status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(|| "Невалиден отговор от webhook адреса.".to_string())?;
+
+        if !(200..300).contains(&status) {
+            return Err(format!("Webhook адресът отговори с {}.", status));
+        }
+        Ok(())
+    }
+}
+
+/// The channels the backend delivers on: email only when an SMTP host is configured.
+pub fn default_channels() -> Vec<Box<dyn DeliveryChannel>> {
+    let mut channels: Vec<Box<dyn DeliveryChannel>> = vec![Box::new(InAppChannel), Box::new(WebhookChannel)];
+    if let Some(email) = EmailChannel::from_env() {
+        channels.push(Box::new(email));
+    }
+    channels
+}
+
+/// Splits `http://host[:port][/path]` into its parts.
+pub fn parse_http_url(url: &str) -> Result<(String, u16, String), String> {
+    let invalid = || format!("Невалиден webhook адрес: {}. Адресът трябва да започва с http://.", url);
+
+    let rest = url.trim().strip_prefix("http://").ok_or_else(invalid)?;
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().map_err(|_| invalid())?),
+        None => (authority, 80),
+    };
+
+    if host.is_empty() {
+        return Err(invalid());
+    }
+    Ok((host.to_string(), port, path.to_string()))
+}
+
+/// Tries every address the host resolves to, giving each `CONNECT_TIMEOUT`.
+fn connect(host: &str, port: u16) -> Result<TcpStream, String> {
+    let unreachable = |e: std::io::Error| format!("Няма връзка с {}:{}: {}", host, port, e);
+    let mut last_error = None;
+
+    for address in (host, port).to_socket_addrs().map_err(unreachable)? {
+        match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+            Ok(stream) => {
+                stream.set_read_timeout(Some(CONNECT_TIMEOUT)).map_err(|e| e.to_string())?;
+                stream.set_write_timeout(Some(CONNECT_TIMEOUT)).map_err(|e| e.to_string())?;
+                return Ok(stream);
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    Err(unreachable(last_error.unwrap_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "адресът не е намерен"))))
+}
+
+/// Reads a possibly multi-line SMTP reply and returns its code.
+fn read_smtp_reply(reader: &mut impl BufRead) -> Result<u16, String> {
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+            return Err("SMTP сървърът затвори връзката.".to_string());
+        }
+
+        let code = line.get(..3).and_then(|code| code.parse().ok())
+            .ok_or_else(|| format!("Невалиден отговор от SMTP сървъра: {}", line.trim()))?;
+        if line.as_bytes().get(3) != Some(&b'-') {
+            return Ok(code);
+        }
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut json = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
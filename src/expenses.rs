@@ -1,16 +1,35 @@
+use crate::money::Money;
+use crate::payment::Payment;
+use crate::currency::DEFAULT_CURRENCY;
+use crate::split::{Split, SplitMode};
+
 #[derive(Debug, Clone)]
 pub struct Expenses {
     id: i32,
     username: String,
-    amount: f32,
+    amount: Money,
     group_name: String,
     due_date: String,
     description: String,
+    paid: Money,
+    payments: Vec<Payment>,
+    currency: String,
 }
 
 impl Expenses {
-    pub fn new(id: i32, username: String, amount: f32, group_name: String, due_date: String, description: String) -> Self {
-        Self {id, username, amount, group_name, due_date, description}
+    pub fn new(id: i32, username: String, amount: Money, group_name: String, due_date: String, description: String) -> Self {
+        Self {id, username, amount, group_name, due_date, description, paid: Money::ZERO, payments: Vec::new(), currency: DEFAULT_CURRENCY.to_string()}
+    }
+
+    pub fn with_payments(mut self, paid: Money, payments: Vec<Payment>) -> Self {
+        self.paid = paid;
+        self.payments = payments;
+        self
+    }
+
+    pub fn with_currency(mut self, currency: String) -> Self {
+        self.currency = currency;
+        self
     }
 
     pub fn id(&self) -> i32 {
@@ -21,7 +40,7 @@ impl Expenses {
         &self.username
     }
 
-    pub fn amount(&self) -> f32 {
+    pub fn amount(&self) -> Money {
         self.amount
     }
 
@@ -36,4 +55,172 @@ impl Expenses {
     pub fn description(&self) -> &str {
         &self.description
     }
-}
\ No newline at end of file
+
+    /// The part of the debt covered by payments both sides have confirmed.
+    pub fn paid(&self) -> Money {
+        self.paid
+    }
+
+    pub fn remaining(&self) -> Money {
+        self.amount - self.paid
+    }
+
+    pub fn payments(&self) -> &[Payment] {
+        &self.payments
+    }
+
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+}
+
+/// A participant's part of an expense.
+#[derive(Debug, Clone)]
+pub struct ExpenseShare {
+    user_id: i32,
+    username: String,
+    split_value: i64,
+    amount: Money,
+}
+
+impl ExpenseShare {
+    pub fn new(user_id: i32, username: String, split_value: i64, amount: Money) -> Self {
+        Self { user_id, username, split_value, amount }
+    }
+
+    pub fn user_id(&self) -> i32 {
+        self.user_id
+    }
+
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    /// The value entered for the split mode, see `Split`.
+    pub fn split_value(&self) -> i64 {
+        self.split_value
+    }
+
+    pub fn amount(&self) -> Money {
+        self.amount
+    }
+}
+
+/// An expense as it was entered, from which the debts between members are derived.
+#[derive(Debug, Clone)]
+pub struct Expense {
+    id: i32,
+    group_id: i32,
+    payer_id: i32,
+    payer_name: String,
+    amount: Money,
+    description: String,
+    due_date: String,
+    split_mode: SplitMode,
+    created_at: String,
+    shares: Vec<ExpenseShare>,
+    currency: String,
+    group_currency: String,
+    group_amount: Money,
+}
+
+impl Expense {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        id: i32,
+        group_id: i32,
+        payer_id: i32,
+        payer_name: String,
+        amount: Money,
+        description: String,
+        due_date: String,
+        split_mode: SplitMode,
+        created_at: String,
+        shares: Vec<ExpenseShare>,
+    ) -> Self {
+        Self {
+            id,
+            group_id,
+            payer_id,
+            payer_name,
+            amount,
+            description,
+            due_date,
+            split_mode,
+            created_at,
+            shares,
+            currency: DEFAULT_CURRENCY.to_string(),
+            group_currency: DEFAULT_CURRENCY.to_string(),
+            group_amount: amount,
+        }
+    }
+
+    /// Sets the currency the expense was paid in and its total in the group currency.
+    pub fn with_currency(self, currency: String, group_currency: String, group_amount: Money) -> Self {
+        Self { currency, group_currency, group_amount, ..self }
+    }
+
+    pub fn with_shares(self, shares: Vec<ExpenseShare>) -> Self {
+        Self { shares, ..self }
+    }
+
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn group_id(&self) -> i32 {
+        self.group_id
+    }
+
+    pub fn payer_id(&self) -> i32 {
+        self.payer_id
+    }
+
+    pub fn payer_name(&self) -> &str {
+        &self.payer_name
+    }
+
+    pub fn amount(&self) -> Money {
+        self.amount
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn due_date(&self) -> &str {
+        &self.due_date
+    }
+
+    pub fn split_mode(&self) -> SplitMode {
+        self.split_mode
+    }
+
+    pub fn created_at(&self) -> &str {
+        &self.created_at
+    }
+
+    pub fn shares(&self) -> &[ExpenseShare] {
+        &self.shares
+    }
+
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+
+    /// The currency the expense was charged in, which is the group currency at
+    /// the time. It differs from the group's own once the group changes currency.
+    pub fn group_currency(&self) -> &str {
+        &self.group_currency
+    }
+
+    /// The total converted into the group currency when the expense was recorded.
+    pub fn group_amount(&self) -> Money {
+        self.group_amount
+    }
+
+    /// Rebuilds the split the expense was entered with.
+    pub fn split(&self) -> Split {
+        Split::new(self.split_mode, self.shares.iter().map(|s| (s.user_id, s.split_value)).collect())
+    }
+}
@@ -1,12 +1,100 @@
+use crate::currency::DEFAULT_CURRENCY;
+use crate::expenses::Expense;
+use crate::money::Money;
+use crate::payment::Payment;
+use crate::settlement::{Balance, Transfer};
+use crate::user::User;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+
+/// What a member may do in a group. Every group has exactly one owner.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GroupRole {
+    Owner,
+    Admin,
+    #[default]
+    Member,
+}
+
+impl GroupRole {
+    pub const ALL: [GroupRole; 3] = [GroupRole::Owner, GroupRole::Admin, GroupRole::Member];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GroupRole::Owner => "owner",
+            GroupRole::Admin => "admin",
+            GroupRole::Member => "member",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GroupRole::Owner => "Собственик",
+            GroupRole::Admin => "Администратор",
+            GroupRole::Member => "Член",
+        }
+    }
+
+    /// Adding and removing members. Only the owner may remove an admin.
+    pub fn can_manage_members(&self) -> bool {
+        matches!(self, GroupRole::Owner | GroupRole::Admin)
+    }
+
+    /// Editing and deleting expenses someone else paid. Anyone may change their own.
+    pub fn can_edit_any_expense(&self) -> bool {
+        matches!(self, GroupRole::Owner | GroupRole::Admin)
+    }
+
+    /// Renaming the group and changing its currency.
+    pub fn can_manage_group(&self) -> bool {
+        matches!(self, GroupRole::Owner | GroupRole::Admin)
+    }
+
+    /// Archiving or deleting the group, changing roles and handing over ownership.
+    pub fn is_owner(&self) -> bool {
+        *self == GroupRole::Owner
+    }
+}
+
+impl ToSql for GroupRole {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for GroupRole {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let text = value.as_str()?;
+        GroupRole::ALL
+            .into_iter()
+            .find(|role| role.as_str() == text)
+            .ok_or_else(|| FromSqlError::Other(format!("unknown group role: {}", text).into()))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Group {
     id: i32,
     groupname: String,
+    currency: String,
+    role: GroupRole,
+    archived: bool,
 }
 
 impl Group {
     pub fn new(id: i32, groupname: String) -> Group {
-        Self { id, groupname }
+        Self { id, groupname, currency: DEFAULT_CURRENCY.to_string(), role: GroupRole::default(), archived: false }
+    }
+
+    pub fn with_currency(self, currency: String) -> Group {
+        Self { currency, ..self }
+    }
+
+    pub fn with_role(self, role: GroupRole) -> Group {
+        Self { role, ..self }
+    }
+
+    pub fn with_archived(self, archived: bool) -> Group {
+        Self { archived, ..self }
     }
 
     pub fn groupname(&self) -> &str {
@@ -16,4 +104,92 @@ impl Group {
     pub fn id(&self) -> i32 {
         self.id
     }
-}
\ No newline at end of file
+
+    /// The currency the group's debts are kept and settled in.
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+
+    /// The role of the user the group was loaded for.
+    pub fn role(&self) -> GroupRole {
+        self.role
+    }
+
+    /// Archived groups are settled and hidden from the group list, but can still be opened.
+    pub fn is_archived(&self) -> bool {
+        self.archived
+    }
+}
+
+/// One entry in a group's history.
+#[derive(Debug, Clone)]
+pub enum GroupActivity {
+    Expense(Expense),
+    Payment(Payment),
+}
+
+impl GroupActivity {
+    /// When the expense or payment was recorded.
+    pub fn created_at(&self) -> &str {
+        match self {
+            GroupActivity::Expense(expense) => expense.created_at(),
+            GroupActivity::Payment(payment) => payment.created_at(),
+        }
+    }
+}
+
+/// Everything the group screen shows about one group.
+#[derive(Debug, Clone)]
+pub struct GroupDetail {
+    group: Group,
+    members: Vec<User>,
+    balances: Vec<Balance>,
+    history: Vec<GroupActivity>,
+    settlement: Vec<Transfer>,
+}
+
+impl GroupDetail {
+    pub fn new(group: Group, members: Vec<User>) -> Self {
+        Self { group, members, balances: Vec::new(), history: Vec::new(), settlement: Vec::new() }
+    }
+
+    pub fn with_balances(self, balances: Vec<Balance>, settlement: Vec<Transfer>) -> Self {
+        Self { balances, settlement, ..self }
+    }
+
+    pub fn with_history(self, history: Vec<GroupActivity>) -> Self {
+        Self { history, ..self }
+    }
+
+    pub fn group(&self) -> &Group {
+        &self.group
+    }
+
+    pub fn members(&self) -> &[User] {
+        &self.members
+    }
+
+    /// Net balance of every member over the group's open debts.
+    pub fn balances(&self) -> &[Balance] {
+        &self.balances
+    }
+
+    /// The net balance of one member, zero if they have none.
+    pub fn balance_of(&self, user_id: i32) -> Money {
+        self.balances
+            .iter()
+            .find(|balance| balance.user_id() == user_id)
+            .map(|balance| balance.amount())
+            .unwrap_or_default()
+    }
+
+    /// Expenses and payments, oldest first.
+    pub fn history(&self) -> &[GroupActivity] {
+        &self.history
+    }
+
+    /// The fewest transfers that would settle the group.
+    pub fn settlement(&self) -> &[Transfer] {
+        &self.settlement
+    }
+}
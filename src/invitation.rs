@@ -0,0 +1,171 @@
+use rand_core::{OsRng, RngCore};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+
+/// How many characters an invite code has.
+pub const INVITE_CODE_LENGTH: usize = 8;
+
+/// How long an invite code can be used after it was created.
+pub const INVITE_CODE_VALID_DAYS: i64 = 7;
+
+/// Letters and digits that cannot be mistaken for one another when typed in.
+const INVITE_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+/// A fresh random invite code such as `K7PX2MQA`.
+pub fn generate_invite_code() -> String {
+    (0..INVITE_CODE_LENGTH)
+        .map(|_| INVITE_CODE_ALPHABET[OsRng.next_u32() as usize % INVITE_CODE_ALPHABET.len()] as char)
+        .collect()
+}
+
+/// Reads a code the way it was typed in: spaces and dashes are dropped and
+/// lower case letters are accepted.
+pub fn parse_invite_code(input: &str) -> Result<String, String> {
+    let code: String = input
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+
+    if code.len() != INVITE_CODE_LENGTH || !code.bytes().all(|c| INVITE_CODE_ALPHABET.contains(&c)) {
+        return Err(format!("Невалиден код за покана: {}.", input.trim()));
+    }
+    Ok(code)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InvitationStatus {
+    #[default]
+    Pending,
+    Accepted,
+    Declined,
+}
+
+impl InvitationStatus {
+    pub const ALL: [InvitationStatus; 3] = [InvitationStatus::Pending, InvitationStatus::Accepted, InvitationStatus::Declined];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InvitationStatus::Pending => "pending",
+            InvitationStatus::Accepted => "accepted",
+            InvitationStatus::Declined => "declined",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            InvitationStatus::Pending => "Чакаща",
+            InvitationStatus::Accepted => "Приета",
+            InvitationStatus::Declined => "Отказана",
+        }
+    }
+}
+
+impl ToSql for InvitationStatus {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for InvitationStatus {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let text = value.as_str()?;
+        InvitationStatus::ALL
+            .into_iter()
+            .find(|status| status.as_str() == text)
+            .ok_or_else(|| FromSqlError::Other(format!("unknown invitation status: {}", text).into()))
+    }
+}
+
+/// An invitation for one user to join a group.
+#[derive(Debug, Clone)]
+pub struct Invitation {
+    id: i32,
+    group_id: i32,
+    group_name: String,
+    inviter: String,
+    created_at: String,
+    status: InvitationStatus,
+}
+
+impl Invitation {
+    pub fn new(id: i32, group_id: i32, group_name: String, inviter: String, created_at: String) -> Self {
+        Self { id, group_id, group_name, inviter, created_at, status: InvitationStatus::Pending }
+    }
+
+    pub fn with_status(mut self, status: InvitationStatus) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn group_id(&self) -> i32 {
+        self.group_id
+    }
+
+    pub fn group_name(&self) -> &str {
+        &self.group_name
+    }
+
+    /// The username of whoever sent the invitation.
+    pub fn inviter(&self) -> &str {
+        &self.inviter
+    }
+
+    pub fn created_at(&self) -> &str {
+        &self.created_at
+    }
+
+    pub fn status(&self) -> InvitationStatus {
+        self.status
+    }
+}
+
+/// A request from one member for another to take over their open debts in a group.
+#[derive(Debug, Clone)]
+pub struct DebtTransfer {
+    id: i32,
+    group_id: i32,
+    group_name: String,
+    requester: String,
+    created_at: String,
+    status: InvitationStatus,
+}
+
+impl DebtTransfer {
+    pub fn new(id: i32, group_id: i32, group_name: String, requester: String, created_at: String) -> Self {
+        Self { id, group_id, group_name, requester, created_at, status: InvitationStatus::Pending }
+    }
+
+    pub fn with_status(mut self, status: InvitationStatus) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn group_id(&self) -> i32 {
+        self.group_id
+    }
+
+    pub fn group_name(&self) -> &str {
+        &self.group_name
+    }
+
+    /// The username of the member whose debts would be taken over.
+    pub fn requester(&self) -> &str {
+        &self.requester
+    }
+
+    pub fn created_at(&self) -> &str {
+        &self.created_at
+    }
+
+    pub fn status(&self) -> InvitationStatus {
+        self.status
+    }
+}
@@ -4,4 +4,13 @@ pub mod app;
 pub mod user;
 pub mod group;
 pub mod expenses;
-pub mod notification;
\ No newline at end of file
+pub mod notification;
+pub mod money;
+pub mod split;
+pub mod settlement;
+pub mod payment;
+pub mod currency;
+pub mod recurring;
+pub mod scheduler;
+pub mod delivery;
+pub mod invitation;
@@ -1,14 +1,6 @@
 use eframe::NativeOptions;
 
-mod db;
-mod backend;
-mod app;
-mod user;
-mod group;
-mod expenses;
-mod notification;
-
-use app::MyApp;
+use split_money_manager::app::MyApp;
 
 
 fn main() -> eframe::Result<()> {
@@ -0,0 +1,138 @@
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+use rusqlite::types::{FromSql, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+
+/// Number of minor units (stotinki, cents) in one major unit.
+pub const MINOR_PER_MAJOR: i64 = 100;
+
+/// An exact amount of money stored as an integer number of minor units.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(i64);
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+
+    pub fn from_minor(minor: i64) -> Self {
+        Self(minor)
+    }
+
+    pub fn from_major(major: i64) -> Self {
+        Self(major * MINOR_PER_MAJOR)
+    }
+
+    pub fn minor(&self) -> i64 {
+        self.0
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn is_positive(&self) -> bool {
+        self.0 > 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.0 < 0
+    }
+
+    pub fn abs(&self) -> Self {
+        Self(self.0.abs())
+    }
+
+    /// Parses user input such as `12`, `12.5`, `12.50` or `12,50`.
+    pub fn parse(input: &str) -> Result<Money, String> {
+        let input = input.trim();
+        let (negative, digits) = match input.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, input),
+        };
+
+        let (major, minor) = match digits.split_once(['.', ',']) {
+            Some((major, minor)) => (major, minor),
+            None => (digits, ""),
+        };
+
+        let all_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+        if major.is_empty() || !all_digits(major) || !all_digits(minor) {
+            return Err("Невалидна сума.".to_string());
+        }
+        if minor.len() > 2 {
+            return Err("Сумата може да има най-много два знака след десетичната запетая.".to_string());
+        }
+
+        let major: i64 = major.parse().map_err(|_| "Невалидна сума.".to_string())?;
+        let minor: i64 = format!("{:0<2}", minor).parse().map_err(|_| "Невалидна сума.".to_string())?;
+
+        let total = major
+            .checked_mul(MINOR_PER_MAJOR)
+            .and_then(|m| m.checked_add(minor))
+            .ok_or_else(|| "Сумата е твърде голяма.".to_string())?;
+
+        Ok(Money(if negative { -total } else { total }))
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        let per = MINOR_PER_MAJOR as u64;
+        write!(f, "{}{}.{:02}", sign, abs / per, abs % per)
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, other: Money) -> Money {
+        Money(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, other: Money) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, other: Money) -> Money {
+        Money(self.0 - other.0)
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, other: Money) {
+        self.0 -= other.0;
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money(-self.0)
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::ZERO, |acc, m| acc + m)
+    }
+}
+
+impl ToSql for Money {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.0))
+    }
+}
+
+impl FromSql for Money {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        i64::column_result(value).map(Money)
+    }
+}
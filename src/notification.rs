@@ -1,12 +1,237 @@
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+
+/// The event a notification was created for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationKind {
+    ExpenseAdded,
+    DebtOverdue,
+    DueReminder,
+    Nudge,
+    PaymentConfirmed,
+    AddedToGroup,
+    GroupInvitation,
+    DebtTransfer,
+    Digest,
+}
+
+impl NotificationKind {
+    pub const ALL: [NotificationKind; 9] = [
+        NotificationKind::ExpenseAdded,
+        NotificationKind::DebtOverdue,
+        NotificationKind::DueReminder,
+        NotificationKind::Nudge,
+        NotificationKind::PaymentConfirmed,
+        NotificationKind::AddedToGroup,
+        NotificationKind::GroupInvitation,
+        NotificationKind::DebtTransfer,
+        NotificationKind::Digest,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationKind::ExpenseAdded => "expense_added",
+            NotificationKind::DebtOverdue => "debt_overdue",
+            NotificationKind::DueReminder => "due_reminder",
+            NotificationKind::Nudge => "nudge",
+            NotificationKind::PaymentConfirmed => "payment_confirmed",
+            NotificationKind::AddedToGroup => "added_to_group",
+            NotificationKind::GroupInvitation => "group_invitation",
+            NotificationKind::DebtTransfer => "debt_transfer",
+            NotificationKind::Digest => "digest",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            NotificationKind::ExpenseAdded => "Нов разход",
+            NotificationKind::DebtOverdue => "Просрочен дълг",
+            NotificationKind::DueReminder => "Наближаващ срок",
+            NotificationKind::Nudge => "Напомняне от кредитор",
+            NotificationKind::PaymentConfirmed => "Потвърдено плащане",
+            NotificationKind::AddedToGroup => "Нова група",
+            NotificationKind::GroupInvitation => "Покана за група",
+            NotificationKind::DebtTransfer => "Прехвърляне на дългове",
+            NotificationKind::Digest => "Дневно обобщение",
+        }
+    }
+}
+
+impl ToSql for NotificationKind {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for NotificationKind {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let text = value.as_str()?;
+        NotificationKind::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == text)
+            .ok_or_else(|| FromSqlError::Other(format!("unknown notification kind: {}", text).into()))
+    }
+}
+
+/// How long a creditor has to wait before reminding a debtor of the same debt again.
+pub const NUDGE_INTERVAL_HOURS: i64 = 24;
+
+/// The furthest ahead of a due date a reminder can be set.
+pub const MAX_REMINDER_DAYS: i64 = 30;
+
+/// Reads how many days before a due date to remind, such as `3, 1`.
+/// Returns the days without repeats, furthest first. An empty input turns reminders off.
+pub fn parse_reminder_days(input: &str) -> Result<Vec<i64>, String> {
+    let mut days = Vec::new();
+
+    for part in input.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+        let day: i64 = part.parse().map_err(|_| format!("Невалиден брой дни: {}.", part))?;
+        if !(1..=MAX_REMINDER_DAYS).contains(&day) {
+            return Err(format!("Напомнянията може да са от 1 до {} дни преди срока.", MAX_REMINDER_DAYS));
+        }
+        days.push(day);
+    }
+
+    days.sort_unstable_by(|a, b| b.cmp(a));
+    days.dedup();
+    Ok(days)
+}
+
+/// Writes reminder days the way `parse_reminder_days` reads them.
+pub fn format_reminder_days(days: &[i64]) -> String {
+    days.iter().map(|day| day.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+/// Checks that `input` is a time of day such as `22:00` and returns it as `HH:MM`.
+pub fn parse_time_of_day(input: &str) -> Result<String, String> {
+    let invalid = || format!("Невалиден час: {}. Използвайте формат ЧЧ:ММ.", input.trim());
+
+    let (hours, minutes) = input.trim().split_once(':').ok_or_else(invalid)?;
+    let hours: u32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: u32 = minutes.parse().map_err(|_| invalid())?;
+    if hours > 23 || minutes > 59 {
+        return Err(invalid());
+    }
+
+    Ok(format!("{:02}:{:02}", hours, minutes))
+}
+
+/// When the daily digest is sent unless the user picks another time.
+pub const DEFAULT_DIGEST_TIME: &str = "08:00";
+
+/// What a user wants to be told about and when.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotificationPreferences {
+    muted_kinds: Vec<NotificationKind>,
+    muted_groups: Vec<i32>,
+    quiet_hours: Option<(String, String)>,
+    digest: bool,
+    digest_time: String,
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        Self {
+            muted_kinds: Vec::new(),
+            muted_groups: Vec::new(),
+            quiet_hours: None,
+            digest: false,
+            digest_time: DEFAULT_DIGEST_TIME.to_string(),
+        }
+    }
+}
+
+impl NotificationPreferences {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_muted_kinds(self, muted_kinds: Vec<NotificationKind>) -> Self {
+        Self { muted_kinds, ..self }
+    }
+
+    pub fn with_muted_groups(self, muted_groups: Vec<i32>) -> Self {
+        Self { muted_groups, ..self }
+    }
+
+    /// Quiet hours run from the first time to the second, past midnight when
+    /// the first is later, both as `HH:MM` in local time.
+    pub fn with_quiet_hours(self, quiet_hours: Option<(String, String)>) -> Self {
+        Self { quiet_hours, ..self }
+    }
+
+    pub fn with_digest(self, digest: bool) -> Self {
+        Self { digest, ..self }
+    }
+
+    pub fn with_digest_time(self, digest_time: String) -> Self {
+        Self { digest_time, ..self }
+    }
+
+    /// Kinds of events the user is not told about at all.
+    pub fn muted_kinds(&self) -> &[NotificationKind] {
+        &self.muted_kinds
+    }
+
+    /// Groups the user is not told about at all.
+    pub fn muted_groups(&self) -> &[i32] {
+        &self.muted_groups
+    }
+
+    pub fn quiet_hours(&self) -> Option<(&str, &str)> {
+        self.quiet_hours.as_ref().map(|(start, end)| (start.as_str(), end.as_str()))
+    }
+
+    /// Whether notifications are collected into one summary a day instead of
+    /// reaching the user one by one.
+    pub fn digest(&self) -> bool {
+        self.digest
+    }
+
+    /// The local time, as `HH:MM`, after which the day's digest is sent.
+    pub fn digest_time(&self) -> &str {
+        &self.digest_time
+    }
+
+    /// Whether a notification of `kind` about `group_id` should be dropped.
+    pub fn mutes(&self, kind: NotificationKind, group_id: Option<i32>) -> bool {
+        self.muted_kinds.contains(&kind) || group_id.is_some_and(|group_id| self.muted_groups.contains(&group_id))
+    }
+
+    /// Whether `time`, as `HH:MM`, falls within the quiet hours.
+    pub fn is_quiet_at(&self, time: &str) -> bool {
+        match self.quiet_hours() {
+            Some((start, end)) if start <= end => start <= time && time < end,
+            Some((start, end)) => time >= start || time < end,
+            None => false,
+        }
+    }
+}
+
+/// An entry in a user's inbox. It stays there until it is archived.
 #[derive(Debug, Clone)]
 pub struct Notification {
     id: i32,
     message: String,
+    kind: NotificationKind,
+    created_at: String,
+    read: bool,
+    archived: bool,
+    debt_id: Option<i32>,
+    group_id: Option<i32>,
 }
 
 impl Notification {
-    pub fn new(id: i32, message: String) -> Notification {
-        Notification { id, message }
+    pub fn new(id: i32, message: String, kind: NotificationKind, created_at: String, read: bool) -> Notification {
+        Notification { id, message, kind, created_at, read, archived: false, debt_id: None, group_id: None }
+    }
+
+    /// Links the notification to the debt or group it is about.
+    pub fn with_links(self, debt_id: Option<i32>, group_id: Option<i32>) -> Notification {
+        Notification { debt_id, group_id, ..self }
+    }
+
+    pub fn with_archived(self, archived: bool) -> Notification {
+        Notification { archived, ..self }
     }
 
     pub fn id(&self) -> i32 {
@@ -16,4 +241,28 @@ impl Notification {
     pub fn message(&self) -> &str {
         &self.message
     }
-}
\ No newline at end of file
+
+    pub fn kind(&self) -> NotificationKind {
+        self.kind
+    }
+
+    pub fn created_at(&self) -> &str {
+        &self.created_at
+    }
+
+    pub fn is_read(&self) -> bool {
+        self.read
+    }
+
+    pub fn is_archived(&self) -> bool {
+        self.archived
+    }
+
+    pub fn debt_id(&self) -> Option<i32> {
+        self.debt_id
+    }
+
+    pub fn group_id(&self) -> Option<i32> {
+        self.group_id
+    }
+}
@@ -0,0 +1,155 @@
+use crate::money::Money;
+use crate::currency::DEFAULT_CURRENCY;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PaymentMethod {
+    #[default]
+    Cash,
+    BankTransfer,
+    Other,
+}
+
+impl PaymentMethod {
+    pub const ALL: [PaymentMethod; 3] = [PaymentMethod::Cash, PaymentMethod::BankTransfer, PaymentMethod::Other];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentMethod::Cash => "cash",
+            PaymentMethod::BankTransfer => "bank_transfer",
+            PaymentMethod::Other => "other",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PaymentMethod::Cash => "В брой",
+            PaymentMethod::BankTransfer => "Банков превод",
+            PaymentMethod::Other => "Друго",
+        }
+    }
+}
+
+impl ToSql for PaymentMethod {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for PaymentMethod {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let text = value.as_str()?;
+        PaymentMethod::ALL
+            .into_iter()
+            .find(|method| method.as_str() == text)
+            .ok_or_else(|| FromSqlError::Other(format!("unknown payment method: {}", text).into()))
+    }
+}
+
+/// Money paid back against a debt on a given date. It only counts towards the
+/// debt once both the debtor and the creditor have confirmed it.
+#[derive(Debug, Clone)]
+pub struct Payment {
+    id: i32,
+    debt_id: i32,
+    amount: Money,
+    method: PaymentMethod,
+    paid_on: String,
+    created_at: String,
+    confirmed_by_debtor: bool,
+    confirmed_by_creditor: bool,
+    from_name: String,
+    to_name: String,
+    group_name: String,
+    currency: String,
+}
+
+impl Payment {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        id: i32,
+        debt_id: i32,
+        amount: Money,
+        method: PaymentMethod,
+        paid_on: String,
+        created_at: String,
+        confirmed_by_debtor: bool,
+        confirmed_by_creditor: bool,
+    ) -> Self {
+        Self {
+            id,
+            debt_id,
+            amount,
+            method,
+            paid_on,
+            created_at,
+            confirmed_by_debtor,
+            confirmed_by_creditor,
+            from_name: String::new(),
+            to_name: String::new(),
+            group_name: String::new(),
+            currency: DEFAULT_CURRENCY.to_string(),
+        }
+    }
+
+    /// Adds who paid whom and in which group, for payment history lists.
+    pub fn with_parties(mut self, from_name: String, to_name: String, group_name: String, currency: String) -> Self {
+        self.from_name = from_name;
+        self.to_name = to_name;
+        self.group_name = group_name;
+        self.currency = currency;
+        self
+    }
+
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn debt_id(&self) -> i32 {
+        self.debt_id
+    }
+
+    pub fn amount(&self) -> Money {
+        self.amount
+    }
+
+    pub fn method(&self) -> PaymentMethod {
+        self.method
+    }
+
+    pub fn paid_on(&self) -> &str {
+        &self.paid_on
+    }
+
+    pub fn created_at(&self) -> &str {
+        &self.created_at
+    }
+
+    pub fn confirmed_by_debtor(&self) -> bool {
+        self.confirmed_by_debtor
+    }
+
+    pub fn confirmed_by_creditor(&self) -> bool {
+        self.confirmed_by_creditor
+    }
+
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmed_by_debtor && self.confirmed_by_creditor
+    }
+
+    pub fn from_name(&self) -> &str {
+        &self.from_name
+    }
+
+    pub fn to_name(&self) -> &str {
+        &self.to_name
+    }
+
+    pub fn group_name(&self) -> &str {
+        &self.group_name
+    }
+
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+}
@@ -0,0 +1,237 @@
+use crate::money::Money;
+use crate::split::Split;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RecurrenceInterval {
+    Weekly,
+    #[default]
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl RecurrenceInterval {
+    pub const ALL: [RecurrenceInterval; 4] = [
+        RecurrenceInterval::Weekly,
+        RecurrenceInterval::Monthly,
+        RecurrenceInterval::Quarterly,
+        RecurrenceInterval::Yearly,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecurrenceInterval::Weekly => "weekly",
+            RecurrenceInterval::Monthly => "monthly",
+            RecurrenceInterval::Quarterly => "quarterly",
+            RecurrenceInterval::Yearly => "yearly",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            RecurrenceInterval::Weekly => "Всяка седмица",
+            RecurrenceInterval::Monthly => "Всеки месец",
+            RecurrenceInterval::Quarterly => "На три месеца",
+            RecurrenceInterval::Yearly => "Всяка година",
+        }
+    }
+
+    /// The SQLite date modifier that moves the start date to the `n`-th occurrence.
+    /// Occurrences are always counted from the start date, so a schedule starting
+    /// on the 31st falls on the last day of shorter months instead of drifting.
+    pub fn modifier(&self, n: i64) -> String {
+        match self {
+            RecurrenceInterval::Weekly => format!("+{} days", 7 * n),
+            RecurrenceInterval::Monthly => format!("+{} months", n),
+            RecurrenceInterval::Quarterly => format!("+{} months", 3 * n),
+            RecurrenceInterval::Yearly => format!("+{} years", n),
+        }
+    }
+}
+
+impl ToSql for RecurrenceInterval {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for RecurrenceInterval {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let text = value.as_str()?;
+        RecurrenceInterval::ALL
+            .into_iter()
+            .find(|interval| interval.as_str() == text)
+            .ok_or_else(|| FromSqlError::Other(format!("unknown recurrence interval: {}", text).into()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RecurringStatus {
+    #[default]
+    Active,
+    Paused,
+    Cancelled,
+    Finished,
+}
+
+impl RecurringStatus {
+    pub const ALL: [RecurringStatus; 4] = [
+        RecurringStatus::Active,
+        RecurringStatus::Paused,
+        RecurringStatus::Cancelled,
+        RecurringStatus::Finished,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecurringStatus::Active => "active",
+            RecurringStatus::Paused => "paused",
+            RecurringStatus::Cancelled => "cancelled",
+            RecurringStatus::Finished => "finished",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            RecurringStatus::Active => "Активен",
+            RecurringStatus::Paused => "На пауза",
+            RecurringStatus::Cancelled => "Отказан",
+            RecurringStatus::Finished => "Приключил",
+        }
+    }
+
+    /// Cancelled and finished schedules never record anything again.
+    pub fn is_closed(&self) -> bool {
+        matches!(self, RecurringStatus::Cancelled | RecurringStatus::Finished)
+    }
+}
+
+impl ToSql for RecurringStatus {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for RecurringStatus {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let text = value.as_str()?;
+        RecurringStatus::ALL
+            .into_iter()
+            .find(|status| status.as_str() == text)
+            .ok_or_else(|| FromSqlError::Other(format!("unknown recurring status: {}", text).into()))
+    }
+}
+
+/// An expense that repeats on a schedule, such as rent or a subscription.
+/// Every occurrence that comes due is recorded as an ordinary expense.
+#[derive(Debug, Clone)]
+pub struct RecurringExpense {
+    id: i32,
+    group_id: i32,
+    payer_id: i32,
+    payer_name: String,
+    amount: Money,
+    currency: String,
+    description: String,
+    interval: RecurrenceInterval,
+    start_date: String,
+    end_date: Option<String>,
+    next_date: String,
+    status: RecurringStatus,
+    split: Split,
+}
+
+impl RecurringExpense {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        id: i32,
+        group_id: i32,
+        payer_id: i32,
+        payer_name: String,
+        amount: Money,
+        currency: String,
+        description: String,
+        interval: RecurrenceInterval,
+        start_date: String,
+        end_date: Option<String>,
+        next_date: String,
+        status: RecurringStatus,
+    ) -> Self {
+        Self {
+            id,
+            group_id,
+            payer_id,
+            payer_name,
+            amount,
+            currency,
+            description,
+            interval,
+            start_date,
+            end_date,
+            next_date,
+            status,
+            split: Split::default(),
+        }
+    }
+
+    pub fn with_split(self, split: Split) -> Self {
+        Self { split, ..self }
+    }
+
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn group_id(&self) -> i32 {
+        self.group_id
+    }
+
+    pub fn payer_id(&self) -> i32 {
+        self.payer_id
+    }
+
+    pub fn payer_name(&self) -> &str {
+        &self.payer_name
+    }
+
+    pub fn amount(&self) -> Money {
+        self.amount
+    }
+
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn interval(&self) -> RecurrenceInterval {
+        self.interval
+    }
+
+    pub fn start_date(&self) -> &str {
+        &self.start_date
+    }
+
+    /// The last day an occurrence may fall on, if the schedule ends at all.
+    pub fn end_date(&self) -> Option<&str> {
+        self.end_date.as_deref()
+    }
+
+    /// When the next occurrence will be recorded.
+    pub fn next_date(&self) -> &str {
+        &self.next_date
+    }
+
+    pub fn status(&self) -> RecurringStatus {
+        self.status
+    }
+
+    /// The split every occurrence is recorded with. An equal split without
+    /// entries covers whoever is in the group when the occurrence comes due.
+    pub fn split(&self) -> &Split {
+        &self.split
+    }
+}
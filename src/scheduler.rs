@@ -0,0 +1,127 @@
+use std::time::{Duration, Instant};
+use rusqlite::Connection;
+use crate::db::{deliver_notifications, materialize_recurring_expenses, notify_overdue_debts, notify_recurring_expense, notify_upcoming_debts, send_digests};
+use crate::delivery::default_channels;
+
+/// A message for one user produced by a background job.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alert {
+    user_id: i32,
+    message: String,
+}
+
+impl Alert {
+    pub fn new(user_id: i32, message: String) -> Self {
+        Self { user_id, message }
+    }
+
+    pub fn user_id(&self) -> i32 {
+        self.user_id
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+type JobFn = fn(&Connection) -> Result<Vec<Alert>, String>;
+
+struct Job {
+    name: &'static str,
+    every: Duration,
+    run: JobFn,
+    next_run: Instant,
+}
+
+/// Runs periodic jobs from the backend thread between commands.
+#[derive(Default)]
+pub struct Scheduler {
+    jobs: Vec<Job>,
+}
+
+impl Scheduler {
+    /// Records recurring expenses every ten minutes. Every hour it looks for
+    /// debts that have become overdue or are coming due and sends the daily
+    /// digests that are ready. All jobs first run at `start`.
+    pub fn with_default_jobs(start: Instant) -> Self {
+        Self::default()
+            .every("recurring expenses", Duration::from_secs(10 * 60), record_recurring_expenses, start)
+            .every("overdue debts", Duration::from_secs(60 * 60), scan_overdue_debts, start)
+            .every("due reminders", Duration::from_secs(60 * 60), remind_due_debts, start)
+            .every("daily digests", Duration::from_secs(60 * 60), send_daily_digests, start)
+    }
+
+    /// Adds a job that first runs at `start` and then every `every`.
+    pub fn every(mut self, name: &'static str, every: Duration, run: JobFn, start: Instant) -> Self {
+        self.jobs.push(Job { name, every, run, next_run: start });
+        self
+    }
+
+    /// How long the backend may wait for a command before a job is due.
+    pub fn time_until_next(&self, now: Instant) -> Duration {
+        self.jobs
+            .iter()
+            .map(|job| job.next_run.saturating_duration_since(now))
+            .min()
+            .unwrap_or(Duration::MAX)
+    }
+
+    /// Runs every job that is due at `now` and collects what they found.
+    /// A failing job is reported and tried again at its next interval.
+    pub fn run_due(&mut self, conn: &Connection, now: Instant) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+
+        for job in self.jobs.iter_mut().filter(|job| job.next_run <= now) {
+            match (job.run)(conn) {
+                Ok(found) => alerts.extend(found),
+                Err(e) => eprintln!("Scheduled job '{}' failed: {}", job.name, e),
+            }
+            job.next_run = now + job.every;
+        }
+
+        alerts
+    }
+}
+
+fn record_recurring_expenses(conn: &Connection) -> Result<Vec<Alert>, String> {
+    let mut alerts = Vec::new();
+
+    for expense_id in materialize_recurring_expenses(conn)? {
+        alerts.extend(
+            notify_recurring_expense(conn, expense_id)?
+                .into_iter()
+                .map(|(user_id, message)| Alert::new(user_id, message)),
+        );
+    }
+
+    Ok(alerts)
+}
+
+/// Only debts that were not overdue at the previous scan produce an alert.
+fn scan_overdue_debts(conn: &Connection) -> Result<Vec<Alert>, String> {
+    Ok(notify_overdue_debts(conn)?
+        .into_iter()
+        .map(|(user_id, message)| Alert::new(user_id, message))
+        .collect())
+}
+
+fn send_daily_digests(conn: &Connection) -> Result<Vec<Alert>, String> {
+    Ok(send_digests(conn)?
+        .into_iter()
+        .map(|(user_id, message)| Alert::new(user_id, message))
+        .collect())
+}
+
+/// Sends notifications by email and webhook, retrying the ones that failed.
+/// It produces no alerts of its own.
+pub fn send_notifications(conn: &Connection) -> Result<Vec<Alert>, String> {
+    deliver_notifications(conn, &default_channels())?;
+    Ok(Vec::new())
+}
+
+fn remind_due_debts(conn: &Connection) -> Result<Vec<Alert>, String> {
+    Ok(notify_upcoming_debts(conn)?
+        .into_iter()
+        .map(|(user_id, message)| Alert::new(user_id, message))
+        .collect())
+}
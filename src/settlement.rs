@@ -0,0 +1,133 @@
+use crate::money::Money;
+use crate::currency::DEFAULT_CURRENCY;
+
+/// A member's net position in a group: positive when the others owe them money,
+/// negative when they owe money to the others.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Balance {
+    user_id: i32,
+    username: String,
+    amount: Money,
+    currency: String,
+}
+
+impl Balance {
+    pub fn new(user_id: i32, username: String, amount: Money) -> Self {
+        Self { user_id, username, amount, currency: DEFAULT_CURRENCY.to_string() }
+    }
+
+    pub fn with_currency(self, currency: String) -> Self {
+        Self { currency, ..self }
+    }
+
+    pub fn user_id(&self) -> i32 {
+        self.user_id
+    }
+
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    pub fn amount(&self) -> Money {
+        self.amount
+    }
+
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+}
+
+/// A single "X pays Y amount" step of a settlement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transfer {
+    from_id: i32,
+    from_name: String,
+    to_id: i32,
+    to_name: String,
+    amount: Money,
+    currency: String,
+}
+
+impl Transfer {
+    pub fn new(from_id: i32, from_name: String, to_id: i32, to_name: String, amount: Money) -> Self {
+        Self { from_id, from_name, to_id, to_name, amount, currency: DEFAULT_CURRENCY.to_string() }
+    }
+
+    pub fn with_currency(self, currency: String) -> Self {
+        Self { currency, ..self }
+    }
+
+    pub fn from_id(&self) -> i32 {
+        self.from_id
+    }
+
+    pub fn from_name(&self) -> &str {
+        &self.from_name
+    }
+
+    pub fn to_id(&self) -> i32 {
+        self.to_id
+    }
+
+    pub fn to_name(&self) -> &str {
+        &self.to_name
+    }
+
+    pub fn amount(&self) -> Money {
+        self.amount
+    }
+
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+}
+
+/// Turns net balances into a short list of transfers that settles everyone.
+///
+/// The member who owes the most pays the member who is owed the most, as much
+/// as the smaller of the two amounts, until every balance is zero. Each step
+/// settles at least one member, so there are never more than `n - 1` transfers
+/// and cycles such as A→B→C→A disappear. Ties are broken by ascending user id.
+/// The balances are expected to share one currency, which the transfers keep.
+pub fn simplify(balances: &[Balance]) -> Vec<Transfer> {
+    let currency = balances.first().map_or(DEFAULT_CURRENCY, |balance| balance.currency());
+
+    let mut debtors: Vec<(i32, &str, Money)> = Vec::new();
+    let mut creditors: Vec<(i32, &str, Money)> = Vec::new();
+
+    for balance in balances {
+        if balance.amount.is_negative() {
+            debtors.push((balance.user_id, &balance.username, -balance.amount));
+        }
+        else if balance.amount.is_positive() {
+            creditors.push((balance.user_id, &balance.username, balance.amount));
+        }
+    }
+
+    let mut transfers = Vec::new();
+
+    loop {
+        let debtor = debtors.iter().enumerate().max_by_key(|(_, d)| (d.2, std::cmp::Reverse(d.0))).map(|(i, _)| i);
+        let creditor = creditors.iter().enumerate().max_by_key(|(_, c)| (c.2, std::cmp::Reverse(c.0))).map(|(i, _)| i);
+
+        let (Some(d), Some(c)) = (debtor, creditor) else {
+            break;
+        };
+
+        let amount = debtors[d].2.min(creditors[c].2);
+        transfers.push(Transfer::new(
+            debtors[d].0,
+            debtors[d].1.to_string(),
+            creditors[c].0,
+            creditors[c].1.to_string(),
+            amount,
+        ).with_currency(currency.to_string()));
+
+        debtors[d].2 -= amount;
+        creditors[c].2 -= amount;
+        debtors.retain(|d| d.2.is_positive());
+        creditors.retain(|c| c.2.is_positive());
+    }
+
+    transfers
+}
@@ -0,0 +1,215 @@
+use crate::money::Money;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+
+/// Basis points in 100%; percentages are stored with two decimal places.
+pub const FULL_PERCENTAGE: i64 = 10_000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SplitMode {
+    #[default]
+    Equal,
+    Exact,
+    Percentage,
+    Shares,
+}
+
+impl SplitMode {
+    pub const ALL: [SplitMode; 4] = [SplitMode::Equal, SplitMode::Exact, SplitMode::Percentage, SplitMode::Shares];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SplitMode::Equal => "equal",
+            SplitMode::Exact => "exact",
+            SplitMode::Percentage => "percentage",
+            SplitMode::Shares => "shares",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SplitMode::Equal => "Поравно",
+            SplitMode::Exact => "Точни суми",
+            SplitMode::Percentage => "Проценти",
+            SplitMode::Shares => "Дялове",
+        }
+    }
+}
+
+impl ToSql for SplitMode {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for SplitMode {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let text = value.as_str()?;
+        SplitMode::ALL
+            .into_iter()
+            .find(|mode| mode.as_str() == text)
+            .ok_or_else(|| FromSqlError::Other(format!("unknown split mode: {}", text).into()))
+    }
+}
+
+/// How an expense is divided between participants.
+///
+/// Each entry is a user id and a value whose meaning depends on the mode:
+/// ignored for `Equal`, minor units for `Exact`, basis points (hundredths of a
+/// percent) for `Percentage` and a weight for `Shares`.
+/// An equal split without entries covers every member of the group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Split {
+    mode: SplitMode,
+    entries: Vec<(i32, i64)>,
+}
+
+impl Default for Split {
+    fn default() -> Self {
+        Self::equal(Vec::new())
+    }
+}
+
+impl Split {
+    pub fn new(mode: SplitMode, entries: Vec<(i32, i64)>) -> Self {
+        Self { mode, entries }
+    }
+
+    pub fn equal(participants: Vec<i32>) -> Self {
+        Self::new(SplitMode::Equal, participants.into_iter().map(|id| (id, 1)).collect())
+    }
+
+    pub fn exact(amounts: Vec<(i32, Money)>) -> Self {
+        Self::new(SplitMode::Exact, amounts.into_iter().map(|(id, amount)| (id, amount.minor())).collect())
+    }
+
+    pub fn percentage(basis_points: Vec<(i32, i64)>) -> Self {
+        Self::new(SplitMode::Percentage, basis_points)
+    }
+
+    pub fn shares(weights: Vec<(i32, i64)>) -> Self {
+        Self::new(SplitMode::Shares, weights)
+    }
+
+    pub fn mode(&self) -> SplitMode {
+        self.mode
+    }
+
+    pub fn entries(&self) -> &[(i32, i64)] {
+        &self.entries
+    }
+
+    pub fn participants(&self) -> Vec<i32> {
+        self.entries.iter().map(|&(id, _)| id).collect()
+    }
+
+    /// Returns each participant's share of `total` in ascending user id order.
+    /// The shares always sum exactly to `total`.
+    pub fn allocate(&self, total: Money, payer_id: i32) -> Result<Vec<(i32, Money)>, String> {
+        if self.entries.is_empty() {
+            return Err("Изберете поне един участник.".to_string());
+        }
+
+        let mut ids = self.participants();
+        ids.sort_unstable();
+        ids.dedup();
+        if ids.len() != self.entries.len() {
+            return Err("Участник е посочен повече от веднъж.".to_string());
+        }
+
+        if self.entries.iter().any(|&(_, value)| value < 0) {
+            return Err("Стойностите за разпределение не могат да са отрицателни.".to_string());
+        }
+
+        let sum: i64 = self.entries.iter().map(|&(_, value)| value).sum();
+
+        match self.mode {
+            SplitMode::Equal => Ok(split_equal(total, &ids, payer_id)),
+            SplitMode::Exact => {
+                if sum != total.minor() {
+                    return Err(format!(
+                        "Сборът на сумите ({}) не съвпада с общата сума ({}).",
+                        Money::from_minor(sum),
+                        total
+                    ));
+                }
+                let mut shares: Vec<(i32, Money)> = self.entries.iter()
+                    .map(|&(id, value)| (id, Money::from_minor(value)))
+                    .collect();
+                shares.sort_by_key(|&(id, _)| id);
+                Ok(shares)
+            }
+            SplitMode::Percentage => {
+                if sum != FULL_PERCENTAGE {
+                    return Err("Процентите трябва да са общо 100%.".to_string());
+                }
+                Ok(split_by_weight(total, &self.entries, payer_id))
+            }
+            SplitMode::Shares => {
+                if sum == 0 {
+                    return Err("Общият брой дялове трябва да е положителен.".to_string());
+                }
+                Ok(split_by_weight(total, &self.entries, payer_id))
+            }
+        }
+    }
+}
+
+/// Parses a percentage such as `60` or `33.33` into basis points.
+pub fn parse_percentage(input: &str) -> Result<i64, String> {
+    Money::parse(input)
+        .map(|value| value.minor())
+        .map_err(|_| "Невалиден процент.".to_string())
+}
+
+/// Splits `total` equally between `participants`, rounded to whole minor units.
+///
+/// Every participant gets `total / n` rounded down. The leftover minor units
+/// (always fewer than `n`) are handed out one at a time: first to the payer,
+/// if they take part in the expense, and then to the remaining participants in
+/// ascending user id order. The returned shares always sum exactly to `total`
+/// and are listed in ascending user id order.
+pub fn split_equal(total: Money, participants: &[i32], payer_id: i32) -> Vec<(i32, Money)> {
+    let weights: Vec<(i32, i64)> = participants.iter().map(|&id| (id, 1)).collect();
+    split_by_weight(total, &weights, payer_id)
+}
+
+/// Splits `total` proportionally to `weights` using the largest remainder method.
+///
+/// Every participant gets their exact proportional share rounded down. The
+/// leftover minor units go one at a time to the participants with the largest
+/// discarded fractions; ties are broken in favour of the payer and then by
+/// ascending user id, which makes an equal split behave as `split_equal`
+/// documents. The returned shares always sum exactly to `total` and are listed
+/// in ascending user id order.
+pub fn split_by_weight(total: Money, weights: &[(i32, i64)], payer_id: i32) -> Vec<(i32, Money)> {
+    let mut weights = weights.to_vec();
+    weights.sort_by_key(|&(id, _)| id);
+    weights.dedup_by_key(|&mut (id, _)| id);
+
+    let weight_sum: i128 = weights.iter().map(|&(_, weight)| weight as i128).sum();
+    if weight_sum == 0 {
+        return Vec::new();
+    }
+
+    let total_minor = total.minor() as i128;
+    let mut shares = Vec::with_capacity(weights.len());
+    let mut fractions = Vec::with_capacity(weights.len());
+
+    for &(id, weight) in &weights {
+        let exact = total_minor * weight as i128;
+        shares.push((id, Money::from_minor(exact.div_euclid(weight_sum) as i64)));
+        fractions.push(exact.rem_euclid(weight_sum));
+    }
+
+    let allocated: Money = shares.iter().map(|&(_, share)| share).sum();
+    let leftover = (total - allocated).minor() as usize;
+
+    let mut order: Vec<usize> = (0..shares.len()).collect();
+    order.sort_by_key(|&i| (std::cmp::Reverse(fractions[i]), shares[i].0 != payer_id, shares[i].0));
+
+    for &i in order.iter().take(leftover) {
+        shares[i].1 += Money::from_minor(1);
+    }
+
+    shares
+}
@@ -0,0 +1,68 @@
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::money::Money;
+use split_money_manager::payment::PaymentMethod;
+use split_money_manager::split::Split;
+
+mod common;
+
+#[test]
+fn convert_leva_to_euro() {
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria"), (3, "Georgi")]);
+    conn.execute("INSERT INTO groups (id, name, owner_id, currency) VALUES (1, 'Flat', 1, 'BGN')", []).unwrap();
+    for user_id in 1..=3 {
+        conn.execute("INSERT INTO group_members (group_id, user_id) VALUES (1, ?1)", [user_id]).unwrap();
+    }
+
+    let expense_id = db::add_expenses(&conn, 1, 1, Money::from_major(100), None, "Наем", "2099-01-01", &Split::default()).unwrap();
+    let debt_id: i32 = conn.query_row("SELECT id FROM debts WHERE from_id = 2", [], |row| row.get(0)).unwrap();
+    db::record_partial_payment(&conn, 2, debt_id, Money::from_major(10), PaymentMethod::Cash, "2026-01-10").unwrap();
+    let payment_id = db::get_debt_payments(&conn, debt_id).unwrap()[0].id();
+    db::confirm_partial_payment(&conn, 1, payment_id).unwrap();
+    // Georgi's 4 лв. share is added to his open debt; 4 лв. alone would round up to 2.05 €.
+    let water_id = db::add_expenses(&conn, 1, 1, Money::from_major(12), None, "Вода", "2099-01-01", &Split::default()).unwrap();
+
+    assert_eq!(db::convert_leva_to_euro(&conn).unwrap(), 9);
+    assert_eq!(db::convert_leva_to_euro(&conn).unwrap(), 0);
+
+    assert_eq!(db::get_group_currency(&conn, 1).unwrap(), "EUR");
+
+    let (amount, paid, currency): (Money, Money, String) = conn.query_row(
+        "SELECT amount, paid, currency FROM debts WHERE id = ?1",
+        [debt_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    ).unwrap();
+    assert_eq!((amount, paid, currency.as_str()), (Money::from_minor(1704), Money::from_minor(511), "EUR"));
+
+    let expense = db::get_expense(&conn, expense_id).unwrap();
+    assert_eq!(
+        (expense.amount(), expense.currency(), expense.group_amount(), expense.group_currency()),
+        (Money::from_minor(5113), "EUR", Money::from_minor(5113), "EUR")
+    );
+    let shares: Vec<Money> = expense.shares().iter().map(|share| share.amount()).collect();
+    assert_eq!(shares, vec![Money::from_minor(1705), Money::from_minor(1704), Money::from_minor(1704)]);
+
+    let water: Vec<Money> = db::get_expense(&conn, water_id).unwrap().shares().iter().map(|share| share.amount()).collect();
+    assert_eq!(water, vec![Money::from_minor(205), Money::from_minor(205), Money::from_minor(204)]);
+    let georgi: Money = conn.query_row("SELECT amount FROM debts WHERE from_id = 3", [], |row| row.get(0)).unwrap();
+    assert_eq!(georgi, Money::from_minor(1704) + Money::from_minor(204));
+
+    let (original, converted): (Money, Money) = conn.query_row(
+        "SELECT original_amount, converted_amount FROM currency_conversions
+         WHERE table_name = 'debts' AND row_id = ?1 AND column_name = 'amount'",
+        [debt_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).unwrap();
+    assert_eq!((original, converted), (Money::from_minor(3333), Money::from_minor(1704)));
+
+    db::delete_expense(&conn, 1, expense_id).unwrap();
+    let balances = db::get_group_balances(&conn, 1, 1).unwrap();
+    let amounts: Vec<Money> = balances.iter().map(|balance| balance.amount()).collect();
+    assert_eq!(amounts, vec![Money::from_minor(-102), Money::from_minor(306), Money::from_minor(-204)]);
+
+    let group_id = db::create_group(&conn, "Trip", 1, &[1, 2]).unwrap();
+    assert_eq!(db::get_group_currency(&conn, group_id).unwrap(), "EUR");
+}
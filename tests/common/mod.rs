@@ -0,0 +1,12 @@
+use rusqlite::Connection;
+
+/// Inserts users with the given ids and names, each with a `name@example.com`
+/// address and no password.
+pub fn insert_users(conn: &Connection, users: &[(i32, &str)]) {
+    for &(id, name) in users {
+        conn.execute(
+            "INSERT INTO users (id, username, email, password_hash) VALUES (?1, ?2, ?3, '')",
+            rusqlite::params![id, name, format!("{}@example.com", name.to_lowercase())],
+        ).unwrap();
+    }
+}
@@ -1,37 +1,15 @@
 use rusqlite::{Connection, params};
 use split_money_manager::*;
 
+mod common;
+
 #[test]
 
 fn create_group() {
     let conn = Connection::open_in_memory().unwrap();
-    conn.execute_batch("
-        CREATE TABLE users (
-            id INTEGER PRIMARY KEY,
-            username TEXT NOT NULL,
-            email TEXT UNIQUE NOT NULL,
-            on_time_payments INTEGER DEFAULT 0,
-            loyal_payer INTEGER DEFAULT 0
-        );
-
-        CREATE TABLE groups (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            name TEXT NOT NULL,
-            owner_id INTEGER NOT NULL
-        );
-
-        CREATE TABLE group_members (
-            group_id INTEGER NOT NULL,
-            user_id INTEGER NOT NULL,
-            PRIMARY KEY (group_id, user_id)
-        );
-
-    ").unwrap();
+    db::init_schema(&conn).unwrap();
 
-    conn.execute("INSERT INTO users (id, username, email) VALUES (1, 'Ivan', 'ivan@example.com')", []).unwrap();
-    conn.execute("INSERT INTO users (id, username, email) VALUES (2, 'Maria', 'maria@example.com')", []).unwrap();
-    conn.execute("INSERT INTO users (id, username, email) VALUES (3, 'Georgi', 'georgi@example.com')", []).unwrap();
-    conn.execute("INSERT INTO users (id, username, email) VALUES (4, 'Petar', 'petar@example.com')", []).unwrap();
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria"), (3, "Georgi"), (4, "Petar")]);
 
     let _ = db::create_group(&conn, "super group", 1, &[2, 3, 4]);
 
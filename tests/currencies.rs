@@ -0,0 +1,62 @@
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::currency;
+use split_money_manager::money::Money;
+use split_money_manager::split::Split;
+
+mod common;
+
+#[test]
+fn multi_currency_expenses() {
+    let csv = "Date, USD, JPY, BGN, CHF, TRY, \n17 October 2025, 1.1697, 175.80, 1.9558, 0.9300, 48.9915, \n";
+    let rates = currency::parse_ecb_csv(csv).unwrap();
+    assert_eq!(rates.len(), 5);
+    assert_eq!(rates[3].currency(), "CHF");
+    assert_eq!(rates[3].rate_date(), "2025-10-17");
+    assert_eq!(rates[3].rate(), 930_000);
+    assert_eq!(currency::parse_code("eur").unwrap(), "EUR");
+    assert_eq!(currency::parse_code("euro").unwrap_err(), "Невалиден код на валута: euro.");
+    assert_eq!(currency::convert(Money::from_major(100), 930_000, 1_000_000), Money::from_minor(10753));
+    assert_eq!(currency::format_amount(Money::from_major(5), "BGN"), "5.00 лв.");
+    assert_eq!(currency::format_amount(Money::from_major(5), "TRY"), "5.00 TRY");
+
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+    assert_eq!(db::import_exchange_rates(&conn, csv).unwrap(), 5);
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria"), (3, "Georgi")]);
+
+    let group_id = db::create_group(&conn, "Alps", 1, &[1, 2, 3]).unwrap();
+    db::set_group_currency(&conn, 1, group_id, "eur").unwrap();
+    assert_eq!(db::get_group_currency(&conn, group_id).unwrap(), "EUR");
+    assert_eq!(db::set_group_currency(&conn, 1, group_id, "XYZ").unwrap_err(), "Няма зареден курс за XYZ.");
+
+    // 100 CHF is 107.53 EUR, split three ways.
+    let hotel = db::add_expenses(&conn, 1, group_id, Money::from_major(100), Some("CHF"), "Хотел", "2099-01-01", &Split::default()).unwrap();
+    db::add_expenses(&conn, 2, group_id, Money::from_major(30), None, "Вечеря", "2099-01-01", &Split::default()).unwrap();
+
+    let expense = db::get_expense(&conn, hotel).unwrap();
+    assert_eq!((expense.currency(), expense.group_currency()), ("CHF", "EUR"));
+    assert_eq!(expense.group_amount(), Money::from_minor(10753));
+    assert_eq!(expense.shares().iter().map(|share| share.amount()).sum::<Money>(), Money::from_major(100));
+
+    let balances = db::get_group_balances(&conn, 1, group_id).unwrap();
+    assert_eq!(balances.iter().map(|balance| balance.amount()).sum::<Money>(), Money::ZERO);
+    assert_eq!(balances[0].amount(), Money::from_minor(7168 - 1000));
+    assert_eq!(balances[0].currency(), "EUR");
+
+    let debts = db::get_user_debts_or_credits(&conn, 3, true).unwrap();
+    assert!(debts.iter().all(|debt| debt.currency() == "EUR"));
+
+    assert_eq!(
+        db::set_group_currency(&conn, 1, group_id, "BGN").unwrap_err(),
+        "Валутата на групата може да се смени само когато всички дългове в нея са уредени."
+    );
+
+    // Rates moving after the fact do not change what deleting the expense gives back.
+    db::import_exchange_rates(&conn, "Date,CHF\n2025-10-20,0.9000\n").unwrap();
+    db::delete_expense(&conn, 1, hotel).unwrap();
+    let balances = db::get_group_balances(&conn, 1, group_id).unwrap();
+    assert_eq!(balances[0].amount(), Money::from_major(-10));
+    assert_eq!(balances[1].amount(), Money::from_major(20));
+}
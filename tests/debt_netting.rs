@@ -0,0 +1,54 @@
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::money::Money;
+use split_money_manager::split::Split;
+
+mod common;
+
+#[test]
+fn netted_debt_keeps_its_expenses() {
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria")]);
+    conn.execute("INSERT INTO groups (id, name, owner_id) VALUES (1, 'gr1', 1)", []).unwrap();
+    conn.execute("INSERT INTO group_members (group_id, user_id) VALUES (1, 1)", []).unwrap();
+    conn.execute("INSERT INTO group_members (group_id, user_id) VALUES (1, 2)", []).unwrap();
+
+    let dinner = db::add_expenses(&conn, 1, 1, Money::from_major(20), None, "Вечеря", "2099-01-01", &Split::default()).unwrap();
+    let taxi = db::add_expenses(&conn, 2, 1, Money::from_major(20), None, "Такси", "2099-01-01", &Split::default()).unwrap();
+
+    let (debt_id, settled): (i32, bool) = conn.query_row("SELECT id, settled FROM debts", [], |row| Ok((row.get(0)?, row.get(1)?))).unwrap();
+    assert!(settled);
+    let linked: Vec<i32> = conn.prepare("SELECT expense_id FROM debt_expenses WHERE debt_id = ?1 ORDER BY expense_id").unwrap()
+        .query_map([debt_id], |row| row.get(0)).unwrap()
+        .collect::<Result<_, _>>().unwrap();
+    assert_eq!(linked, vec![dinner, taxi]);
+
+    db::delete_expense(&conn, 1, dinner).unwrap();
+    let open: Vec<(i32, i32, Money)> = conn.prepare("SELECT from_id, to_id, amount FROM debts WHERE settled = 0").unwrap()
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))).unwrap()
+        .collect::<Result<_, _>>().unwrap();
+    assert_eq!(open, vec![(1, 2, Money::from_major(10))]);
+
+    // A larger debt the other way round closes the open one without changing it.
+    let (open_id, creditor): (i32, i32) = conn.query_row("SELECT id, to_id FROM debts WHERE settled = 0", [], |row| Ok((row.get(0)?, row.get(1)?))).unwrap();
+    db::nudge_debtor(&conn, creditor, open_id).unwrap();
+    let rent = db::add_expenses(&conn, 1, 1, Money::from_major(60), None, "Наем", "2099-01-01", &Split::default()).unwrap();
+
+    let closed: (Money, Money, bool, bool) = conn.query_row(
+        "SELECT amount, paid, settled, netted FROM debts WHERE id = ?1",
+        [open_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    ).unwrap();
+    assert_eq!(closed, (Money::from_major(10), Money::ZERO, true, true));
+    let nudges: i32 = conn.query_row("SELECT COUNT(*) FROM debt_nudges d JOIN debts ON debts.id = d.debt_id", [], |row| row.get(0)).unwrap();
+    assert_eq!(nudges, 1);
+
+    let (remaining_id, remaining): (i32, Money) = conn.query_row("SELECT id, amount FROM debts WHERE settled = 0 AND from_id = 2 AND to_id = 1", [], |row| Ok((row.get(0)?, row.get(1)?))).unwrap();
+    assert_eq!(remaining, Money::from_major(20));
+    let linked: Vec<i32> = conn.prepare("SELECT expense_id FROM debt_expenses WHERE debt_id = ?1 ORDER BY expense_id").unwrap()
+        .query_map([remaining_id], |row| row.get(0)).unwrap()
+        .collect::<Result<_, _>>().unwrap();
+    assert_eq!(linked, vec![dinner, rent]);
+}
@@ -0,0 +1,39 @@
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::notification::NotificationKind;
+
+mod common;
+
+#[test]
+fn debt_nudge() {
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria")]);
+    let group_id = db::create_group(&conn, "Flat", 1, &[1, 2]).unwrap();
+    conn.execute(
+        "INSERT INTO debts (id, from_id, to_id, amount, group_id, due_date, description, currency)
+         VALUES (100, 2, 1, 1250, ?1, '2099-01-01', 'Наем', 'EUR')",
+        [group_id],
+    ).unwrap();
+
+    assert_eq!(db::nudge_debtor(&conn, 2, 100).unwrap_err(), "Само кредиторът може да напомня за този дълг.");
+    assert_eq!(db::nudge_debtor(&conn, 1, 100).unwrap(), "Напомнянето е изпратено на Maria.");
+    assert!(db::nudge_debtor(&conn, 1, 100).unwrap_err().starts_with("Вече напомнихте за този дълг."));
+
+    let inbox = db::get_user_notifications(&conn, 2).unwrap();
+    assert_eq!(inbox[0].kind(), NotificationKind::Nudge);
+    assert_eq!(inbox[0].message(), "Ivan ви напомня за дълга от 12.50 € със срок 2099-01-01");
+    assert_eq!(inbox[0].debt_id(), Some(100));
+
+    conn.execute("UPDATE debt_nudges SET created_at = datetime('now', '-25 hours')", []).unwrap();
+    db::nudge_debtor(&conn, 1, 100).unwrap();
+
+    let nudges: Vec<(i32, i32)> = conn.prepare("SELECT from_id, to_id FROM debt_nudges WHERE debt_id = 100").unwrap()
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap()
+        .collect::<Result<_, _>>().unwrap();
+    assert_eq!(nudges, vec![(1, 2), (1, 2)]);
+    assert_eq!(db::count_unread_notifications(&conn, 2).unwrap(), 3);
+
+    assert_eq!(db::nudge_debtor(&conn, 1, 999).unwrap_err(), "Дългът не е намерен или вече е изплатен.");
+}
@@ -0,0 +1,46 @@
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::money::Money;
+use split_money_manager::notification::{NotificationPreferences, DEFAULT_DIGEST_TIME};
+use split_money_manager::split::Split;
+
+mod common;
+
+#[test]
+fn digest_waits_for_its_time() {
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria")]);
+    let flat = db::create_group(&conn, "Flat", 1, &[1, 2]).unwrap();
+    let add = |description: &str| {
+        db::add_expenses(&conn, 1, flat, Money::from_major(30), None, description, "2099-01-01", &Split::default()).unwrap();
+    };
+    assert_eq!(db::get_notification_preferences(&conn, 2).unwrap().digest_time(), DEFAULT_DIGEST_TIME);
+
+    let bad = NotificationPreferences::new().with_digest(true).with_digest_time("8".to_string());
+    assert_eq!(db::set_notification_preferences(&conn, 2, &bad).unwrap_err(), "Невалиден час: 8. Използвайте формат ЧЧ:ММ.");
+
+    let now: String = conn.query_row("SELECT strftime('%H:%M', 'now', 'localtime')", [], |row| row.get(0)).unwrap();
+    if now.as_str() < "23:59" {
+        let evening = NotificationPreferences::new().with_digest(true).with_digest_time("23:59".to_string());
+        db::set_notification_preferences(&conn, 2, &evening).unwrap();
+        add("Хляб");
+        assert!(db::send_digests(&conn).unwrap().is_empty());
+    }
+
+    let morning = NotificationPreferences::new().with_digest(true).with_digest_time("00:00".to_string());
+    db::set_notification_preferences(&conn, 2, &morning).unwrap();
+    assert_eq!(db::get_notification_preferences(&conn, 2).unwrap(), morning);
+    conn.execute("DELETE FROM notifications WHERE user_id = 2", []).unwrap();
+    add("Хляб");
+    add("Мляко");
+    assert_eq!(db::send_digests(&conn).unwrap(), vec![(
+        2,
+        "Обобщение за деня (2 известия):\n• Ivan добави разход „Хляб“ от 30.00 € в група Flat\n• Ivan добави разход „Мляко“ от 30.00 € в група Flat".to_string(),
+    )]);
+
+    add("Сирене");
+    assert!(db::send_digests(&conn).unwrap().is_empty());
+    assert_eq!(db::count_unread_notifications(&conn, 2).unwrap(), 1);
+}
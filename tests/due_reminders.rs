@@ -0,0 +1,43 @@
+use rusqlite::Connection;
+use split_money_manager::*;
+
+mod common;
+
+#[test]
+fn due_reminders() {
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria"), (3, "Georgi")]);
+    let group_id = db::create_group(&conn, "Flat", 1, &[1, 2, 3]).unwrap();
+    assert_eq!(db::get_reminder_days(&conn, 2).unwrap(), vec![3, 1]);
+
+    for (id, from_id, days) in [(100, 2, 2), (101, 3, 1), (102, 1, 10)] {
+        conn.execute(
+            "INSERT INTO debts (id, from_id, to_id, amount, group_id, due_date, description, currency)
+             VALUES (?1, ?2, 1, 1000, ?3, date('now', ?4), 'Наем', 'EUR')",
+            rusqlite::params![id, from_id, group_id, format!("+{} days", days)],
+        ).unwrap();
+    }
+    conn.execute("UPDATE debts SET to_id = 2 WHERE id = 102", []).unwrap();
+
+    let due_in_two: String = conn.query_row("SELECT due_date FROM debts WHERE id = 100", [], |row| row.get(0)).unwrap();
+    assert_eq!(db::notify_upcoming_debts(&conn).unwrap(), vec![
+        (3, "Утре е срокът на дълга ви от 10.00 € към Ivan".to_string()),
+        (2, format!("След 2 дни ({}) е срокът на дълга ви от 10.00 € към Ivan", due_in_two)),
+    ]);
+    assert!(db::notify_upcoming_debts(&conn).unwrap().is_empty());
+
+    db::set_reminder_days(&conn, 1, &[7, 14, 14]).unwrap();
+    assert_eq!(db::get_reminder_days(&conn, 1).unwrap(), vec![14, 7]);
+    assert_eq!(db::notify_upcoming_debts(&conn).unwrap().len(), 1);
+
+    db::set_reminder_days(&conn, 3, &[]).unwrap();
+    assert!(db::get_reminder_days(&conn, 3).unwrap().is_empty());
+    conn.execute("UPDATE debts SET due_date = date('now', '+3 days') WHERE id = 101", []).unwrap();
+    assert!(db::notify_upcoming_debts(&conn).unwrap().is_empty());
+
+    assert_eq!(db::set_reminder_days(&conn, 2, &[0]).unwrap_err(), "Напомнянията може да са от 1 до 30 дни преди срока.");
+    assert_eq!(notification::parse_reminder_days("3, 1, 3").unwrap(), vec![3, 1]);
+    assert_eq!(notification::parse_reminder_days("утре").unwrap_err(), "Невалиден брой дни: утре.");
+}
@@ -0,0 +1,30 @@
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::money::Money;
+use split_money_manager::split::Split;
+
+mod common;
+
+#[test]
+fn edit_expense_after_currency_change() {
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria")]);
+    conn.execute("INSERT INTO groups (id, name, owner_id, currency) VALUES (1, 'gr1', 1, 'BGN')", []).unwrap();
+    conn.execute("INSERT INTO group_members (group_id, user_id) VALUES (1, 1)", []).unwrap();
+    conn.execute("INSERT INTO group_members (group_id, user_id) VALUES (1, 2)", []).unwrap();
+
+    let expense_id = db::add_expenses(&conn, 1, 1, Money::from_major(40), None, "Наем", "2099-01-01", &Split::default()).unwrap();
+    conn.execute("UPDATE debts SET paid = amount, settled = 1", []).unwrap();
+    db::set_group_currency(&conn, 1, 1, "EUR").unwrap();
+    assert_eq!(db::get_expense(&conn, expense_id).unwrap().group_currency(), "BGN");
+
+    // The 40 лв. are reversed as 20.45 € split in two, and the new 20 € share is charged in full.
+    db::edit_expense(&conn, 1, expense_id, Money::from_major(40), None, "Наем", "2099-01-01", &Split::default()).unwrap();
+    let open: Vec<(i32, i32, Money, String)> = conn.prepare("SELECT from_id, to_id, amount, currency FROM debts WHERE settled = 0").unwrap()
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))).unwrap()
+        .collect::<Result<_, _>>().unwrap();
+    assert_eq!(open, vec![(2, 1, Money::from_minor(978), "EUR".to_string())]);
+    assert_eq!(db::get_expense(&conn, expense_id).unwrap().group_currency(), "EUR");
+}
@@ -0,0 +1,60 @@
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::money::Money;
+use split_money_manager::split::Split;
+
+mod common;
+
+fn open_debts(conn: &Connection) -> Vec<(i32, i32, Money)> {
+    conn.prepare("SELECT from_id, to_id, amount FROM debts WHERE settled = 0 ORDER BY from_id, to_id")
+        .unwrap()
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .unwrap()
+        .map(|e| e.unwrap())
+        .collect()
+}
+
+#[test]
+fn edit_and_delete_expense() {
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria"), (3, "Georgi")]);
+    conn.execute("INSERT INTO groups (id, name, owner_id) VALUES (1, 'gr1', 1)", []).unwrap();
+    conn.execute("INSERT INTO group_members (group_id, user_id) VALUES (1, 1)", []).unwrap();
+    conn.execute("INSERT INTO group_members (group_id, user_id) VALUES (1, 2)", []).unwrap();
+    conn.execute("INSERT INTO group_members (group_id, user_id) VALUES (1, 3)", []).unwrap();
+
+    let expense_id = db::add_expenses(&conn, 1, 1, Money::from_major(90), None, "Вечеря", "2099-01-01", &Split::default()).unwrap();
+
+    let error = db::edit_expense(&conn, 2, expense_id, Money::from_major(60), None, "Вечеря", "2099-01-01", &Split::default()).unwrap_err();
+    assert_eq!(error, "Само платилият или администратор на групата може да променя разхода.");
+
+    db::edit_expense(&conn, 1, expense_id, Money::from_major(60), None, "Вечеря", "2099-01-01", &Split::default()).unwrap();
+    assert_eq!(open_debts(&conn), vec![
+        (2, 1, Money::from_major(20)),
+        (3, 1, Money::from_major(20)),
+    ]);
+    assert_eq!(db::get_expense(&conn, expense_id).unwrap().amount(), Money::from_major(60));
+
+    let debt_id: i32 = conn.query_row("SELECT id FROM debts WHERE from_id = 3 AND settled = 0", [], |row| row.get(0)).unwrap();
+    db::payment_confirmation(&conn, 3, debt_id).unwrap();
+    db::payment_confirmation(&conn, 1, debt_id).unwrap();
+
+    // Georgi has paid and left, but can still be kept on the expense.
+    db::leave_group(&conn, 3, 1).unwrap();
+    let error = db::edit_expense(&conn, 1, expense_id, Money::from_major(60), None, "Вечеря", "2099-01-01", &Split::equal(vec![1, 2, 3, 4])).unwrap_err();
+    assert_eq!(error, "Участникът не е член на групата.");
+    db::edit_expense(&conn, 1, expense_id, Money::from_major(60), None, "Вечеря", "2099-01-01", &Split::equal(vec![1, 2, 3])).unwrap();
+    assert_eq!(open_debts(&conn), vec![
+        (2, 1, Money::from_major(20)),
+    ]);
+
+    db::delete_expense(&conn, 1, expense_id).unwrap();
+    assert_eq!(open_debts(&conn), vec![
+        (1, 3, Money::from_major(20)),
+    ]);
+
+    assert!(db::get_group_expenses(&conn, 1, 1).unwrap().is_empty());
+    assert_eq!(db::get_expense(&conn, expense_id).unwrap_err(), "Разходът не е намерен.");
+}
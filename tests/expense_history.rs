@@ -0,0 +1,47 @@
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::money::Money;
+use split_money_manager::split::{Split, SplitMode};
+
+mod common;
+
+#[test]
+fn expense_history() {
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria")]);
+    conn.execute("INSERT INTO groups (id, name, owner_id) VALUES (1, 'gr1', 1)", []).unwrap();
+    conn.execute("INSERT INTO group_members (group_id, user_id) VALUES (1, 1)", []).unwrap();
+    conn.execute("INSERT INTO group_members (group_id, user_id) VALUES (1, 2)", []).unwrap();
+
+    let dinner = db::add_expenses(&conn, 1, 1, Money::from_major(100), None, "Вечеря", "2026-01-01", &Split::default()).unwrap();
+    let taxi = db::add_expenses(&conn, 2, 1, Money::from_major(40), None, "Такси", "2026-02-01", &Split::shares(vec![(1, 1), (2, 1)])).unwrap();
+
+    let (amount, description): (Money, String) = conn.query_row(
+        "SELECT amount, description FROM debts WHERE from_id = 2 AND to_id = 1 AND settled = 0",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).unwrap();
+    assert_eq!(amount, Money::from_major(30));
+    assert_eq!(description, "Вечеря");
+
+    let links: Vec<i32> = conn
+        .prepare("SELECT expense_id FROM debt_expenses ORDER BY expense_id")
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .map(|e| e.unwrap())
+        .collect();
+    assert_eq!(links, vec![dinner, taxi]);
+
+    let history = db::get_group_expenses(&conn, 1, 1).unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].payer_name(), "Ivan");
+    assert_eq!(history[0].amount(), Money::from_major(100));
+    assert_eq!(history[0].split_mode(), SplitMode::Equal);
+    assert_eq!(history[1].description(), "Такси");
+    assert_eq!(history[1].split(), Split::shares(vec![(1, 1), (2, 1)]));
+    assert_eq!(history[1].shares()[0].username(), "Ivan");
+    assert_eq!(history[1].shares()[0].amount(), Money::from_major(20));
+}
@@ -0,0 +1,37 @@
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::money::Money;
+use split_money_manager::split::Split;
+
+mod common;
+
+#[test]
+fn expense_participants() {
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria"), (3, "Georgi"), (4, "Petar")]);
+    conn.execute("INSERT INTO groups (id, name, owner_id) VALUES (1, 'gr1', 1)", []).unwrap();
+
+    for user_id in 1..=4 {
+        conn.execute("INSERT INTO group_members (group_id, user_id) VALUES (1, ?1)", [user_id]).unwrap();
+    }
+
+    db::add_expenses(&conn, 1, 1, Money::from_major(90), None, "Вечеря", "2026-01-01", &Split::equal(vec![1, 2, 3])).unwrap();
+    db::add_expenses(&conn, 4, 1, Money::from_major(20), None, "Такси", "2026-01-01", &Split::equal(vec![2, 3])).unwrap();
+
+    let debts: Vec<(i32, i32, Money)> = conn
+        .prepare("SELECT from_id, to_id, amount FROM debts ORDER BY from_id, to_id")
+        .unwrap()
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .unwrap()
+        .map(|e| e.unwrap())
+        .collect();
+
+    assert_eq!(debts, vec![
+        (2, 1, Money::from_major(30)),
+        (2, 4, Money::from_major(10)),
+        (3, 1, Money::from_major(30)),
+        (3, 4, Money::from_major(10)),
+    ]);
+}
@@ -0,0 +1,48 @@
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::group::GroupActivity;
+use split_money_manager::money::Money;
+use split_money_manager::payment::PaymentMethod;
+use split_money_manager::split::Split;
+
+mod common;
+
+#[test]
+fn group_detail() {
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria"), (3, "Georgi"), (4, "Petar")]);
+    let group_id = db::create_group(&conn, "Flat", 1, &[1, 2, 3]).unwrap();
+
+    let rent = db::add_expenses(&conn, 1, group_id, Money::from_major(90), None, "Наем", "2099-01-01", &Split::default()).unwrap();
+    let debt_id: i32 = conn.query_row("SELECT id FROM debts WHERE from_id = 2", [], |row| row.get(0)).unwrap();
+    db::record_partial_payment(&conn, 2, debt_id, Money::from_major(10), PaymentMethod::Cash, "2026-01-10").unwrap();
+    let payment_id = db::get_debt_payments(&conn, debt_id).unwrap()[0].id();
+    db::confirm_partial_payment(&conn, 1, payment_id).unwrap();
+    let food = db::add_expenses(&conn, 2, group_id, Money::from_major(30), None, "Храна", "2099-01-01", &Split::default()).unwrap();
+
+    conn.execute("UPDATE expenses SET created_at = '2026-01-01 10:00:00' WHERE id = ?1", [rent]).unwrap();
+    conn.execute("UPDATE payments SET created_at = '2026-01-02 10:00:00' WHERE id = ?1", [payment_id]).unwrap();
+    conn.execute("UPDATE expenses SET created_at = '2026-01-03 10:00:00' WHERE id = ?1", [food]).unwrap();
+
+    assert_eq!(db::get_group_detail(&conn, 4, group_id).unwrap_err(), "Не сте член на тази група.");
+
+    let detail = db::get_group_detail(&conn, 2, group_id).unwrap();
+    assert_eq!(detail.group().groupname(), "Flat");
+    assert_eq!(detail.members().len(), 3);
+    assert_eq!(detail.balance_of(1), Money::from_major(40));
+    assert_eq!(detail.balance_of(2), Money::from_major(0));
+    assert_eq!(detail.balance_of(3), Money::from_major(-40));
+
+    let history: Vec<String> = detail.history().iter().map(|activity| match activity {
+        GroupActivity::Expense(expense) => expense.description().to_string(),
+        GroupActivity::Payment(payment) => format!("{} -> {}", payment.from_name(), payment.to_name()),
+    }).collect();
+    assert_eq!(history, vec!["Наем", "Maria -> Ivan", "Храна"]);
+
+    assert_eq!(detail.settlement().len(), 1);
+    assert_eq!(detail.settlement()[0].from_id(), 3);
+    assert_eq!(detail.settlement()[0].to_id(), 1);
+    assert_eq!(detail.settlement()[0].amount(), Money::from_major(40));
+}
@@ -0,0 +1,61 @@
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::notification::NotificationKind;
+
+mod common;
+
+#[test]
+fn group_invitations() {
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria"), (3, "Georgi"), (4, "Petar")]);
+    let group_id = db::create_group(&conn, "Flat", 1, &[1]).unwrap();
+    let members = || -> Vec<i32> {
+        db::get_group_members(&conn, 1, group_id).unwrap().iter().map(|user| user.id()).collect()
+    };
+
+    assert_eq!(db::invite_to_group(&conn, 1, group_id, 2).unwrap(), "Изпратихте покана на Maria.");
+    assert_eq!(db::invite_to_group(&conn, 1, group_id, 2).unwrap_err(), "Maria вече има покана за тази група.");
+    db::invite_to_group(&conn, 1, group_id, 3).unwrap();
+    assert_eq!(members(), vec![1]);
+
+    let note = &db::get_user_notifications(&conn, 2).unwrap()[0];
+    assert_eq!(note.kind(), NotificationKind::GroupInvitation);
+    assert_eq!(note.message(), "Ivan ви кани в група Flat");
+
+    let invitations = db::get_pending_invitations(&conn, 2).unwrap();
+    assert_eq!(invitations.len(), 1);
+    assert_eq!(invitations[0].group_name(), "Flat");
+    assert_eq!(invitations[0].inviter(), "Ivan");
+    assert_eq!(db::respond_to_invitation(&conn, 3, invitations[0].id(), true).unwrap_err(), "Поканата не е намерена.");
+    assert_eq!(db::respond_to_invitation(&conn, 2, invitations[0].id(), true).unwrap(), "Присъединихте се към група Flat.");
+    assert_eq!(db::respond_to_invitation(&conn, 2, invitations[0].id(), false).unwrap_err(), "Вече отговорихте на тази покана.");
+
+    let declined = db::get_pending_invitations(&conn, 3).unwrap()[0].id();
+    assert_eq!(db::respond_to_invitation(&conn, 3, declined, false).unwrap(), "Отказахте поканата за група Flat.");
+    assert!(db::get_pending_invitations(&conn, 3).unwrap().is_empty());
+    assert_eq!(members(), vec![1, 2]);
+    assert_eq!(db::get_user_notifications(&conn, 1).unwrap()[0].message(), "Georgi отказа поканата за група Flat");
+
+    assert_eq!(db::create_invite_code(&conn, 2, group_id).unwrap_err(), "Само собственикът или администратор може да кани членове.");
+    let code = db::create_invite_code(&conn, 1, group_id).unwrap();
+    assert_eq!(code.len(), invitation::INVITE_CODE_LENGTH);
+    assert_eq!(db::join_group_with_code(&conn, 4, "abc").unwrap_err(), "Невалиден код за покана: abc.");
+    assert_eq!(db::join_group_with_code(&conn, 2, &code).unwrap_err(), "Вече сте член на тази група.");
+    assert_eq!(db::join_group_with_code(&conn, 4, &code.to_lowercase()).unwrap(), "Присъединихте се към група Flat.");
+    assert_eq!(db::join_group_with_code(&conn, 3, &code).unwrap_err(), "Кодът за покана вече е използван.");
+    assert_eq!(members(), vec![1, 2, 4]);
+
+    let expired = db::create_invite_code(&conn, 1, group_id).unwrap();
+    conn.execute("UPDATE group_invite_codes SET expires_at = datetime('now', '-1 minute') WHERE code = ?1", [&expired]).unwrap();
+    assert_eq!(db::join_group_with_code(&conn, 3, &expired).unwrap_err(), "Кодът за покана е изтекъл.");
+
+    let groups = || -> i64 { conn.query_row("SELECT COUNT(*) FROM groups", [], |row| row.get(0)).unwrap() };
+    assert_eq!(db::create_group_with_invites(&conn, "Trip", 1, "XYZ", &[2]).unwrap_err(), "Няма зареден курс за XYZ.");
+    assert!(db::create_group_with_invites(&conn, "Trip", 1, "EUR", &[2, 99]).is_err());
+    assert_eq!(groups(), 1);
+    let trip = db::create_group_with_invites(&conn, "Trip", 1, "eur", &[1, 2, 3]).unwrap();
+    assert_eq!(db::get_group_currency(&conn, trip).unwrap(), "EUR");
+    assert_eq!(db::get_pending_invitations(&conn, 3).unwrap()[0].group_name(), "Trip");
+}
@@ -0,0 +1,56 @@
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::money::Money;
+use split_money_manager::payment::PaymentMethod;
+use split_money_manager::split::Split;
+
+mod common;
+
+#[test]
+fn group_lifecycle() {
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria")]);
+    let group_id = db::create_group(&conn, "Trip", 1, &[1, 2]).unwrap();
+    db::add_expenses(&conn, 1, group_id, Money::from_major(20), None, "Бензин", "2099-01-01", &Split::default()).unwrap();
+
+    assert_eq!(db::rename_group(&conn, 2, group_id, "Море").unwrap_err(), "Само собственикът или администратор може да променя групата.");
+    assert_eq!(db::rename_group(&conn, 1, group_id, "  ").unwrap_err(), "Името на групата не може да е празно.");
+    db::rename_group(&conn, 1, group_id, " Море 2026 ").unwrap();
+    assert_eq!(db::get_user_groups(&conn, 2).unwrap()[0].groupname(), "Море 2026");
+
+    assert_eq!(
+        db::set_group_archived(&conn, 1, group_id, true).unwrap_err(),
+        "Групата може да бъде архивирана само когато всички дългове са уредени."
+    );
+    let debt_id: i32 = conn.query_row("SELECT id FROM debts WHERE from_id = 2", [], |row| row.get(0)).unwrap();
+    db::record_partial_payment(&conn, 2, debt_id, Money::from_major(10), PaymentMethod::Cash, "2026-01-10").unwrap();
+    let payment_id = db::get_debt_payments(&conn, debt_id).unwrap()[0].id();
+    db::confirm_partial_payment(&conn, 1, payment_id).unwrap();
+
+    db::set_group_archived(&conn, 1, group_id, true).unwrap();
+    assert_eq!(db::get_user_groups(&conn, 1).unwrap_err(), "Нямате групи!");
+    let archived = db::get_archived_groups(&conn, 1).unwrap();
+    assert_eq!(archived.len(), 1);
+    assert!(archived[0].is_archived());
+    assert!(db::get_group_detail(&conn, 2, group_id).unwrap().group().is_archived());
+    assert_eq!(
+        db::add_expenses(&conn, 1, group_id, Money::from_major(20), None, "Бензин", "2099-01-01", &Split::default()).unwrap_err(),
+        "Групата е архивирана. Възстановете я, за да променяте разходите."
+    );
+
+    assert_eq!(db::delete_group(&conn, 2, group_id).unwrap_err(), "Само собственикът може да изтрие групата.");
+    assert_eq!(
+        db::delete_group(&conn, 1, group_id).unwrap_err(),
+        "Групата може да бъде изтрита само когато в нея не са останали други членове."
+    );
+    db::leave_group(&conn, 2, group_id).unwrap();
+    db::delete_group(&conn, 1, group_id).unwrap();
+
+    assert!(db::get_archived_groups(&conn, 1).unwrap().is_empty());
+    for table in ["groups", "group_members", "debts", "expenses", "payments", "notifications"] {
+        let count: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 0, "{}", table);
+    }
+}
@@ -0,0 +1,92 @@
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::money::Money;
+use split_money_manager::split::Split;
+
+mod common;
+
+#[test]
+fn group_membership() {
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria"), (3, "Georgi"), (4, "Petar")]);
+    let group_id = db::create_group(&conn, "Flat", 1, &[1, 2]).unwrap();
+    let members = |group_id: i32| -> Vec<i32> {
+        db::get_group_members(&conn, 3, group_id).unwrap().iter().map(|user| user.id()).collect()
+    };
+
+    assert_eq!(db::invite_to_group(&conn, 3, group_id, 4).unwrap_err(), "Не сте член на тази група.");
+    assert_eq!(db::invite_to_group(&conn, 2, group_id, 3).unwrap_err(), "Само собственикът или администратор може да кани членове.");
+    assert_eq!(db::invite_to_group(&conn, 1, group_id, 3).unwrap(), "Изпратихте покана на Georgi.");
+    let invitation_id = db::get_pending_invitations(&conn, 3).unwrap()[0].id();
+    db::respond_to_invitation(&conn, 3, invitation_id, true).unwrap();
+    assert_eq!(db::invite_to_group(&conn, 1, group_id, 3).unwrap_err(), "Georgi вече е в групата.");
+    assert_eq!(members(group_id), vec![3, 1, 2]);
+
+    db::add_expenses(&conn, 1, group_id, Money::from_major(30), None, "Наем", "2099-01-01", &Split::default()).unwrap();
+
+    assert_eq!(
+        db::leave_group(&conn, 2, group_id).unwrap_err(),
+        "Maria има неуредени дългове в групата. Те трябва да бъдат изплатени или прехвърлени на друг член."
+    );
+    assert_eq!(db::remove_group_member(&conn, 2, group_id, 1).unwrap_err(), "Собственикът на групата не може да бъде премахнат.");
+
+    assert!(db::request_debt_transfer(&conn, 2, group_id, 1).is_err());
+    assert_eq!(db::request_debt_transfer(&conn, 2, group_id, 4).unwrap_err(), "Дълговете могат да бъдат прехвърлени само на член на групата.");
+    assert_eq!(db::request_debt_transfer(&conn, 2, group_id, 3).unwrap(), "Изпратихте молба за прехвърляне на Georgi.");
+    assert!(db::request_debt_transfer(&conn, 2, group_id, 3).is_err());
+    let declined = db::get_pending_debt_transfers(&conn, 3).unwrap()[0].id();
+    assert_eq!(db::respond_to_debt_transfer(&conn, 1, declined, true).unwrap_err(), "Молбата за прехвърляне не е намерена.");
+    assert_eq!(db::respond_to_debt_transfer(&conn, 3, declined, false).unwrap(), "Отказахте да поемете дълговете на Maria в група Flat.");
+    assert_eq!(db::get_user_notifications(&conn, 2).unwrap()[0].message(), "Georgi отказа да поеме дълговете ви в група Flat");
+
+    let open = || -> Vec<(i32, i32, Money)> {
+        conn.prepare("SELECT from_id, to_id, amount FROM debts WHERE group_id = ?1 AND settled = 0 ORDER BY from_id").unwrap()
+            .query_map([group_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))).unwrap()
+            .collect::<Result<_, _>>().unwrap()
+    };
+    assert_eq!(open(), vec![(2, 1, Money::from_major(10)), (3, 1, Money::from_major(10))]);
+
+    // Georgi already owes Ivan, so taking over Maria's debt adds to it.
+    db::request_debt_transfer(&conn, 2, group_id, 3).unwrap();
+    let accepted = db::get_pending_debt_transfers(&conn, 3).unwrap()[0].id();
+    assert_eq!(db::respond_to_debt_transfer(&conn, 3, accepted, true).unwrap(), "Поехте дълговете на Maria в група Flat.");
+    assert_eq!(db::respond_to_debt_transfer(&conn, 3, accepted, true).unwrap_err(), "Вече отговорихте на тази молба.");
+    assert_eq!(open(), vec![(3, 1, Money::from_major(20))]);
+    let maria_debt: (Money, bool, bool, i32) = conn.query_row(
+        "SELECT amount, settled, netted, transfer_id FROM debts WHERE from_id = 2 AND group_id = ?1",
+        [group_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    ).unwrap();
+    assert_eq!(maria_debt, (Money::from_major(10), true, true, accepted));
+    let expenses_of_georgi_debt: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM debt_expenses de JOIN debts d ON d.id = de.debt_id
+         JOIN debt_replacements r ON r.replaced_by = d.id
+         WHERE d.from_id = 3 AND d.settled = 0 AND r.debt_id IN (SELECT id FROM debts WHERE from_id = 2 AND group_id = ?1)",
+        [group_id],
+        |row| row.get(0),
+    ).unwrap();
+    assert_eq!(expenses_of_georgi_debt, 1);
+
+    // Ivan owes Georgi in this group, so Maria's debt to Ivan nets against it.
+    let trip = db::create_group(&conn, "Trip", 1, &[1, 2, 3]).unwrap();
+    for (from_id, to_id, amount) in [(1, 3, 400), (2, 1, 1000)] {
+        conn.execute(
+            "INSERT INTO debts (from_id, to_id, amount, group_id, due_date, description, currency) VALUES (?1, ?2, ?3, ?4, '2099-01-01', 'Хижа', 'EUR')",
+            [from_id, to_id, amount, trip],
+        ).unwrap();
+    }
+    db::request_debt_transfer(&conn, 2, trip, 3).unwrap();
+    let netted = db::get_pending_debt_transfers(&conn, 3).unwrap()[0].id();
+    db::respond_to_debt_transfer(&conn, 3, netted, true).unwrap();
+    let trip_debts: Vec<(i32, i32, Money)> = conn.prepare("SELECT from_id, to_id, amount FROM debts WHERE group_id = ?1 AND settled = 0").unwrap()
+        .query_map([trip], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))).unwrap()
+        .collect::<Result<_, _>>().unwrap();
+    assert_eq!(trip_debts, vec![(3, 1, Money::from_major(6))]);
+
+    assert_eq!(db::leave_group(&conn, 2, group_id).unwrap(), "Напуснахте групата.");
+    assert_eq!(members(group_id), vec![3, 1]);
+    assert!(db::remove_group_member(&conn, 1, group_id, 3).is_err());
+    assert_eq!(db::remove_group_member(&conn, 1, group_id, 2).unwrap_err(), "Потребителят не е член на тази група.");
+}
@@ -0,0 +1,50 @@
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::group::GroupRole;
+use split_money_manager::money::Money;
+use split_money_manager::split::Split;
+
+mod common;
+
+#[test]
+fn group_roles() {
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria"), (3, "Georgi"), (4, "Petar")]);
+    let group_id = db::create_group(&conn, "Flat", 1, &[1, 2, 3]).unwrap();
+
+    assert_eq!(db::get_group_roles(&conn, 1, group_id).unwrap(), vec![
+        (1, GroupRole::Owner),
+        (2, GroupRole::Member),
+        (3, GroupRole::Member),
+    ]);
+    assert_eq!(db::add_expenses(&conn, 4, group_id, Money::from_major(30), None, "Наем", "2099-01-01", &Split::default()).unwrap_err(), "Не сте член на тази група.");
+    let expense_id = db::add_expenses(&conn, 3, group_id, Money::from_major(30), None, "Наем", "2099-01-01", &Split::default()).unwrap();
+
+    assert_eq!(db::set_member_role(&conn, 2, group_id, 3, GroupRole::Admin).unwrap_err(), "Само собственикът може да променя ролите.");
+    assert_eq!(db::set_group_currency(&conn, 2, group_id, "EUR").unwrap_err(), "Само собственикът или администратор може да променя групата.");
+    assert!(db::delete_expense(&conn, 2, expense_id).is_err());
+
+    db::set_member_role(&conn, 1, group_id, 2, GroupRole::Admin).unwrap();
+    assert_eq!(db::get_user_groups(&conn, 2).unwrap()[0].role(), GroupRole::Admin);
+    db::edit_expense(&conn, 2, expense_id, Money::from_major(60), None, "Наем", "2099-01-01", &Split::default()).unwrap();
+    db::invite_to_group(&conn, 2, group_id, 4).unwrap();
+    let invitation_id = db::get_pending_invitations(&conn, 4).unwrap()[0].id();
+    db::respond_to_invitation(&conn, 4, invitation_id, true).unwrap();
+
+    assert_eq!(db::leave_group(&conn, 1, group_id).unwrap_err(), "Прехвърлете собствеността на друг член, преди да напуснете групата.");
+    assert_eq!(db::transfer_group_ownership(&conn, 2, group_id, 3).unwrap_err(), "Само собственикът може да прехвърли групата.");
+    db::transfer_group_ownership(&conn, 1, group_id, 3).unwrap();
+    assert_eq!(db::get_group_roles(&conn, 1, group_id).unwrap(), vec![
+        (1, GroupRole::Admin),
+        (2, GroupRole::Admin),
+        (3, GroupRole::Owner),
+        (4, GroupRole::Member),
+    ]);
+    assert_eq!(db::get_user_groups(&conn, 3).unwrap()[0].role(), GroupRole::Owner);
+
+    assert_eq!(db::remove_group_member(&conn, 2, group_id, 1).unwrap_err(), "Само собственикът може да премахне администратор.");
+    assert_eq!(db::remove_group_member(&conn, 4, group_id, 2).unwrap_err(), "Само собственикът или администратор може да премахва членове.");
+    assert_eq!(db::remove_group_member(&conn, 2, group_id, 4).unwrap(), "Премахнахте Petar от групата.");
+}
@@ -0,0 +1,39 @@
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::money::Money;
+
+#[test]
+fn money_parse_and_migration() {
+    assert_eq!(Money::parse("12").unwrap(), Money::from_minor(1200));
+    assert_eq!(Money::parse("12.5").unwrap(), Money::from_minor(1250));
+    assert_eq!(Money::parse("12,05").unwrap(), Money::from_minor(1205));
+    assert_eq!(Money::parse("12.345").unwrap_err(), "Сумата може да има най-много два знака след десетичната запетая.");
+    assert_eq!(Money::parse("abc").unwrap_err(), "Невалидна сума.");
+    assert_eq!(Money::from_minor(-5).to_string(), "-0.05");
+    assert_eq!(Money::from_minor(123456).to_string(), "1234.56");
+
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch("
+        CREATE TABLE debts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            from_id INTEGER NOT NULL,
+            to_id INTEGER NOT NULL,
+            amount REAL NOT NULL,
+            group_id INTEGER NOT NULL,
+            due_date TEXT NOT NULL,
+            description TEXT NOT NULL,
+            confirmed_by_debtor BOOLEAN DEFAULT 0,
+            confirmed_by_creditor BOOLEAN DEFAULT 0,
+            settled BOOLEAN DEFAULT 0
+        );
+    ").unwrap();
+
+    conn.execute("INSERT INTO debts (from_id, to_id, amount, group_id, due_date, description) VALUES (1, 2, 33.333333, 1, '2026-01-01', 'Вечеря')", []).unwrap();
+
+    db::init_schema(&conn).unwrap();
+    db::init_schema(&conn).unwrap();
+
+    let amount: Money = conn.query_row("SELECT amount FROM debts WHERE id = 1", [], |row| row.get(0)).unwrap();
+    let original: Money = conn.query_row("SELECT original_amount FROM currency_conversions WHERE row_id = 1", [], |row| row.get(0)).unwrap();
+    assert_eq!((original, amount), (Money::from_minor(3333), Money::from_minor(1704)));
+}
@@ -0,0 +1,103 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::thread;
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::delivery::{Channel, DeliveryChannel, EmailChannel, InAppChannel, WebhookChannel};
+
+mod common;
+
+#[test]
+fn notification_delivery() {
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria"), (3, "Georgi")]);
+
+    let smtp = TcpListener::bind("127.0.0.1:0").unwrap();
+    let smtp_port = smtp.local_addr().unwrap().port();
+    let mail = thread::spawn(move || {
+        let (stream, _) = smtp.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut writer = stream;
+        let mut transcript = String::new();
+        writer.write_all(b"220 test\r\n").unwrap();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            transcript.push_str(&line);
+            let reply: &[u8] = match line.trim_end() {
+                "DATA" => b"354 go on\r\n",
+                "." => b"250 queued\r\n",
+                "QUIT" => { writer.write_all(b"221 bye\r\n").unwrap(); break; }
+                command if command.starts_with("HELO") || command.starts_with("MAIL") || command.starts_with("RCPT") => b"250 ok\r\n",
+                _ => continue,
+            };
+            writer.write_all(reply).unwrap();
+        }
+        transcript
+    });
+
+    let http = TcpListener::bind("127.0.0.1:0").unwrap();
+    let webhook_url = format!("http://127.0.0.1:{}/hooks/split", http.local_addr().unwrap().port());
+    let webhook = thread::spawn(move || {
+        let (mut stream, _) = http.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buffer = [0; 1024];
+        while !String::from_utf8_lossy(&request).contains("\"created_at\"") {
+            let read = stream.read(&mut buffer).unwrap();
+            request.extend_from_slice(&buffer[..read]);
+        }
+        stream.write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n").unwrap();
+        String::from_utf8(request).unwrap()
+    });
+
+    assert!(!db::get_notification_channels(&conn, 2).unwrap()[1].is_enabled());
+    assert_eq!(db::get_notification_channels(&conn, 2).unwrap()[1].target(), "maria@example.com");
+    db::set_notification_channel(&conn, 2, Channel::Email, true, "maria@example.com").unwrap();
+    db::set_notification_channel(&conn, 3, Channel::Webhook, true, &webhook_url).unwrap();
+    assert_eq!(db::set_notification_channel(&conn, 3, Channel::InApp, false, "").unwrap_err(), "Известията в приложението не могат да се изключат.");
+    assert_eq!(db::set_notification_channel(&conn, 3, Channel::Email, true, "не-имейл").unwrap_err(), "Невалиден имейл.");
+    assert!(db::set_notification_channel(&conn, 3, Channel::Webhook, true, "ftp://example.com").is_err());
+
+    db::create_group(&conn, "Flat", 1, &[1, 2, 3]).unwrap();
+
+    let channels: Vec<Box<dyn DeliveryChannel>> = vec![
+        Box::new(InAppChannel),
+        Box::new(EmailChannel::new("127.0.0.1".to_string(), smtp_port, "app@example.com".to_string())),
+        Box::new(WebhookChannel),
+    ];
+    assert_eq!(db::deliver_notifications(&conn, &channels).unwrap(), 2);
+    assert_eq!(db::deliver_notifications(&conn, &channels).unwrap(), 0);
+
+    let transcript = mail.join().unwrap();
+    assert!(transcript.contains("RCPT TO:<maria@example.com>"));
+    assert!(transcript.contains("Добавени сте в група Flat"));
+
+    let request = webhook.join().unwrap();
+    assert!(request.starts_with("POST /hooks/split HTTP/1.1"));
+    assert!(request.contains("\"username\":\"Georgi\",\"kind\":\"added_to_group\",\"message\":\"Добавени сте в група Flat\""));
+
+    let closed = TcpListener::bind("127.0.0.1:0").unwrap();
+    let closed_url = format!("http://127.0.0.1:{}/", closed.local_addr().unwrap().port());
+    drop(closed);
+    db::set_notification_channel(&conn, 3, Channel::Webhook, true, &closed_url).unwrap();
+    db::create_group(&conn, "Trip", 1, &[1, 3]).unwrap();
+
+    assert_eq!(db::deliver_notifications(&conn, &channels).unwrap(), 0);
+    let (attempts, failed): (i64, bool) = conn.query_row(
+        "SELECT attempts, last_error IS NOT NULL AND next_attempt_at > CURRENT_TIMESTAMP
+         FROM notification_deliveries WHERE target = ?1",
+        [&closed_url],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).unwrap();
+    assert_eq!((attempts, failed), (1, true));
+    assert_eq!(db::deliver_notifications(&conn, &channels).unwrap(), 0);
+
+    conn.execute("UPDATE notification_deliveries SET next_attempt_at = datetime('now', '-1 minute')", []).unwrap();
+    db::deliver_notifications(&conn, &channels).unwrap();
+    let attempts: i64 = conn.query_row("SELECT attempts FROM notification_deliveries WHERE target = ?1", [&closed_url], |row| row.get(0)).unwrap();
+    assert_eq!(attempts, 2);
+    assert_eq!(delivery::retry_delay(1), 60);
+    assert_eq!(delivery::retry_delay(2), 120);
+}
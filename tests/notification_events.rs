@@ -0,0 +1,45 @@
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::money::Money;
+use split_money_manager::split::Split;
+
+mod common;
+
+#[test]
+fn notifications_once_per_event() {
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria"), (3, "Georgi")]);
+    let group_id = db::create_group(&conn, "Flat", 1, &[1, 2, 3]).unwrap();
+
+    let expense_id = db::add_expenses(&conn, 1, group_id, Money::from_major(30), None, "Вечеря", "2099-01-01", &Split::default()).unwrap();
+    db::edit_expense(&conn, 1, expense_id, Money::from_major(36), None, "Вечеря", "2099-01-01", &Split::default()).unwrap();
+
+    conn.execute(
+        "INSERT INTO debts (id, from_id, to_id, amount, group_id, due_date, description, currency)
+         VALUES (100, 2, 3, 500, ?1, '2025-01-01', 'Такси', 'EUR')",
+        [group_id],
+    ).unwrap();
+    assert_eq!(db::notify_overdue_debts(&conn).unwrap().len(), 1);
+    assert!(db::notify_overdue_debts(&conn).unwrap().is_empty());
+
+    db::payment_confirmation(&conn, 3, 100).unwrap();
+
+    let messages: Vec<String> = db::get_user_notifications(&conn, 2).unwrap()
+        .iter()
+        .map(|note| note.message().to_string())
+        .collect();
+    assert_eq!(messages, vec![
+        "Georgi потвърди плащане от 5.00 €",
+        "Имате просрочен дълг от 5.00 € със срок 2025-01-01",
+        "Ivan добави разход „Вечеря“ от 30.00 € в група Flat",
+        "Добавени сте в група Flat",
+    ]);
+    assert_eq!(db::get_user_notifications(&conn, 2).unwrap().len(), 4);
+    assert_eq!(db::get_user_notifications(&conn, 2).unwrap()[0].group_id(), Some(group_id));
+    assert!(db::get_user_notifications(&conn, 1).is_err());
+
+    let kept: i32 = conn.query_row("SELECT COUNT(*) FROM notifications WHERE user_id = 2", [], |row| row.get(0)).unwrap();
+    assert_eq!(kept, 4);
+}
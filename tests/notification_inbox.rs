@@ -0,0 +1,48 @@
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::money::Money;
+use split_money_manager::notification::NotificationKind;
+use split_money_manager::split::Split;
+
+mod common;
+
+#[test]
+fn notification_inbox() {
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria")]);
+    let group_id = db::create_group(&conn, "Flat", 1, &[1, 2]).unwrap();
+    db::add_expenses(&conn, 1, group_id, Money::from_major(20), None, "Хляб", "2099-01-01", &Split::default()).unwrap();
+    db::add_expenses(&conn, 1, group_id, Money::from_major(40), None, "Ток", "2099-01-01", &Split::default()).unwrap();
+
+    assert_eq!(db::count_unread_notifications(&conn, 2).unwrap(), 3);
+
+    let inbox = db::get_user_notifications(&conn, 2).unwrap();
+    assert_eq!(inbox.len(), 3);
+    assert_eq!(inbox[0].kind(), NotificationKind::ExpenseAdded);
+    assert_eq!(inbox[0].group_id(), Some(group_id));
+    assert_eq!(inbox[2].kind(), NotificationKind::AddedToGroup);
+    assert!(inbox.iter().all(|note| !note.is_read()));
+
+    db::set_notification_read(&conn, 2, inbox[0].id(), true).unwrap();
+    assert_eq!(db::count_unread_notifications(&conn, 2).unwrap(), 2);
+    db::set_notification_read(&conn, 2, inbox[0].id(), false).unwrap();
+    assert_eq!(db::count_unread_notifications(&conn, 2).unwrap(), 3);
+    assert_eq!(db::set_notification_read(&conn, 1, inbox[0].id(), true).unwrap_err(), "Известието не е намерено.");
+
+    db::set_notification_archived(&conn, 2, inbox[2].id(), true).unwrap();
+    assert_eq!(db::get_user_notifications(&conn, 2).unwrap().len(), 2);
+    let archived = db::get_archived_notifications(&conn, 2).unwrap();
+    assert_eq!(archived.len(), 1);
+    assert!(archived[0].is_read() && archived[0].is_archived());
+    assert_eq!(db::count_unread_notifications(&conn, 2).unwrap(), 2);
+
+    assert_eq!(db::mark_all_notifications_read(&conn, 2).unwrap(), 2);
+    assert_eq!(db::count_unread_notifications(&conn, 2).unwrap(), 0);
+    assert!(db::get_user_notifications(&conn, 2).unwrap().iter().all(|note| note.is_read()));
+
+    db::set_notification_archived(&conn, 2, archived[0].id(), false).unwrap();
+    assert_eq!(db::get_user_notifications(&conn, 2).unwrap().len(), 3);
+    assert_eq!(db::get_archived_notifications(&conn, 2).unwrap_err(), "Нямате архивирани известия.");
+}
@@ -0,0 +1,83 @@
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::delivery::Channel;
+use split_money_manager::money::Money;
+use split_money_manager::notification::{NotificationKind, NotificationPreferences};
+use split_money_manager::split::Split;
+
+mod common;
+
+#[test]
+fn notification_preferences() {
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria"), (3, "Georgi")]);
+    let flat = db::create_group(&conn, "Flat", 1, &[1, 2, 3]).unwrap();
+    let trip = db::create_group(&conn, "Trip", 1, &[1, 2]).unwrap();
+    let add = |group_id: i32, description: &str| {
+        db::add_expenses(&conn, 1, group_id, Money::from_major(30), None, description, "2099-01-01", &Split::default()).unwrap();
+    };
+    let inbox = |user_id: i32| -> Vec<String> {
+        db::get_user_notifications(&conn, user_id).unwrap().iter().map(|note| note.message().to_string()).collect()
+    };
+    assert_eq!(db::get_notification_preferences(&conn, 2).unwrap(), NotificationPreferences::new());
+
+    let muted = NotificationPreferences::new().with_muted_kinds(vec![NotificationKind::ExpenseAdded]);
+    db::set_notification_preferences(&conn, 2, &muted).unwrap();
+    add(flat, "Хляб");
+    assert_eq!(inbox(2).len(), 2);
+    assert_eq!(inbox(3).len(), 2);
+
+    let no_trip = NotificationPreferences::new().with_muted_groups(vec![trip]);
+    assert_eq!(db::set_notification_preferences(&conn, 3, &no_trip).unwrap_err(), "Не сте член на тази група.");
+    db::set_notification_preferences(&conn, 2, &no_trip).unwrap();
+    assert_eq!(db::get_notification_preferences(&conn, 2).unwrap(), no_trip);
+    add(trip, "Бензин");
+    add(flat, "Ток");
+    assert_eq!(inbox(2)[0], "Ivan добави разход „Ток“ от 30.00 € в група Flat");
+    assert_eq!(inbox(2).len(), 3);
+
+    let (from, to): (String, String) = conn.query_row(
+        "SELECT strftime('%H:%M', 'now', 'localtime', '-1 hours'), strftime('%H:%M', 'now', 'localtime', '+1 hours')",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).unwrap();
+    db::set_notification_preferences(&conn, 3, &NotificationPreferences::new().with_quiet_hours(Some((from, to)))).unwrap();
+    db::set_notification_channel(&conn, 3, Channel::Webhook, true, "http://127.0.0.1:9/").unwrap();
+    conn.execute(
+        "INSERT INTO debts (id, from_id, to_id, amount, group_id, due_date, description, currency)
+         VALUES (100, 3, 1, 500, ?1, '2025-01-01', 'Такси', 'EUR')",
+        [flat],
+    ).unwrap();
+    assert!(db::notify_overdue_debts(&conn).unwrap().is_empty());
+    assert_eq!(inbox(3)[0], "Имате просрочен дълг от 5.00 € със срок 2025-01-01");
+    let deferred: bool = conn.query_row(
+        "SELECT next_attempt_at > CURRENT_TIMESTAMP FROM notification_deliveries ORDER BY id DESC LIMIT 1",
+        [],
+        |row| row.get(0),
+    ).unwrap();
+    assert!(deferred);
+
+    db::set_notification_preferences(&conn, 2, &NotificationPreferences::new().with_digest(true).with_digest_time("00:00".to_string())).unwrap();
+    add(flat, "Вода");
+    assert_eq!(db::count_unread_notifications(&conn, 2).unwrap(), 3);
+    assert_eq!(db::send_digests(&conn).unwrap(), vec![
+        (2, "Обобщение за деня (1 известия):\n• Ivan добави разход „Вода“ от 30.00 € в група Flat".to_string()),
+    ]);
+    let notes = db::get_user_notifications(&conn, 2).unwrap();
+    assert_eq!((notes[0].kind(), notes[0].is_read()), (NotificationKind::Digest, false));
+    assert!(notes[1].is_read());
+    assert!(db::send_digests(&conn).unwrap().is_empty());
+
+    add(flat, "Интернет");
+    assert!(db::send_digests(&conn).unwrap().is_empty());
+    assert_eq!(inbox(2).len(), 5);
+    db::set_notification_preferences(&conn, 2, &NotificationPreferences::new()).unwrap();
+    assert_eq!(inbox(2).len(), 6);
+
+    let bad = NotificationPreferences::new().with_quiet_hours(Some(("25:00".to_string(), "07:00".to_string())));
+    assert_eq!(db::set_notification_preferences(&conn, 2, &bad).unwrap_err(), "Невалиден час: 25:00. Използвайте формат ЧЧ:ММ.");
+    let same = NotificationPreferences::new().with_quiet_hours(Some(("22:00".to_string(), "22:00".to_string())));
+    assert!(db::set_notification_preferences(&conn, 2, &same).is_err());
+}
@@ -0,0 +1,66 @@
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::money::Money;
+use split_money_manager::payment::PaymentMethod;
+
+mod common;
+
+#[test]
+fn partial_payments() {
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria")]);
+    conn.execute("INSERT INTO groups (id, name, owner_id) VALUES (1, 'gr1', 1)", []).unwrap();
+    conn.execute("INSERT INTO debts (id, from_id, to_id, amount, group_id, due_date, description) VALUES (1, 1, 2, 10000, 1, '2099-01-01', 'Наем')", []).unwrap();
+
+    assert_eq!(
+        db::record_partial_payment(&conn, 1, 1, Money::from_major(150), PaymentMethod::Cash, "2026-01-10").unwrap_err(),
+        "Плащането надвишава оставащата сума от 100.00 лв."
+    );
+    assert_eq!(
+        db::record_partial_payment(&conn, 1, 1, Money::from_major(40), PaymentMethod::Cash, "10.01.2026").unwrap_err(),
+        "Невалидна дата на плащане. Използвайте формат ГГГГ-ММ-ДД."
+    );
+
+    db::record_partial_payment(&conn, 1, 1, Money::from_major(40), PaymentMethod::Cash, "2026-01-10").unwrap();
+    let debts = db::get_user_debts_or_credits(&conn, 1, true).unwrap();
+    assert_eq!(debts[0].remaining(), Money::from_major(100));
+    assert_eq!(debts[0].payments().len(), 1);
+    assert!(!debts[0].payments()[0].is_confirmed());
+
+    let payment_id = debts[0].payments()[0].id();
+    assert_eq!(db::confirm_partial_payment(&conn, 1, payment_id).unwrap(), "Потвърдено. Очаква се другата страна да потвърди.");
+    assert_eq!(db::confirm_partial_payment(&conn, 2, payment_id).unwrap(), "Плащането е потвърдено. Остават 60.00 лв.");
+
+    let credits = db::get_user_debts_or_credits(&conn, 2, false).unwrap();
+    assert_eq!(credits[0].paid(), Money::from_major(40));
+    assert_eq!(credits[0].remaining(), Money::from_major(60));
+
+    assert_eq!(db::get_counterparty_balances(&conn, 2).unwrap()[0].amount(), Money::from_major(60));
+
+    db::record_partial_payment(&conn, 2, 1, Money::from_major(60), PaymentMethod::BankTransfer, "2026-02-10").unwrap();
+    let payment_id = db::get_user_debts_or_credits(&conn, 1, true).unwrap()[0].payments()[1].id();
+    assert_eq!(db::confirm_partial_payment(&conn, 1, payment_id).unwrap(), "Дългът е напълно изплатен и приключен.");
+
+    let settled: bool = conn.query_row("SELECT settled FROM debts WHERE id = 1", [], |row| row.get(0)).unwrap();
+    assert!(settled);
+
+    conn.execute("INSERT INTO debts (id, from_id, to_id, amount, group_id, due_date, description) VALUES (2, 1, 2, 2000, 1, '2099-01-01', 'Ток')", []).unwrap();
+    db::record_partial_payment(&conn, 1, 2, Money::from_major(5), PaymentMethod::Cash, "2026-03-10").unwrap();
+    assert_eq!(
+        db::payment_confirmation(&conn, 1, 2).unwrap_err(),
+        "Има плащания, които чакат потвърждение. Потвърдете ги, преди да приключите дълга."
+    );
+
+    let payment_id = db::get_debt_payments(&conn, 2).unwrap()[0].id();
+    db::confirm_partial_payment(&conn, 2, payment_id).unwrap();
+    db::payment_confirmation(&conn, 1, 2).unwrap();
+    assert_eq!(db::payment_confirmation(&conn, 2, 2).unwrap(), "Дългът е напълно изплатен и приключен.");
+
+    let payments: Vec<(Money, PaymentMethod)> = db::get_debt_payments(&conn, 2).unwrap()
+        .iter()
+        .map(|p| (p.amount(), p.method()))
+        .collect();
+    assert_eq!(payments, vec![(Money::from_major(5), PaymentMethod::Cash), (Money::from_major(15), PaymentMethod::Other)]);
+}
@@ -1,37 +1,22 @@
 use rusqlite::Connection;
 use split_money_manager::*;
 
+mod common;
+
 #[test]
 
 fn payment_confirmation_on_time_payments_20() {
     let conn = Connection::open_in_memory().unwrap();
 
-    conn.execute_batch("
-        CREATE TABLE users (
-            id INTEGER PRIMARY KEY,
-            username TEXT NOT NULL,
-            on_time_payments INTEGER DEFAULT 0,
-            loyal_payer INTEGER DEFAULT 0
-        );
-
-        CREATE TABLE debts (
-            id INTEGER PRIMARY KEY,
-            from_id INTEGER NOT NULL,
-            to_id INTEGER NOT NULL,
-            amount REAL NOT NULL,
-            due_date TEXT,
-            confirmed_by_debtor INTEGER DEFAULT 0,
-            confirmed_by_creditor INTEGER DEFAULT 0,
-            settled BOOLEAN DEFAULT 0
-        );
-    ").unwrap();
+    db::init_schema(&conn).unwrap();
 
-    conn.execute("INSERT INTO users (id, username, on_time_payments) VALUES (1, 'Ivan', 19)", []).unwrap();
-    conn.execute("INSERT INTO users (id, username) VALUES (2, 'Maria')", []).unwrap();
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria")]);
+    conn.execute("UPDATE users SET on_time_payments = 19 WHERE id = 1", []).unwrap();
+    conn.execute("INSERT INTO groups (id, name, owner_id) VALUES (1, 'gr1', 1)", []).unwrap();
 
     conn.execute(
-        "INSERT INTO debts (id, from_id, to_id, amount, due_date)
-         VALUES (1, 1, 2, 50.0, date('now', '+1 day'))",
+        "INSERT INTO debts (id, from_id, to_id, amount, group_id, due_date, description)
+         VALUES (1, 1, 2, 5000, 1, date('now', '+1 day'), 'Наем')",
         [],
     ).unwrap();
 
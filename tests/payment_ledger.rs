@@ -0,0 +1,46 @@
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::money::Money;
+use split_money_manager::payment::PaymentMethod;
+
+mod common;
+
+#[test]
+fn payment_ledger_history() {
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria"), (3, "Georgi")]);
+    conn.execute("INSERT INTO groups (id, name, owner_id) VALUES (1, 'Flat', 1)", []).unwrap();
+    conn.execute("INSERT INTO groups (id, name, owner_id) VALUES (2, 'Trip', 3)", []).unwrap();
+    for (group_id, user_id) in [(1, 1), (1, 2), (2, 1), (2, 3)] {
+        conn.execute("INSERT INTO group_members (group_id, user_id) VALUES (?1, ?2)", [group_id, user_id]).unwrap();
+    }
+    conn.execute("INSERT INTO debts (id, from_id, to_id, amount, group_id, due_date, description) VALUES (1, 1, 2, 10000, 1, '2099-01-01', 'Наем')", []).unwrap();
+    conn.execute("INSERT INTO debts (id, from_id, to_id, amount, group_id, due_date, description) VALUES (2, 3, 1, 2500, 2, '2099-01-01', 'Бензин')", []).unwrap();
+
+    db::record_partial_payment(&conn, 2, 1, Money::from_major(30), PaymentMethod::BankTransfer, "2026-03-01").unwrap();
+    let payment_id = db::get_group_payments(&conn, 1, 1).unwrap()[0].id();
+    db::confirm_partial_payment(&conn, 1, payment_id).unwrap();
+
+    db::payment_confirmation(&conn, 1, 1).unwrap();
+    assert_eq!(db::payment_confirmation(&conn, 2, 1).unwrap(), "Дългът е напълно изплатен и приключен.");
+
+    let (paid, settled): (Money, bool) = conn.query_row("SELECT paid, settled FROM debts WHERE id = 1", [], |row| Ok((row.get(0)?, row.get(1)?))).unwrap();
+    assert_eq!(paid, Money::from_major(100));
+    assert!(settled);
+
+    let group = db::get_group_payments(&conn, 1, 1).unwrap();
+    assert_eq!(group.len(), 2);
+    assert!(group.iter().all(|payment| payment.is_confirmed()));
+    assert_eq!(group.iter().map(|payment| payment.amount()).sum::<Money>(), Money::from_major(100));
+    let transfer = group.iter().find(|payment| payment.paid_on() == "2026-03-01").unwrap();
+    assert_eq!(transfer.method(), PaymentMethod::BankTransfer);
+    assert_eq!((transfer.from_name(), transfer.to_name(), transfer.group_name()), ("Ivan", "Maria", "Flat"));
+
+    db::record_partial_payment(&conn, 3, 2, Money::from_major(10), PaymentMethod::Cash, "2026-03-05").unwrap();
+    assert_eq!(db::get_user_payments(&conn, 1).unwrap().len(), 3);
+    assert_eq!(db::get_user_payments(&conn, 3).unwrap()[0].method(), PaymentMethod::Cash);
+    assert_eq!(db::get_user_payments(&conn, 2).unwrap().len(), 2);
+    assert_eq!(db::get_group_payments(&conn, 3, 2).unwrap().len(), 1);
+}
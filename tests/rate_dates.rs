@@ -0,0 +1,14 @@
+use split_money_manager::currency;
+
+#[test]
+fn rate_dates_follow_the_calendar() {
+    let rates = currency::parse_ecb_csv("Date,USD\n2024-02-29,1.08\n29 February 2024,1.08\n").unwrap();
+    assert!(rates.iter().all(|r| r.rate_date() == "2024-02-29"));
+
+    for date in ["2024-02-30", "2024-13-01", "2023-02-29", "2024-04-31", "2024-00-10", "31 June 2024", "0 May 2024"] {
+        assert_eq!(
+            currency::parse_ecb_csv(&format!("Date,USD\n{},1.08\n", date)).unwrap_err(),
+            format!("Невалидна дата във файла с курсове: {}.", date),
+        );
+    }
+}
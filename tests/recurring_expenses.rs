@@ -0,0 +1,71 @@
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::money::Money;
+use split_money_manager::recurring::{RecurrenceInterval, RecurringStatus};
+use split_money_manager::split::Split;
+
+mod common;
+
+#[test]
+fn recurring_expenses() {
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria"), (3, "Georgi")]);
+    let group_id = db::create_group(&conn, "Flat", 1, &[1, 2]).unwrap();
+
+    let rent = db::add_recurring_expense(
+        &conn, 1, group_id, Money::from_major(1000), None, "Наем",
+        RecurrenceInterval::Monthly, "2025-01-31", Some("2025-04-30"), &Split::default(),
+    ).unwrap();
+    let internet = db::add_recurring_expense(
+        &conn, 2, group_id, Money::from_major(30), None, "Интернет",
+        RecurrenceInterval::Weekly, "2026-01-01", None, &Split::equal(vec![1, 2]),
+    ).unwrap();
+    let gym = db::add_recurring_expense(
+        &conn, 2, group_id, Money::from_major(50), None, "Фитнес",
+        RecurrenceInterval::Yearly, "2099-01-01", None, &Split::exact(vec![(2, Money::from_major(50))]),
+    ).unwrap();
+
+    assert_eq!(
+        db::add_recurring_expense(&conn, 1, group_id, Money::from_major(10), None, "Ток", RecurrenceInterval::Monthly, "2025-05-01", Some("2025-04-01"), &Split::default()).unwrap_err(),
+        "Невалиден период. Крайната дата трябва да е след началната."
+    );
+    assert_eq!(
+        db::add_recurring_expense(&conn, 3, group_id, Money::from_major(10), None, "Ток", RecurrenceInterval::Monthly, "2025-05-01", None, &Split::default()).unwrap_err(),
+        "Не сте член на тази група."
+    );
+
+    db::set_recurring_status(&conn, 1, internet, RecurringStatus::Paused).unwrap();
+    assert_eq!(db::set_recurring_status(&conn, 3, internet, RecurringStatus::Active).unwrap_err(), "Не сте член на тази група.");
+
+    assert_eq!(db::materialize_recurring_expenses(&conn).unwrap().len(), 4);
+    assert!(db::materialize_recurring_expenses(&conn).unwrap().is_empty());
+
+    let due_dates: Vec<String> = db::get_group_expenses(&conn, 1, group_id).unwrap()
+        .iter()
+        .map(|expense| expense.due_date().to_string())
+        .collect();
+    assert_eq!(due_dates, vec!["2025-01-31", "2025-02-28", "2025-03-31", "2025-04-30"]);
+    let owed: Money = conn.query_row("SELECT SUM(amount) FROM debts WHERE from_id = 2 AND to_id = 1", [], |row| row.get(0)).unwrap();
+    assert_eq!(owed, Money::from_major(2000));
+    assert_eq!(db::get_recurring_expense(&conn, rent).unwrap().status(), RecurringStatus::Finished);
+
+    // Weeks missed while paused are skipped rather than charged all at once.
+    db::set_recurring_status(&conn, 2, internet, RecurringStatus::Active).unwrap();
+    let resumed = db::get_recurring_expense(&conn, internet).unwrap();
+    let in_next_week: bool = conn.query_row(
+        "SELECT ?1 >= date('now') AND ?1 < date('now', '+7 days')",
+        [resumed.next_date()],
+        |row| row.get(0),
+    ).unwrap();
+    assert!(in_next_week);
+    assert_eq!(resumed.split(), &Split::equal(vec![1, 2]));
+
+    db::set_recurring_status(&conn, 1, gym, RecurringStatus::Cancelled).unwrap();
+    assert_eq!(db::set_recurring_status(&conn, 2, gym, RecurringStatus::Active).unwrap_err(), "Повтарящият се разход вече е приключил.");
+
+    let listed = db::get_group_recurring_expenses(&conn, 1, group_id).unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].description(), "Интернет");
+}
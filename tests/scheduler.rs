@@ -0,0 +1,59 @@
+use std::time::{Duration, Instant};
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::money::Money;
+use split_money_manager::notification::NotificationKind;
+use split_money_manager::recurring::RecurrenceInterval;
+use split_money_manager::scheduler::{Alert, Scheduler};
+use split_money_manager::split::Split;
+
+mod common;
+
+#[test]
+fn scheduler_runs_due_jobs() {
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria")]);
+    let group_id = db::create_group(&conn, "Flat", 1, &[1, 2]).unwrap();
+
+    conn.execute(
+        "INSERT INTO debts (from_id, to_id, amount, group_id, due_date, description, currency)
+         VALUES (2, 1, 1500, ?1, '2025-01-01', 'Вечеря', 'EUR')",
+        [group_id],
+    ).unwrap();
+    conn.execute(
+        "INSERT INTO debts (from_id, to_id, amount, group_id, due_date, description, currency)
+         VALUES (1, 2, 700, ?1, date('now', '+1 day'), 'Кино', 'EUR')",
+        [group_id],
+    ).unwrap();
+    db::add_recurring_expense(
+        &conn, 1, group_id, Money::from_major(20), None, "Интернет",
+        RecurrenceInterval::Monthly, "2025-03-01", Some("2025-03-31"), &Split::default(),
+    ).unwrap();
+
+    let start = Instant::now();
+    let mut scheduler = Scheduler::with_default_jobs(start);
+    assert_eq!(scheduler.time_until_next(start), Duration::ZERO);
+
+    let alerts = scheduler.run_due(&conn, start);
+    let recorded = "Записан е повтарящ се разход „Интернет“ от 20.00 € със срок 2025-03-01".to_string();
+    assert_eq!(alerts, vec![
+        Alert::new(1, recorded.clone()),
+        Alert::new(2, recorded),
+        Alert::new(2, "Имате просрочен дълг от 25.00 € със срок 2025-03-01".to_string()),
+        Alert::new(1, "Утре е срокът на дълга ви от 7.00 € към Maria".to_string()),
+    ]);
+
+    let inbox = db::get_user_notifications(&conn, 1).unwrap();
+    let recorded = inbox.iter().find(|note| note.message().starts_with("Записан е повтарящ се разход")).unwrap();
+    assert_eq!((recorded.kind(), recorded.group_id()), (NotificationKind::ExpenseAdded, Some(group_id)));
+
+    assert_eq!(scheduler.time_until_next(start), Duration::from_secs(10 * 60));
+    assert!(scheduler.run_due(&conn, start).is_empty());
+    assert!(scheduler.run_due(&conn, start + Duration::from_secs(10 * 60)).is_empty());
+    assert_eq!(
+        scheduler.time_until_next(start + Duration::from_secs(10 * 60)),
+        Duration::from_secs(10 * 60)
+    );
+}
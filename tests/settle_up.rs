@@ -0,0 +1,65 @@
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::money::Money;
+use split_money_manager::payment::PaymentMethod;
+
+mod common;
+
+#[test]
+fn settle_up_across_groups() {
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria"), (3, "Georgi")]);
+    conn.execute("INSERT INTO groups (id, name, owner_id) VALUES (1, 'Flat', 1)", []).unwrap();
+    conn.execute("INSERT INTO groups (id, name, owner_id) VALUES (2, 'Ski trip', 2)", []).unwrap();
+    conn.execute("INSERT INTO debts (from_id, to_id, amount, group_id, due_date, description) VALUES (1, 2, 5000, 1, '2099-01-01', 'Наем')", []).unwrap();
+    conn.execute("INSERT INTO debts (from_id, to_id, amount, group_id, due_date, description) VALUES (2, 1, 3000, 2, '2099-01-01', 'Лифт карта')", []).unwrap();
+    conn.execute("INSERT INTO debts (from_id, to_id, amount, group_id, due_date, description) VALUES (3, 2, 1000, 1, '2099-01-01', 'Ток')", []).unwrap();
+
+    let balances = db::get_counterparty_balances(&conn, 2).unwrap();
+    assert_eq!(balances.len(), 2);
+    assert_eq!((balances[0].username(), balances[0].amount()), ("Georgi", Money::from_major(10)));
+    assert_eq!((balances[1].username(), balances[1].amount()), ("Ivan", Money::from_major(20)));
+
+    db::record_partial_payment(&conn, 1, 1, Money::from_major(10), PaymentMethod::Cash, "2026-01-10").unwrap();
+    assert_eq!(
+        db::settle_up_with(&conn, 1, 2).unwrap_err(),
+        "Има плащания, които чакат потвърждение. Потвърдете ги, преди да уредите сметките."
+    );
+    db::confirm_partial_payment(&conn, 2, 1).unwrap();
+
+    let first = db::settle_up_with(&conn, 1, 2).unwrap();
+    assert_eq!(first, "Потвърдено. Очаква се другата страна да уреди сметките.");
+
+    let second = db::settle_up_with(&conn, 2, 1).unwrap();
+    assert_eq!(second, "Всички сметки с този потребител са уредени.");
+
+    let open: i32 = conn.query_row("SELECT COUNT(*) FROM debts WHERE settled = 0", [], |row| row.get(0)).unwrap();
+    assert_eq!(open, 1);
+
+    // The offset debts stay as they were, and only the 10 left over was paid.
+    let closed: Vec<(Money, Money, bool)> = conn
+        .prepare("SELECT amount, paid, netted FROM debts WHERE id IN (1, 2) ORDER BY id")
+        .unwrap()
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .unwrap()
+        .map(|e| e.unwrap())
+        .collect();
+    assert_eq!(closed, vec![
+        (Money::from_major(50), Money::from_major(10), true),
+        (Money::from_major(30), Money::ZERO, true),
+    ]);
+
+    let payments: Vec<(String, String, Money)> = db::get_user_payments(&conn, 2).unwrap()
+        .iter()
+        .map(|p| (p.from_name().to_string(), p.to_name().to_string(), p.amount()))
+        .collect();
+    assert_eq!(payments, vec![
+        ("Ivan".to_string(), "Maria".to_string(), Money::from_major(10)),
+        ("Ivan".to_string(), "Maria".to_string(), Money::from_major(10)),
+    ]);
+
+    assert_eq!(db::get_counterparty_balances(&conn, 1).unwrap_err(), "Нямате неуредени сметки.");
+    assert_eq!(db::settle_up_with(&conn, 1, 2).unwrap_err(), "Нямате неуредени сметки с този потребител.");
+}
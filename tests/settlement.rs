@@ -0,0 +1,89 @@
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::money::Money;
+
+mod common;
+
+#[test]
+fn group_settlement() {
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria"), (3, "Georgi"), (4, "Petar")]);
+    conn.execute("INSERT INTO groups (id, name, owner_id) VALUES (1, 'Flat', 1)", []).unwrap();
+    for id in 1..=4 {
+        conn.execute("INSERT INTO group_members (group_id, user_id) VALUES (1, ?1)", [id]).unwrap();
+    }
+
+    for (from_id, to_id, amount) in [(1, 2, 1000), (2, 3, 1000), (3, 1, 1000), (4, 2, 500), (3, 4, 200)] {
+        conn.execute(
+            "INSERT INTO debts (from_id, to_id, amount, group_id, due_date, description) VALUES (?1, ?2, ?3, 1, '2026-01-01', 'Сметки')",
+            [from_id, to_id, amount],
+        ).unwrap();
+        let debt_id = conn.last_insert_rowid() as i32;
+        conn.execute(
+            "INSERT INTO expenses (id, group_id, payer_id, amount, description, due_date, split_mode) VALUES (?1, 1, ?2, ?3, 'Сметки', '2026-01-01', 'exact')",
+            [debt_id, to_id, amount],
+        ).unwrap();
+        conn.execute("INSERT INTO debt_expenses (debt_id, expense_id) VALUES (?1, ?1)", [debt_id]).unwrap();
+    }
+
+    let balances = db::get_group_balances(&conn, 1, 1).unwrap();
+    let amounts: Vec<Money> = balances.iter().map(|b| b.amount()).collect();
+    assert_eq!(amounts, vec![Money::ZERO, Money::from_minor(500), Money::from_minor(-200), Money::from_minor(-300)]);
+
+    let transfers = db::suggest_settlement(&conn, 1, 1).unwrap();
+    assert_eq!(transfers.len(), 2);
+    assert_eq!((transfers[0].from_name(), transfers[0].to_name(), transfers[0].amount()), ("Petar", "Maria", Money::from_minor(300)));
+    assert_eq!((transfers[1].from_name(), transfers[1].to_name(), transfers[1].amount()), ("Georgi", "Maria", Money::from_minor(200)));
+
+    assert_eq!(db::apply_settlement(&conn, 9, 1).unwrap_err(), "Не сте член на тази група.");
+    assert_eq!(db::get_group_balances(&conn, 9, 1).unwrap_err(), "Не сте член на тази група.");
+    assert_eq!(db::apply_settlement(&conn, 2, 1).unwrap_err(), "Само собственикът или администратор може да променя групата.");
+    conn.execute("UPDATE groups SET archived_at = CURRENT_TIMESTAMP WHERE id = 1", []).unwrap();
+    assert_eq!(db::apply_settlement(&conn, 1, 1).unwrap_err(), "Групата е архивирана. Възстановете я, за да променяте разходите.");
+    conn.execute("UPDATE groups SET archived_at = NULL WHERE id = 1", []).unwrap();
+    db::apply_settlement(&conn, 1, 1).unwrap();
+
+    let debts: Vec<(i32, i32, Money)> = conn
+        .prepare("SELECT from_id, to_id, amount FROM debts WHERE settled = 0 ORDER BY from_id")
+        .unwrap()
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .unwrap()
+        .map(|e| e.unwrap())
+        .collect();
+    assert_eq!(debts, vec![(3, 2, Money::from_minor(200)), (4, 2, Money::from_minor(300))]);
+
+    // The old debts stay as they were, closed as netted.
+    let closed: Vec<(i32, bool)> = conn
+        .prepare("SELECT amount, netted FROM debts WHERE settled = 1 ORDER BY id")
+        .unwrap()
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .map(|e| e.unwrap())
+        .collect();
+    assert_eq!(closed, vec![(1000, true), (1000, true), (1000, true), (500, true), (200, true)]);
+
+    // Each transfer only carries the expenses behind the debts of its two people.
+    let linked = |from_id: i32| -> Vec<i32> {
+        conn.prepare(
+            "SELECT de.expense_id FROM debt_expenses de JOIN debts d ON d.id = de.debt_id
+             WHERE d.settled = 0 AND d.from_id = ?1 ORDER BY de.expense_id"
+        )
+            .unwrap()
+            .query_map([from_id], |row| row.get(0))
+            .unwrap()
+            .map(|e| e.unwrap())
+            .collect()
+    };
+    assert_eq!(linked(4), vec![1, 2, 4, 5]);
+    assert_eq!(linked(3), vec![1, 2, 3, 4, 5]);
+    let replacements: i32 = conn.query_row("SELECT COUNT(*) FROM debt_replacements", [], |row| row.get(0)).unwrap();
+    assert_eq!(replacements, 9);
+
+    conn.execute("UPDATE debts SET confirmed_by_debtor = 1 WHERE from_id = 3", []).unwrap();
+    assert_eq!(
+        db::apply_settlement(&conn, 1, 1).unwrap_err(),
+        "Има започнати плащания в групата. Приключете ги преди оптимизиране."
+    );
+}
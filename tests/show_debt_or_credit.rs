@@ -1,5 +1,6 @@
 use rusqlite::Connection;
 use split_money_manager::*;
+use split_money_manager::money::Money;
 
 #[test]
 
@@ -29,11 +30,24 @@ fn get_user_debt_or_credit() {
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             from_id INTEGER NOT NULL,
             to_id INTEGER NOT NULL,
-            amount REAL NOT NULL,
+            amount INTEGER NOT NULL,
             group_id INTEGER NOT NULL,
             due_date TEXT NOT NULL,
             description TEXT NOT NULL,
-            settled BOOLEAN DEFAULT 0
+            settled BOOLEAN DEFAULT 0,
+            paid INTEGER NOT NULL DEFAULT 0,
+            currency TEXT NOT NULL DEFAULT 'BGN'
+        );
+
+        CREATE TABLE payments (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            debt_id INTEGER NOT NULL,
+            amount INTEGER NOT NULL,
+            method TEXT NOT NULL DEFAULT 'other',
+            paid_on TEXT NOT NULL,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+            confirmed_by_debtor BOOLEAN DEFAULT 0,
+            confirmed_by_creditor BOOLEAN DEFAULT 0
         );
 
     ").unwrap();
@@ -43,12 +57,12 @@ fn get_user_debt_or_credit() {
     conn.execute("INSERT INTO groups (id, name, owner_id) VALUES (1, 'gr1', 1)", []).unwrap();
     conn.execute("INSERT INTO group_members (group_id, user_id) VALUES (1, 1)", []).unwrap();
     conn.execute("INSERT INTO group_members (group_id, user_id) VALUES (1, 2)", []).unwrap();
-    conn.execute("INSERT INTO debts (id, from_id, to_id, amount, group_id, due_date, description) VALUES(1, 1, 2, 100, 1, '2026-01-01', 'Балони за рожден ден')", [] ).unwrap();
+    conn.execute("INSERT INTO debts (id, from_id, to_id, amount, group_id, due_date, description) VALUES(1, 1, 2, 10000, 1, '2026-01-01', 'Балони за рожден ден')", [] ).unwrap();
 
     let result = db::get_user_debts_or_credits(&conn, 1, true).unwrap();
     assert_eq!(result[0].username(), "Maria");
     assert_eq!(result[0].group_name(), "gr1");
     assert_eq!(result[0].due_date(), "2026-01-01");
-    assert_eq!(result[0].amount(), 100.0);
+    assert_eq!(result[0].amount(), Money::from_major(100));
     assert_eq!(result[0].description(), "Балони за рожден ден");
 }
\ No newline at end of file
@@ -16,12 +16,15 @@ fn get_user_groups() {
         CREATE TABLE groups (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             name TEXT NOT NULL,
-            owner_id INTEGER NOT NULL
+            owner_id INTEGER NOT NULL,
+            currency TEXT NOT NULL DEFAULT 'BGN',
+            archived_at TEXT
         );
 
         CREATE TABLE group_members (
             group_id INTEGER NOT NULL,
             user_id INTEGER NOT NULL,
+            role TEXT NOT NULL DEFAULT 'member',
             PRIMARY KEY (group_id, user_id)
         );
     ").unwrap();
@@ -0,0 +1,41 @@
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::money::Money;
+use split_money_manager::split::Split;
+
+mod common;
+
+#[test]
+fn split_expense_remainder() {
+    let shares = split::split_equal(Money::from_major(100), &[3, 1, 2], 2);
+    assert_eq!(shares, vec![
+        (1, Money::from_minor(3333)),
+        (2, Money::from_minor(3334)),
+        (3, Money::from_minor(3333)),
+    ]);
+
+    let shares = split::split_equal(Money::from_minor(1002), &[1, 2, 3, 4], 9);
+    assert_eq!(shares.iter().map(|&(_, share)| share).sum::<Money>(), Money::from_minor(1002));
+    assert_eq!(shares[0].1, Money::from_minor(251));
+    assert_eq!(shares[1].1, Money::from_minor(251));
+    assert_eq!(shares[2].1, Money::from_minor(250));
+
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria"), (3, "Georgi"), (4, "Petar")]);
+    conn.execute("INSERT INTO groups (id, name, owner_id) VALUES (1, 'gr1', 1)", []).unwrap();
+
+    conn.execute("INSERT INTO group_members (group_id, user_id) VALUES (1, 1)", []).unwrap();
+    conn.execute("INSERT INTO group_members (group_id, user_id) VALUES (1, 2)", []).unwrap();
+    conn.execute("INSERT INTO group_members (group_id, user_id) VALUES (1, 3)", []).unwrap();
+
+    db::add_expenses(&conn, 1, 1, Money::from_major(100), None, "Вечеря", "2026-01-01", &Split::default()).unwrap();
+
+    let owed: Money = conn.query_row(
+        "SELECT SUM(amount) FROM debts WHERE to_id = 1",
+        [],
+        |row| row.get(0),
+    ).unwrap();
+    assert_eq!(owed, Money::from_minor(6666));
+}
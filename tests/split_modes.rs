@@ -0,0 +1,54 @@
+use rusqlite::Connection;
+use split_money_manager::*;
+use split_money_manager::money::Money;
+use split_money_manager::split::Split;
+
+mod common;
+
+#[test]
+fn split_modes() {
+    let percentage = Split::percentage(vec![(1, 6000), (2, 4000)]);
+    assert_eq!(percentage.allocate(Money::from_minor(1001), 1).unwrap(), vec![
+        (1, Money::from_minor(601)),
+        (2, Money::from_minor(400)),
+    ]);
+    assert_eq!(
+        Split::percentage(vec![(1, 6000), (2, 3000)]).allocate(Money::from_major(10), 1).unwrap_err(),
+        "Процентите трябва да са общо 100%."
+    );
+
+    let shares = Split::shares(vec![(1, 2), (2, 1)]);
+    assert_eq!(shares.allocate(Money::from_major(100), 2).unwrap(), vec![
+        (1, Money::from_minor(6667)),
+        (2, Money::from_minor(3333)),
+    ]);
+
+    let exact = Split::exact(vec![(1, Money::from_major(30)), (2, Money::from_major(60))]);
+    assert_eq!(
+        exact.allocate(Money::from_major(100), 1).unwrap_err(),
+        "Сборът на сумите (90.00) не съвпада с общата сума (100.00)."
+    );
+
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_schema(&conn).unwrap();
+
+    common::insert_users(&conn, &[(1, "Ivan"), (2, "Maria"), (3, "Georgi"), (4, "Petar")]);
+    conn.execute("INSERT INTO groups (id, name, owner_id) VALUES (1, 'gr1', 1)", []).unwrap();
+
+    conn.execute("INSERT INTO group_members (group_id, user_id) VALUES (1, 1)", []).unwrap();
+    conn.execute("INSERT INTO group_members (group_id, user_id) VALUES (1, 2)", []).unwrap();
+
+    let split = Split::exact(vec![(1, Money::from_major(20)), (2, Money::from_major(80))]);
+    db::add_expenses(&conn, 1, 1, Money::from_major(100), None, "Хотел", "2026-01-01", &split).unwrap();
+
+    let amount: Money = conn.query_row(
+        "SELECT amount FROM debts WHERE from_id = 2 AND to_id = 1",
+        [],
+        |row| row.get(0),
+    ).unwrap();
+    assert_eq!(amount, Money::from_major(80));
+
+    let outsider = Split::shares(vec![(1, 1), (3, 1)]);
+    let error = db::add_expenses(&conn, 1, 1, Money::from_major(100), None, "Хотел", "2026-01-01", &outsider).unwrap_err();
+    assert_eq!(error, "Участникът не е член на групата.");
+}